@@ -0,0 +1,7448 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+//use serde::de;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::OpenOptions;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Suit {
+    Hearts,
+    Diamonds,
+    Clubs,
+    Spades,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rank {
+    Ace,
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Nine,
+    Ten,
+    Jack,
+    Queen,
+    King,
+}
+
+impl Rank {
+    const ALL: [Rank; 13] = [
+        Rank::Ace, Rank::Two, Rank::Three, Rank::Four, Rank::Five, Rank::Six, Rank::Seven,
+        Rank::Eight, Rank::Nine, Rank::Ten, Rank::Jack, Rank::Queen, Rank::King,
+    ];
+
+    // Position in the suit, 1 for Ace through 13 for King
+    pub fn number(&self) -> u8 {
+        *self as u8 + 1
+    }
+}
+
+// ASCII suit letters are for logs and terminals that mangle the Unicode symbols
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CardStyle {
+    Unicode,
+    Ascii,
+}
+
+// Saved as its ASCII code, "10H" or "QS", so a saved shoe reads the way the logs do
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Card {
+    pub rank: Rank,
+    pub suit: Suit,
+}
+
+impl From<Card> for String {
+    fn from(card: Card) -> String {
+        card.format(CardStyle::Ascii)
+    }
+}
+
+impl TryFrom<String> for Card {
+    type Error = String;
+
+    fn try_from(code: String) -> Result<Card, String> {
+        Card::from_code(&code).ok_or_else(|| format!("{} is not a card", code))
+    }
+}
+
+impl Card {
+    // Reads back the ASCII form format gives
+    pub fn from_code(code: &str) -> Option<Card> {
+        let (rank, suit) = code.split_at(code.len().checked_sub(1)?);
+        let suit = match suit {
+            "H" => Suit::Hearts,
+            "D" => Suit::Diamonds,
+            "C" => Suit::Clubs,
+            "S" => Suit::Spades,
+            _ => return None,
+        };
+        let rank = match rank {
+            "A" => Rank::Ace,
+            "J" => Rank::Jack,
+            "Q" => Rank::Queen,
+            "K" => Rank::King,
+            number => match number.parse::<usize>().ok()? {
+                number @ 2..=10 => Rank::ALL[number - 1],
+                _ => return None,
+            },
+        };
+        Some(Card { rank, suit })
+    }
+
+    pub fn value(&self) -> u8 {
+        match self.rank {
+            Rank::Ace => 11,
+            Rank::Ten | Rank::Jack | Rank::Queen | Rank::King => 10,
+            rank => rank.number(),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.format(CardStyle::Unicode)
+    }
+
+    pub fn format(&self, style: CardStyle) -> String {
+        let rank_str = match self.rank {
+            Rank::Ace => "A".to_string(),
+            Rank::Jack => "J".to_string(),
+            Rank::Queen => "Q".to_string(),
+            Rank::King => "K".to_string(),
+            rank => rank.number().to_string(),
+        };
+        let suit_str = match (style, self.suit) {
+            (CardStyle::Unicode, Suit::Hearts) => "♥",
+            (CardStyle::Unicode, Suit::Diamonds) => "♦",
+            (CardStyle::Unicode, Suit::Clubs) => "♣",
+            (CardStyle::Unicode, Suit::Spades) => "♠",
+            (CardStyle::Ascii, Suit::Hearts) => "H",
+            (CardStyle::Ascii, Suit::Diamonds) => "D",
+            (CardStyle::Ascii, Suit::Clubs) => "C",
+            (CardStyle::Ascii, Suit::Spades) => "S",
+        };
+        format!("{}{}", rank_str, suit_str)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Hand {
+    pub cards: Vec<Card>,
+    pub doubled: bool,
+    // Made by splitting a pair: a two-card 21 is then an ordinary 21, not a blackjack
+    pub split: bool,
+    pub first_action: bool,
+    pub live: bool,
+    pub stood: bool,
+}
+impl Default for Hand {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hand {
+    pub fn new() -> Self {
+        let mut hand = Self { cards: Vec::new(), doubled: false, split: false, first_action: true, live: true, stood: false };
+        hand.reset_flags();
+        hand
+    }
+
+    // Puts the hand back at the start of play: first decision pending, nothing doubled or stood.
+    // The split marker and cards are kept, so a hand made by splitting can be reset and replayed.
+    pub fn reset_flags(&mut self) {
+        self.doubled = false;
+        self.first_action = true;
+        self.live = true;
+        self.stood = false;
+    }
+
+    pub fn add_card(&mut self, card: Card) {
+        self.cards.push(card);
+    }
+
+    // Counts every ace as 1, then one of them as 11 if that doesn't bust; total() and
+    // is_soft() both read this, so a hand is only ever soft at the total it reports
+    fn best_total(&self) -> (u8, bool) {
+        let hard = self.cards.iter().map(|card| if card.rank == Rank::Ace { 1 } else { card.value() }).sum();
+        let ace = self.cards.iter().any(|card| card.rank == Rank::Ace);
+        best_total(hard, ace)
+    }
+
+    pub fn total(&self) -> u8 {
+        self.best_total().0
+    }
+
+    pub fn is_blackjack(&self) -> bool {
+        !self.split && self.cards.len() == 2 && self.total() == 21
+    }
+
+    pub fn is_pair(&self) -> bool {
+        self.cards.len() == 2 && self.cards[0].rank == self.cards[1].rank
+    }
+
+    pub fn is_busted(&self) -> bool {
+        self.total() > 21
+    }
+
+    pub fn is_soft(&self) -> bool {
+        self.best_total().1
+    }
+
+    pub fn display(&self, style: CardStyle) -> String {
+        self.cards.iter()
+            .map(|c| c.format(style))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+}
+
+// Fisher-Yates is a perfect shuffle; Riffle models N imperfect human riffles, which leave
+// exploitable structure for shuffle-tracking experiments
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ShuffleMethod {
+    FisherYates,
+    Riffle(u8),
+}
+
+// Where deal_card takes from. Anything but Top is a cheating dealer, for forcing
+// particular hands when analysing edge cases
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DealPosition {
+    Top,
+    Bottom,
+    // This many cards below the top, or the top once the shoe is shallower than that
+    Fixed(usize),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deck {
+    pub cards: Vec<Card>,
+    // Every card dealt since this shoe was built, in order
+    pub dealt: Vec<Card>,
+    pub shuffle_method: ShuffleMethod,
+    pub last_permutation: Vec<usize>,
+    pub deal_position: DealPosition,
+}
+
+fn fisher_yates<T>(items: &mut [T], rng: &mut impl Rng) {
+    for i in (1..items.len()).rev() {
+        let j = rng.random_range(0..=i);
+        items.swap(i, j);
+    }
+}
+
+// Gilbert-Shannon-Reeds riffle: a binomial cut, then items drop from each half with
+// probability proportional to how many that half has left
+fn riffle<T: Copy>(items: &mut Vec<T>, rng: &mut impl Rng) {
+    let cut = (0..items.len()).filter(|_| rng.random_bool(0.5)).count();
+    let (left, right) = items.split_at(cut);
+    let mut merged = Vec::with_capacity(items.len());
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() || j < right.len() {
+        let left_remaining = left.len() - i;
+        let right_remaining = right.len() - j;
+        if rng.random_range(0..left_remaining + right_remaining) < left_remaining {
+            merged.push(left[i]);
+            i += 1;
+        } else {
+            merged.push(right[j]);
+            j += 1;
+        }
+    }
+    *items = merged;
+}
+
+impl Deck {
+    pub fn new(count: u8) -> Deck {
+        let mut cards = Vec::new();
+        for _ in 0..count {    
+            for &suit in &[Suit::Hearts, Suit::Diamonds, Suit::Clubs, Suit::Spades] {
+                for rank in Rank::ALL {
+                    cards.push(Card { rank, suit });
+                }
+            }
+        }
+
+        Deck { cards, dealt: Vec::new(), shuffle_method: ShuffleMethod::FisherYates, last_permutation: Vec::new(), deal_position: DealPosition::Top }
+    }
+
+    // Builds an unordered shoe from per-rank counts, Ace first through King
+    pub fn from_composition(counts: [u8; 13]) -> Deck {
+        let suits = [Suit::Hearts, Suit::Diamonds, Suit::Clubs, Suit::Spades];
+        let mut cards = Vec::new();
+        for (&rank, &count) in Rank::ALL.iter().zip(counts.iter()) {
+            for n in 0..count as usize {
+                cards.push(Card { rank, suit: suits[n % suits.len()] });
+            }
+        }
+
+        Deck { cards, dealt: Vec::new(), shuffle_method: ShuffleMethod::FisherYates, last_permutation: Vec::new(), deal_position: DealPosition::Top }
+    }
+
+    // Rebuilds a shoe from its cards listed top first, the order the shuffle log writes
+    pub fn from_cards(cards: &[Card]) -> Deck {
+        let cards = cards.iter().rev().copied().collect();
+        Deck { cards, dealt: Vec::new(), shuffle_method: ShuffleMethod::FisherYates, last_permutation: Vec::new(), deal_position: DealPosition::Top }
+    }
+
+    pub fn order_from_top(&self, style: CardStyle) -> String {
+        self.cards.iter().rev().map(|card| card.format(style)).collect::<Vec<_>>().join(" ")
+    }
+
+    // Shuffles an index order rather than the cards themselves, so the permutation applied
+    // can be kept for shuffle-tracking: after shuffling, cards[i] == previous[last_permutation[i]]
+    pub fn shuffle(&mut self, rng: &mut impl Rng) {
+        let mut order: Vec<usize> = (0..self.cards.len()).collect();
+        match self.shuffle_method {
+            ShuffleMethod::FisherYates => fisher_yates(&mut order, rng),
+            ShuffleMethod::Riffle(passes) => {
+                for _ in 0..passes {
+                    riffle(&mut order, rng);
+                }
+            }
+        }
+        self.cards = order.iter().map(|&i| self.cards[i]).collect();
+        self.last_permutation = order;
+    }
+
+    // Where a slug of pre-shuffle positions ended up, counted in cards from the top of the shoe
+    pub fn slug_depths(&self, slug: std::ops::Range<usize>) -> Vec<usize> {
+        let mut depths: Vec<usize> = self.last_permutation.iter()
+            .enumerate()
+            .filter(|(_, original)| slug.contains(original))
+            .map(|(position, _)| self.last_permutation.len() - 1 - position)
+            .collect();
+        depths.sort_unstable();
+        depths
+    }
+
+    // Moves this fraction of the shoe from the top to the bottom, as a player's cut does.
+    // The recorded permutation is rotated too so slug depths stay accurate.
+    pub fn cut(&mut self, fraction: f64) {
+        let count = (self.cards.len() as f64 * fraction.clamp(0.0, 1.0)).round() as usize;
+        self.cards.rotate_right(count);
+        if self.last_permutation.len() == self.cards.len() {
+            self.last_permutation.rotate_right(count);
+        }
+    }
+
+    // The top of the shoe is the end of `cards`
+    pub fn deal_card(&mut self) -> Option<Card> {
+        let card = match self.deal_position {
+            DealPosition::Top => self.cards.pop()?,
+            DealPosition::Bottom if !self.cards.is_empty() => self.cards.remove(0),
+            DealPosition::Fixed(depth) if depth < self.cards.len() => self.cards.remove(self.cards.len() - 1 - depth),
+            _ => self.cards.pop()?,
+        };
+        self.dealt.push(card);
+        Some(card)
+    }
+}
+
+// Skewed shoes for watching how strategies react to rich or poor decks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShoePreset {
+    Standard,
+    TenRich,
+    TenPoor,
+}
+
+impl ShoePreset {
+    pub const ALL: [ShoePreset; 3] = [ShoePreset::Standard, ShoePreset::TenRich, ShoePreset::TenPoor];
+
+    pub fn label(&self) -> &str {
+        match self {
+            ShoePreset::Standard => "Standard",
+            ShoePreset::TenRich => "Ten-rich (double tens)",
+            ShoePreset::TenPoor => "Ten-poor (half tens)",
+        }
+    }
+
+    fn composition(&self, decks: u8) -> [u8; 13] {
+        let per_rank = decks * 4;
+        let mut counts = [per_rank; 13];
+        for tens in &mut counts[9..] {
+            match self {
+                ShoePreset::Standard => {}
+                ShoePreset::TenRich => *tens = per_rank * 2,
+                ShoePreset::TenPoor => *tens = per_rank / 2,
+            }
+        }
+        counts
+    }
+
+    fn build_deck(&self, decks: u8) -> Deck {
+        match self {
+            ShoePreset::Standard => Deck::new(decks),
+            _ => Deck::from_composition(self.composition(decks)),
+        }
+    }
+}
+
+// When the dealer draws: below stand_total always, and on a soft stand_total if hit_soft
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DealerRule {
+    pub stand_total: u8,
+    pub hit_soft: bool,
+}
+
+impl DealerRule {
+    fn should_hit(&self, hand: &Hand) -> bool {
+        self.should_hit_total(hand.total(), hand.is_soft())
+    }
+
+    fn should_hit_total(&self, total: u8, soft: bool) -> bool {
+        total < self.stand_total || (total == self.stand_total && self.hit_soft && soft)
+    }
+
+    fn describe(&self) -> String {
+        if self.hit_soft {
+            format!("Dealer hits soft {}", self.stand_total)
+        } else {
+            format!("Dealer stands on all {}s", self.stand_total)
+        }
+    }
+}
+
+// Which two-card hands may be doubled
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DoubleRule {
+    AnyTwo,
+    NineToEleven,
+    TenToEleven,
+}
+
+impl DoubleRule {
+    pub const ALL: [DoubleRule; 3] = [DoubleRule::AnyTwo, DoubleRule::NineToEleven, DoubleRule::TenToEleven];
+
+    fn allows(&self, hand: &Hand) -> bool {
+        let total = hand.total();
+        match self {
+            DoubleRule::AnyTwo => true,
+            DoubleRule::NineToEleven => !hand.is_soft() && (9..=11).contains(&total),
+            DoubleRule::TenToEleven => !hand.is_soft() && (10..=11).contains(&total),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DoubleRule::AnyTwo => "Double any two cards",
+            DoubleRule::NineToEleven => "Double on hard 9-11",
+            DoubleRule::TenToEleven => "Double on hard 10-11",
+        }
+    }
+}
+
+// Card tags for the count the counting strategy keeps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CountSystem {
+    HiLo,
+    // Unbalanced: Hi-Lo with the red sevens counted +1 too
+    Red7,
+    // Knock-Out, unbalanced: Hi-Lo with every seven counted +1
+    KO,
+}
+
+impl CountSystem {
+    pub const ALL: [CountSystem; 3] = [CountSystem::HiLo, CountSystem::Red7, CountSystem::KO];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CountSystem::HiLo => "Hi-Lo",
+            CountSystem::Red7 => "Red 7",
+            CountSystem::KO => "KO",
+        }
+    }
+
+    // 2-6 count +1, 7-9 nothing, tens and aces -1; Red 7 also counts red sevens +1, KO all sevens
+    fn tag(&self, card: &Card) -> i32 {
+        match (self, card.value()) {
+            (_, 2..=6) => 1,
+            (CountSystem::Red7, 7) if matches!(card.suit, Suit::Hearts | Suit::Diamonds) => 1,
+            (CountSystem::KO, 7) => 1,
+            (_, 7..=9) => 0,
+            _ => -1,
+        }
+    }
+
+    // What the count gains a deck over the shoe: nothing for a balanced count
+    fn gain_per_deck(&self) -> i32 {
+        match self {
+            CountSystem::HiLo => 0,
+            CountSystem::Red7 => 2,
+            CountSystem::KO => 4,
+        }
+    }
+
+    // The unbalanced counts start their gain a deck down, so a full shoe counts back up to
+    // zero for Red 7 and to KO's documented pivot of +4
+    fn initial_running_count(&self, shoe_cards: usize) -> i32 {
+        let decks = (shoe_cards as f64 / 52.0).round() as i32;
+        self.end_of_shoe_count() - self.gain_per_deck() * decks
+    }
+
+    fn end_of_shoe_count(&self) -> i32 {
+        match self {
+            CountSystem::KO => 4,
+            _ => 0,
+        }
+    }
+
+    // An unbalanced count runs its gain a deck below its end-of-shoe count per deck
+    // remaining in a neutral shoe; adding the gain back reads a true count of zero there
+    fn true_count(&self, running_count: i32, decks: f64) -> f64 {
+        (running_count - self.end_of_shoe_count()) as f64 / decks + self.gain_per_deck() as f64
+    }
+
+    // Where the count ends once a shoe of whole decks has been seen
+    pub fn full_shoe_running_count(&self, shoe_cards: usize) -> i32 {
+        let decks = (shoe_cards as f64 / 52.0).round() as i32;
+        self.initial_running_count(shoe_cards) + self.gain_per_deck() * decks
+    }
+}
+
+// A count kept beside the player's, fed the same cards, to compare how systems track one shoe
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountObserver {
+    pub system: CountSystem,
+    pub running_count: i32,
+    pub cards_remaining: usize,
+}
+
+impl CountObserver {
+    pub fn new(system: CountSystem, shoe_cards: usize) -> CountObserver {
+        CountObserver { system, running_count: system.initial_running_count(shoe_cards), cards_remaining: shoe_cards }
+    }
+
+    fn observe_card(&mut self, card: &Card) {
+        self.running_count += self.system.tag(card);
+        self.cards_remaining = self.cards_remaining.saturating_sub(1);
+    }
+
+    pub fn true_count(&self) -> f64 {
+        self.system.true_count(self.running_count, (self.cards_remaining as f64 / 52.0).max(0.5))
+    }
+}
+
+// How the counter judges the decks left for the true count: to the card, or by eye from
+// the discard tray to the nearest half or whole deck
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeckEstimation {
+    Exact,
+    HalfDeck,
+    WholeDeck,
+}
+
+impl DeckEstimation {
+    pub const ALL: [DeckEstimation; 3] = [DeckEstimation::Exact, DeckEstimation::HalfDeck, DeckEstimation::WholeDeck];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DeckEstimation::Exact => "Exact decks",
+            DeckEstimation::HalfDeck => "Nearest half deck",
+            DeckEstimation::WholeDeck => "Nearest deck",
+        }
+    }
+
+    pub fn estimate(&self, cards_remaining: usize) -> f64 {
+        let decks = cards_remaining as f64 / 52.0;
+        match self {
+            DeckEstimation::Exact => decks,
+            DeckEstimation::HalfDeck => (decks * 2.0).round() / 2.0,
+            DeckEstimation::WholeDeck => decks.round(),
+        }
+    }
+}
+
+// Whether these cards make up whole standard decks, every card the same number of times
+fn is_whole_decks(cards: &[Card]) -> bool {
+    let mut copies = [0usize; 52];
+    for card in cards {
+        copies[card.rank as usize * 4 + card.suit as usize] += 1;
+    }
+    cards.len().is_multiple_of(52) && copies.iter().all(|&count| count == cards.len() / 52)
+}
+
+// Which strategy the table plays; the simple ones are baselines for what basic strategy is worth
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum StrategyKind {
+    Basic,
+    // Stands on strategy_stand_total
+    StandOn,
+    DealerMimic,
+    NeverBust,
+}
+
+impl StrategyKind {
+    pub const ALL: [StrategyKind; 4] = [StrategyKind::Basic, StrategyKind::StandOn, StrategyKind::DealerMimic, StrategyKind::NeverBust];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StrategyKind::Basic => "Basic strategy",
+            StrategyKind::StandOn => "Stand on a total",
+            StrategyKind::DealerMimic => "Mimic the dealer",
+            StrategyKind::NeverBust => "Never bust",
+        }
+    }
+}
+
+// Whether surrender is offered, and if so whether before the dealer checks for blackjack
+// (early, so it saves half the bet even against one) or only once the dealer is known not
+// to have it (late)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SurrenderRule {
+    None,
+    Early,
+    Late,
+}
+
+impl SurrenderRule {
+    pub const ALL: [SurrenderRule; 3] = [SurrenderRule::None, SurrenderRule::Early, SurrenderRule::Late];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SurrenderRule::None => "No surrender",
+            SurrenderRule::Early => "Early surrender",
+            SurrenderRule::Late => "Late surrender",
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            SurrenderRule::None => "NS",
+            SurrenderRule::Early => "ES",
+            SurrenderRule::Late => "LS",
+        }
+    }
+}
+
+// What a player blackjack pays, per unit bet
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BlackjackPayout {
+    ThreeToTwo,
+    SixToFive,
+    EvenMoney,
+}
+
+impl BlackjackPayout {
+    pub const ALL: [BlackjackPayout; 3] = [BlackjackPayout::ThreeToTwo, BlackjackPayout::SixToFive, BlackjackPayout::EvenMoney];
+
+    fn ratio(&self) -> f64 {
+        match self {
+            BlackjackPayout::ThreeToTwo => 1.5,
+            BlackjackPayout::SixToFive => 1.2,
+            BlackjackPayout::EvenMoney => 1.0,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BlackjackPayout::ThreeToTwo => "3:2",
+            BlackjackPayout::SixToFive => "6:5",
+            BlackjackPayout::EvenMoney => "1:1",
+        }
+    }
+}
+
+// Where the cut card sits: the fraction of the shoe dealt, or the cards left behind it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Penetration {
+    Fraction(f64),
+    CardsCutOff(usize),
+}
+
+impl Penetration {
+    fn cards_cut_off(&self, shoe_cards: usize) -> usize {
+        match self {
+            Penetration::Fraction(fraction) => (shoe_cards as f64 * (1.0 - fraction.clamp(0.0, 1.0))).round() as usize,
+            Penetration::CardsCutOff(cards) => (*cards).min(shoe_cards),
+        }
+    }
+}
+
+// How each hand's stake is chosen; whatever is asked for is then clamped between the table
+// minimum and the bankroll
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BetSizing {
+    // The Bet amount every hand
+    Flat,
+    // This share of the current bankroll, rounded down to the bet increment
+    FixedFraction(f64),
+    // This share of the Kelly bet for the advantage the true count implies, rounded down to
+    // the bet increment; a strategy that doesn't count is bet flat
+    Kelly(f64),
+    // Twice the last bet placed after a losing round, back to the Bet amount after a win
+    Martingale,
+    // The share of the Kelly bet that holds the chance of ever losing half the bankroll to
+    // this figure, whatever the bankroll and the edge; a strategy that doesn't count is bet flat
+    ConstantRisk(f64),
+    // The bet_ramp step for the true count, floored; the table minimum below the first step,
+    // and flat for a strategy that doesn't count or an empty ramp
+    Ramp,
+}
+
+// From this true count up, until the next step, the ramp bets this much
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RampStep {
+    pub true_count: i32,
+    pub bet: f64,
+}
+
+// A ramp for true counts 1 to MAX_RAMP_TRUE_COUNT, each step held as a fixed bet to the
+// chance of ever losing the whole bankroll: at edge e and variance v a fixed bet b goes broke
+// with chance exp(-2eB / vb), so b = 2eB / (v ln(1 / risk)), never more than the Kelly bet.
+// Counts without an edge get the table minimum; the spread is the last step over the first.
+pub fn recommended_ramp(risk_of_ruin: f64, bankroll: f64, edge_per_true_count: f64, house_edge: f64, table_min: f64, bet_increment: f64) -> Vec<RampStep> {
+    let kelly_fraction = (2.0 / (1.0 / risk_of_ruin.clamp(1e-6, 0.5)).ln()).min(1.0);
+    (1..=MAX_RAMP_TRUE_COUNT)
+        .map(|true_count| {
+            let edge = (true_count as f64 * edge_per_true_count - house_edge).max(0.0);
+            let bet = (bankroll * kelly_fraction * edge / KELLY_HAND_VARIANCE / bet_increment).floor() * bet_increment;
+            RampStep { true_count, bet: bet.max(table_min) }
+        })
+        .collect()
+}
+
+// After a round whose bet was cut short of what the progression asked for, by the bankroll
+// share cap or the bankroll itself: carry on from the bet actually placed, or start over
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProgressionShortfall {
+    Continue,
+    Reset,
+}
+
+impl ProgressionShortfall {
+    pub const ALL: [ProgressionShortfall; 2] = [ProgressionShortfall::Continue, ProgressionShortfall::Reset];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProgressionShortfall::Continue => "Continue from the bet placed",
+            ProgressionShortfall::Reset => "Start over",
+        }
+    }
+}
+
+// Where the player sits among the other seats. First base acts first; third base acts last,
+// having seen every other seat's cards, though its bet went down before any were dealt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeatPosition {
+    FirstBase,
+    ThirdBase,
+}
+
+impl SeatPosition {
+    pub const ALL: [SeatPosition; 2] = [SeatPosition::FirstBase, SeatPosition::ThirdBase];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SeatPosition::FirstBase => "First base",
+            SeatPosition::ThirdBase => "Third base",
+        }
+    }
+}
+
+// What happens when the bet asked for is more than the bankroll but the table minimum isn't
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BetShortfallPolicy {
+    TableMinimum,
+    SitOut,
+    AllIn,
+}
+
+impl BetShortfallPolicy {
+    pub const ALL: [BetShortfallPolicy; 3] = [BetShortfallPolicy::TableMinimum, BetShortfallPolicy::SitOut, BetShortfallPolicy::AllIn];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BetShortfallPolicy::TableMinimum => "Bet the minimum",
+            BetShortfallPolicy::SitOut => "Sit out",
+            BetShortfallPolicy::AllIn => "Go all-in",
+        }
+    }
+}
+
+// Order of the initial four cards; it only changes which cards are exposed when
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DealOrder {
+    // Player, dealer, player, dealer
+    Alternating,
+    // Player, player, dealer, dealer
+    PlayerFirst,
+}
+
+// When a split hand gets its second card, which decides which cards land on which hand
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SplitPlayOrder {
+    // Each hand is dealt its second card in its turn, once the hands to its left are played out
+    HandByHand,
+    // Both hands get their second card at the split, before either is played
+    DealAcross,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RulesConfig {
+    pub decks: u8,
+    pub dealer: DealerRule,
+    pub double_rule: DoubleRule,
+    pub surrender: SurrenderRule,
+    // Without a peek (ENHC) a dealer blackjack is only discovered after the player acts.
+    // Some tables only peek under one of the two blackjack upcards.
+    pub peek_on_ace: bool,
+    pub peek_on_ten: bool,
+    pub penetration: Penetration,
+    pub deal_order: DealOrder,
+    pub split_play_order: SplitPlayOrder,
+    // How many times a seat may split, counting resplits: aces and other pairs separately
+    pub max_ace_splits: u8,
+    pub max_pair_splits: u8,
+    // Whether a hand made by splitting may be doubled
+    pub double_after_split: bool,
+    // ENHC sub-rule: a hidden dealer blackjack only takes the original bet, not doubles.
+    // Also covers the upcard a partial-peek dealer doesn't check under.
+    pub original_bets_only: bool,
+    // Free Bet style variant: a dealer bust on exactly 22 pushes instead of paying
+    pub dealer_push_on_22: bool,
+    // For analysis only: let the dealer finish drawing even when no player hand is left to settle
+    pub dealer_plays_out: bool,
+    // Training variant: both dealer cards are dealt face up and shown to the strategy
+    pub dealer_exposes_both: bool,
+    // Double Exposure: equal totals lose, though a player blackjack still wins
+    pub dealer_wins_ties: bool,
+    // Double Exposure pays 1:1, and many carpet joints 6:5
+    pub blackjack_payout: BlackjackPayout,
+    // Double down rescue: a doubled hand may be surrendered after its card is seen
+    pub double_rescue: bool,
+    // What a rescued double costs, in original bets; the usual rule forfeits just the double
+    pub double_rescue_loss: f64,
+}
+
+impl Default for RulesConfig {
+    fn default() -> Self {
+        Self {
+            decks: DEFAULT_DECKS,
+            dealer: DealerRule { stand_total: 17, hit_soft: false },
+            double_rule: DoubleRule::AnyTwo,
+            surrender: SurrenderRule::Late,
+            peek_on_ace: true,
+            peek_on_ten: true,
+            penetration: Penetration::CardsCutOff(MIN_CARDS_TO_DEAL),
+            deal_order: DealOrder::Alternating,
+            split_play_order: SplitPlayOrder::HandByHand,
+            max_ace_splits: 1,
+            max_pair_splits: 3,
+            double_after_split: true,
+            original_bets_only: false,
+            dealer_push_on_22: false,
+            dealer_plays_out: false,
+            dealer_exposes_both: false,
+            dealer_wins_ties: false,
+            blackjack_payout: BlackjackPayout::ThreeToTwo,
+            double_rescue: false,
+            double_rescue_loss: 1.0,
+        }
+    }
+}
+
+impl RulesConfig {
+    // Approximate basic-strategy house edge from published rule-variation effects, relative
+    // to 6 decks, S17, double any two, late surrender, peek and 3:2. Variants without a
+    // well-known figure return None rather than a guess.
+    fn approximate_house_edge(&self) -> Option<f64> {
+        if self.dealer.stand_total != 17 || self.dealer_push_on_22 || self.double_rescue || self.dealer_exposes_both
+            || self.dealer_wins_ties || self.peek_on_ace != self.peek_on_ten || self.surrender == SurrenderRule::Early {
+            return None;
+        }
+        // The base figure is for six decks; fewer decks favour the player
+        let mut edge = 0.0046 + match self.decks {
+            1 => -0.0048,
+            2 => -0.0019,
+            3..=4 => -0.0006,
+            5..=6 => 0.0,
+            _ => 0.0002,
+        };
+        if self.dealer.hit_soft {
+            edge += 0.0022;
+        }
+        if !self.peek_on_ace && !self.original_bets_only {
+            edge += 0.0011;
+        }
+        edge += match self.double_rule {
+            DoubleRule::AnyTwo => 0.0,
+            DoubleRule::NineToEleven => 0.0009,
+            DoubleRule::TenToEleven => 0.0018,
+        };
+        if self.surrender == SurrenderRule::None {
+            edge += 0.0008;
+        }
+        if !self.double_after_split {
+            edge += 0.0014;
+        }
+        edge += match self.blackjack_payout {
+            BlackjackPayout::ThreeToTwo => 0.0,
+            BlackjackPayout::SixToFive => 0.0139,
+            BlackjackPayout::EvenMoney => 0.0227,
+        };
+        Some(edge)
+    }
+
+    // Whether the hole card is checked for blackjack before the player acts
+    fn peeks_under(&self, upcard: &Card) -> bool {
+        match upcard.value() {
+            11 => self.peek_on_ace,
+            10 => self.peek_on_ten,
+            _ => false,
+        }
+    }
+
+    fn shoe_cards(&self) -> usize {
+        self.decks as usize * 52
+    }
+
+    // The cut card, but never so shallow that a hand could run the shoe dry
+    pub fn reshuffle_below(&self, shoe_cards: usize) -> usize {
+        self.penetration.cards_cut_off(shoe_cards).max(MIN_CARDS_TO_DEAL)
+    }
+
+    // Compact rule summary in the usual shorthand, e.g. "6D S17 LS BJ3:2 95%pen"
+    pub fn fingerprint(&self) -> String {
+        let mut parts = vec![
+            format!("{}D", self.decks),
+            format!("{}{}", if self.dealer.hit_soft { "H" } else { "S" }, self.dealer.stand_total),
+            match self.double_rule {
+                DoubleRule::AnyTwo => "DA2",
+                DoubleRule::NineToEleven => "D9",
+                DoubleRule::TenToEleven => "D10",
+            }.to_string(),
+            self.surrender.code().to_string(),
+            format!("BJ{}", self.blackjack_payout.label()),
+        ];
+        match (self.peek_on_ace, self.peek_on_ten) {
+            (true, true) => {}
+            (false, false) => parts.push(if self.original_bets_only { "ENHC-OBO" } else { "ENHC" }.to_string()),
+            (true, false) => parts.push(if self.original_bets_only { "PEEK-A-OBO" } else { "PEEK-A" }.to_string()),
+            (false, true) => parts.push(if self.original_bets_only { "PEEK-T-OBO" } else { "PEEK-T" }.to_string()),
+        }
+        if self.dealer_push_on_22 {
+            parts.push("P22".to_string());
+        }
+        if self.double_rescue {
+            parts.push(format!("DDR{}", self.double_rescue_loss));
+        }
+        if self.dealer_plays_out {
+            parts.push("DPO".to_string());
+        }
+        if self.dealer_exposes_both {
+            parts.push("DE".to_string());
+        }
+        if self.dealer_wins_ties {
+            parts.push("DWT".to_string());
+        }
+        if self.max_ace_splits != 1 {
+            parts.push(format!("SA{}", self.max_ace_splits));
+        }
+        if self.max_pair_splits != 3 {
+            parts.push(format!("SP{}", self.max_pair_splits));
+        }
+        if !self.double_after_split {
+            parts.push("NDAS".to_string());
+        }
+        let shoe_cards = self.shoe_cards();
+        let penetration = (shoe_cards - self.reshuffle_below(shoe_cards)) as f64 / shoe_cards as f64 * 100.0;
+        parts.push(format!("{}%pen", penetration.floor() as u32));
+        parts.join(" ")
+    }
+
+    fn describe(&self) -> Vec<String> {
+        let mut rules = vec![
+            format!("{} {}", self.decks, if self.decks == 1 { "deck" } else { "decks" }),
+            self.dealer.describe(),
+            self.double_rule.label().to_string(),
+            format!("Blackjack pays {}", self.blackjack_payout.label()),
+            self.surrender.label().to_string(),
+            format!("Reshuffle below {} cards", self.reshuffle_below(self.shoe_cards())),
+        ];
+        let peek = match (self.peek_on_ace, self.peek_on_ten) {
+            (true, true) => "Dealer peeks for blackjack",
+            (false, false) => "No hole card",
+            (true, false) => "Dealer peeks under an ace only",
+            (false, true) => "Dealer peeks under a ten only",
+        };
+        if self.original_bets_only && !(self.peek_on_ace && self.peek_on_ten) {
+            rules.push(format!("{}, original bets only", peek));
+        } else {
+            rules.push(peek.to_string());
+        }
+        if self.dealer_push_on_22 {
+            rules.push("Dealer 22 pushes".to_string());
+        }
+        if self.dealer_exposes_both {
+            rules.push("Both dealer cards exposed".to_string());
+        }
+        if self.dealer_wins_ties {
+            rules.push("Dealer wins ties".to_string());
+        }
+        if self.double_rescue {
+            rules.push(format!("Double down rescue for {} bets", self.double_rescue_loss));
+        }
+        rules.push(format!("Split pairs {} times, aces {}", self.max_pair_splits, self.max_ace_splits));
+        if !self.double_after_split {
+            rules.push("No double after split".to_string());
+        }
+        rules
+    }
+
+    // Splits a seat may make of this pair, resplits included
+    fn split_limit(&self, hand: &Hand) -> u8 {
+        if hand.cards[0].rank == Rank::Ace { self.max_ace_splits } else { self.max_pair_splits }
+    }
+}
+
+// Regional rule sets. Every preset deals the simulator's six-deck shoe, so Atlantic City's
+// usual eight decks aren't modelled and it plays like the Strip.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RulePreset {
+    VegasStrip,
+    Downtown,
+    AtlanticCity,
+    European,
+    DoubleExposure,
+}
+
+impl RulePreset {
+    pub const ALL: [RulePreset; 5] = [
+        RulePreset::VegasStrip,
+        RulePreset::Downtown,
+        RulePreset::AtlanticCity,
+        RulePreset::European,
+        RulePreset::DoubleExposure,
+    ];
+
+    pub fn label(&self) -> &str {
+        match self {
+            RulePreset::VegasStrip => "Vegas Strip",
+            RulePreset::Downtown => "Downtown Vegas",
+            RulePreset::AtlanticCity => "Atlantic City",
+            RulePreset::European => "European",
+            RulePreset::DoubleExposure => "Double Exposure",
+        }
+    }
+
+    pub fn rules(&self) -> RulesConfig {
+        let defaults = RulesConfig::default();
+        match self {
+            RulePreset::VegasStrip | RulePreset::AtlanticCity => defaults,
+            RulePreset::Downtown => RulesConfig {
+                dealer: DealerRule { stand_total: 17, hit_soft: true },
+                ..defaults
+            },
+            RulePreset::European => RulesConfig {
+                double_rule: DoubleRule::NineToEleven,
+                peek_on_ace: false,
+                peek_on_ten: false,
+                ..defaults
+            },
+            RulePreset::DoubleExposure => RulesConfig {
+                dealer_exposes_both: true,
+                dealer_wins_ties: true,
+                blackjack_payout: BlackjackPayout::EvenMoney,
+                ..defaults
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum GameResult {
+    PlayerWin,
+    DealerWin,
+    Push,
+    PlayerBlackjack,
+    Surrender,
+    DoubledWin,
+    DoubledLose,
+    // A doubled hand surrendered under the double down rescue rule
+    Rescued,
+    // A blackjack paid 1:1 against an ace before the dealer checks
+    EvenMoney,
+}
+
+// What the engine did, queued so the UI can react without parsing the log
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    HandStarted { game: u32 },
+    CardDealt { card: Card, to_dealer: bool },
+    // The hole card goes out face down and is only seen once revealed
+    HoleCardDealt,
+    HoleCardRevealed { card: Card },
+    HandResolved { result: GameResult, bankroll: f64 },
+    Reshuffled,
+    Bankrupt { bankroll: f64 },
+    IllegalAction { attempted: Action, applied: Action },
+    Milestone { description: String },
+    BetCapped { requested: f64, placed: f64 },
+    SatOut { requested: f64 },
+}
+
+impl std::fmt::Display for GameEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GameEvent::HandStarted { game } => write!(f, "Game {} started", game),
+            GameEvent::CardDealt { card, to_dealer } => {
+                write!(f, "{} dealt to {}", card.name(), if *to_dealer { "dealer" } else { "player" })
+            }
+            GameEvent::HandResolved { result, bankroll } => write!(f, "{:?}, bankroll {:.2}", result, bankroll),
+            GameEvent::HoleCardDealt => write!(f, "Hole card dealt face down"),
+            GameEvent::HoleCardRevealed { card } => write!(f, "Dealer reveals {}", card.name()),
+            GameEvent::Reshuffled => write!(f, "Shoe reshuffled"),
+            GameEvent::Bankrupt { bankroll } => write!(f, "Bankrupt with {:.2}", bankroll),
+            GameEvent::IllegalAction { attempted, applied } => write!(f, "Strategy asked for {:?}, played {:?}", attempted, applied),
+            GameEvent::BetCapped { requested, placed } => write!(f, "Bet of {:.2} capped at {:.2}", requested, placed),
+            GameEvent::SatOut { requested } => write!(f, "Sat out, short of a {:.2} bet", requested),
+            GameEvent::Milestone { description } => write!(f, "{}", description),
+        }
+    }
+}
+
+// Runs of consecutive wins or losses; pushes leave the current run alone
+#[derive(Debug, Clone, Default)]
+pub struct StreakTracker {
+    // Positive for a run of wins, negative for a run of losses
+    current: i32,
+    longest_win: u32,
+    longest_loss: u32,
+    // The losing-streak record as it stood when the current losing run began
+    loss_record_to_beat: u32,
+}
+
+impl StreakTracker {
+    // Returns a milestone the first time a run reaches win_milestone wins, or the first time
+    // a losing run beats the previous record
+    fn record(&mut self, result: &GameResult, win_milestone: u32) -> Option<String> {
+        match result {
+            GameResult::PlayerWin | GameResult::PlayerBlackjack | GameResult::DoubledWin | GameResult::EvenMoney => {
+                self.current = self.current.max(0) + 1;
+                self.longest_win = self.longest_win.max(self.current as u32);
+                (win_milestone > 0 && self.current as u32 == win_milestone)
+                    .then(|| format!("{} wins in a row!", win_milestone))
+            }
+            GameResult::DealerWin | GameResult::DoubledLose | GameResult::Surrender | GameResult::Rescued => {
+                if self.current >= 0 {
+                    self.loss_record_to_beat = self.longest_loss;
+                }
+                self.current = self.current.min(0) - 1;
+                let run = self.current.unsigned_abs();
+                self.longest_loss = self.longest_loss.max(run);
+                (self.loss_record_to_beat > 0 && run == self.loss_record_to_beat + 1)
+                    .then(|| format!("Longest losing streak yet: {} in a row", run))
+            }
+            GameResult::Push => None,
+        }
+    }
+}
+
+// Why play stopped before the requested number of hands
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    CashOut,
+    LossLimit,
+    TrailingStop,
+    Bankrupt,
+    ShortOfBet,
+}
+
+impl StopReason {
+    pub fn label(&self) -> &'static str {
+        match self {
+            StopReason::CashOut => "Cash-out target reached",
+            StopReason::LossLimit => "Loss limit reached",
+            StopReason::TrailingStop => "Trailing stop hit",
+            StopReason::Bankrupt => "Insufficient bankroll to continue playing",
+            StopReason::ShortOfBet => "Sitting out, short of the bet",
+        }
+    }
+}
+
+// When the hand log is rolled over to a numbered file and started fresh
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogRotation {
+    Hands(u32),
+    Kilobytes(u64),
+}
+
+impl LogRotation {
+    fn is_due(&self, hands_logged: u32, path: &str) -> bool {
+        match self {
+            LogRotation::Hands(hands) => hands_logged >= *hands,
+            LogRotation::Kilobytes(kilobytes) => std::fs::metadata(path)
+                .is_ok_and(|metadata| metadata.len() >= kilobytes * 1024),
+        }
+    }
+
+    // Rolls the log at path to the first free numbered file beside it (blackjack_log.N.txt,
+    // or blackjack_log.N.jsonl) once the limit is hit; true when it was rolled
+    fn rotate(&self, hands_logged: u32, path: &str) -> bool {
+        if !self.is_due(hands_logged, path) {
+            return false;
+        }
+        let (stem, extension) = path.rsplit_once('.').unwrap();
+        let rotated = (1..)
+            .map(|n| format!("{}.{}.{}", stem, n, extension))
+            .find(|path| !std::path::Path::new(path).exists())
+            .unwrap();
+        std::fs::rename(path, rotated).is_ok()
+    }
+}
+
+// The prose transcript is for reading; the JSON log is one GameRecord per line for tools
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    pub const ALL: [LogFormat; 2] = [LogFormat::Text, LogFormat::Json];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogFormat::Text => "Text",
+            LogFormat::Json => "JSON Lines",
+        }
+    }
+
+    pub fn path(&self) -> &'static str {
+        match self {
+            LogFormat::Text => LOG_PATH,
+            LogFormat::Json => JSON_LOG_PATH,
+        }
+    }
+}
+
+// Another player at the table, with their own strategy, stake and bankroll. They play every
+// round from the same shoe against the same dealer, so their cards reach a counting
+// strategy like anyone else's.
+pub struct Seat {
+    pub strategy_kind: StrategyKind,
+    // What StrategyKind::StandOn stands on
+    pub stand_total: u8,
+    strategy: Box<dyn PlayStrategy>,
+    pub bet: f64,
+    pub bankroll: f64,
+    pub starting_bankroll: f64,
+    // This round's stake, the bet clamped between the table minimum and the bankroll
+    round_bet: f64,
+    pub hands: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub pushes: u32,
+    pub total_wagered: f64,
+    pub net_won: f64,
+}
+
+impl Seat {
+    pub fn new(strategy_kind: StrategyKind, bet: f64, bankroll: f64, rules: &RulesConfig) -> Seat {
+        let stand_total = 17;
+        Seat {
+            strategy_kind,
+            stand_total,
+            strategy: simple_strategy(strategy_kind, stand_total, rules),
+            bet,
+            bankroll,
+            starting_bankroll: bankroll,
+            round_bet: bet,
+            hands: 0,
+            wins: 0,
+            losses: 0,
+            pushes: 0,
+            total_wagered: 0.0,
+            net_won: 0.0,
+        }
+    }
+
+    pub fn strategy_name(&self) -> &str {
+        self.strategy.name()
+    }
+
+    pub fn house_edge(&self) -> f64 {
+        if self.total_wagered > 0.0 { -self.net_won / self.total_wagered } else { 0.0 }
+    }
+
+    // Back to the starting bankroll with no hands played, keeping the strategy and bet
+    pub fn reset(&mut self, bankroll: f64) {
+        self.bankroll = bankroll;
+        self.starting_bankroll = bankroll;
+        self.hands = 0;
+        self.wins = 0;
+        self.losses = 0;
+        self.pushes = 0;
+        self.total_wagered = 0.0;
+        self.net_won = 0.0;
+    }
+
+    fn settle(&mut self, result: &GameResult, rules: &RulesConfig) {
+        let net = hand_net(result, self.round_bet, rules);
+        self.hands += 1;
+        match result {
+            GameResult::PlayerWin | GameResult::DoubledWin | GameResult::PlayerBlackjack | GameResult::EvenMoney => self.wins += 1,
+            GameResult::Push => self.pushes += 1,
+            _ => self.losses += 1,
+        }
+        self.total_wagered += wagered_on(result, self.round_bet);
+        self.bankroll += net;
+        self.net_won += net;
+    }
+}
+
+// One seat's part in a round: its hands, more than one after a split, and each one's result
+// once it's settled. A seat sitting the round out has no hands.
+struct SeatRound {
+    hands: Vec<Hand>,
+    results: Vec<Option<GameResult>>,
+}
+
+impl SeatRound {
+    fn new(playing: bool) -> SeatRound {
+        if playing {
+            SeatRound { hands: vec![Hand::new()], results: vec![None] }
+        } else {
+            SeatRound { hands: Vec::new(), results: Vec::new() }
+        }
+    }
+}
+
+pub struct BlackjackApp {
+    pub last_game_result: Option<GameResult>,
+    pub games_played: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub pushes: u32,
+    pub deck: Deck,
+    // Every shuffle and random strategy choice comes from rng; with a seed the whole
+    // sequence of hands can be replayed
+    pub seed: Option<u64>,
+    pub seed_input: u64,
+    rng: StdRng,
+    pub shoe_preset: ShoePreset,
+    // Where the player cuts each new shoe, as a fraction from the top
+    pub cut_fraction: f64,
+    pub rules: RulesConfig,
+    pub bankroll: f64,
+    // The stake the player asks for; round_bet is what a round actually puts down, clamped
+    // between the table minimum and the bankroll
+    pub bet_amount: f64,
+    round_bet: f64,
+    // True counts from which a counting player opens one more spot each, at round_bet apiece;
+    // empty plays a single spot
+    pub spot_thresholds: Vec<f64>,
+    // Spots the player opened this round, ahead of other_seats or after them by seat_position
+    player_spots: usize,
+    pub seat_position: SeatPosition,
+    pub table_min: f64,
+    // Smallest chip: any bet the sizing computes is rounded down to a multiple of it
+    pub bet_increment: f64,
+    pub bet_sizing: BetSizing,
+    // The bets BetSizing::Ramp places, by the true count each step starts at
+    pub bet_ramp: Vec<RampStep>,
+    // The risk of ruin the UI recommends a ramp for
+    pub ramp_risk_of_ruin: f64,
+    // What a progression asks for next; None starts it over at the Bet amount
+    pub progression_bet: Option<f64>,
+    pub progression_shortfall: ProgressionShortfall,
+    // Start the progression over at every reshuffle, treating each shoe as its own session
+    pub reset_progression_on_shuffle: bool,
+    // This round's bet went down smaller than the sizing asked
+    bet_short: bool,
+    // No single bet may be more than this share of the bankroll, whatever the sizing asks
+    pub max_bet_fraction: Option<f64>,
+    pub bet_shortfall: BetShortfallPolicy,
+    pub show_units: bool,
+    pub card_style: CardStyle,
+    pub strategy: Box<dyn PlayStrategy>,
+    // Players sitting after this one at the table, each settled on their own
+    pub other_seats: Vec<Seat>,
+    evaluator: std::sync::Arc<dyn Evaluator>,
+    pub strategy_kind: StrategyKind,
+    // What StrategyKind::StandOn stands on
+    pub strategy_stand_total: u8,
+    // None plays textbook soft hands, otherwise basic strategy always stands on this soft total
+    pub soft_stand_total: Option<u8>,
+    // Basic strategy plays the Hi-Lo index plays off the count
+    pub hi_lo: bool,
+    pub count_system: CountSystem,
+    // True count at which the Hi-Lo strategy takes insurance
+    pub insurance_true_count: f64,
+    // Share of the main bet put up for insurance, at most half
+    pub insurance_fraction: f64,
+    // The counter insures off a side count of tens rather than insurance_true_count
+    pub insure_by_tens: bool,
+    // Count-dependent late surrenders the counter plays, in place of the chart's
+    pub surrender_indices: Vec<SurrenderIndex>,
+    // Cards the counter runs behind the deal, as a human keeping the count does
+    pub count_lag: usize,
+    pub deck_estimation: DeckEstimation,
+    // Counts kept beside the strategy's on the same cards, for comparison only
+    pub count_observers: Vec<CountObserver>,
+    // Chance per decision of a wrong-but-legal play, None for perfect play
+    pub play_error_rate: Option<f64>,
+    // Logs what textbook basic strategy would have done wherever the active strategy differs
+    pub show_basic_hint: bool,
+    pub reference_strategy: BasicStrategy,
+    pub starting_bankroll: f64,
+    pub total_wagered: f64,
+    pub largest_bet: f64,
+    // Sum of every hand's net result; unlike the bankroll it isn't touched by auto-reset
+    pub net_won: f64,
+    pub peak_bankroll: f64,
+    pub max_drawdown: f64,
+    pub show_summary: bool,
+    pub pending_games: u32,
+    // Whole shoes queued, each dealt through to the cut card
+    pub pending_shoes: u32,
+    pub shoes_to_play: u32,
+    hands_this_shoe: u32,
+    pub completed_shoes: u32,
+    pub completed_shoe_hands: u32,
+    pub autoplay_hands: u32,
+    pub autoplay_delay_ms: u64,
+    pub autoplay: Option<AutoPlay>,
+    pub batch_start: Option<BatchStart>,
+    pub batch_banner: Option<(String, std::time::Instant)>,
+    pub streaks: StreakTracker,
+    // Win streak length that raises a milestone, 0 for none
+    pub streak_milestone: u32,
+    pub milestone_banner: Option<(String, std::time::Instant)>,
+    logging_enabled: bool,
+    pub log_rotation: Option<LogRotation>,
+    pub log_format: LogFormat,
+    // The game being played, when logging as JSON
+    game_record: Option<GameRecord>,
+    // Hands written to the current log file since it was started
+    log_hands: u32,
+    // Verbose audit logging: the whole shoe in deal order after every shuffle
+    pub log_shoe_order: bool,
+    // Settle every hand in whole cents so long runs can't pick up floating-point drift
+    pub exact_cents: bool,
+    pub show_monte_carlo: bool,
+    pub show_cards_seen: bool,
+    pub show_count_calibration: bool,
+    pub count_calibration: Vec<CountStep>,
+    pub show_detailed_stats: bool,
+    pub outcome_histogram: OutcomeHistogram,
+    pub results_by_count: TrueCountResults,
+    // The player's true count as this round was dealt, None for a strategy that doesn't count
+    round_true_count: Option<f64>,
+    pub bankroll_history: BankrollHistory,
+    // Chart only this many of the latest hands rather than the whole session
+    pub chart_window: Option<usize>,
+    // Recent settled hands, for the game CSV; off for Monte Carlo sessions, and batches
+    // only record when asked
+    pub record_games: bool,
+    pub record_batch_games: bool,
+    pub game_rows: VecDeque<GameRow>,
+    pub export_status: Option<String>,
+    // Result of the last save, or why the saved session couldn't be restored
+    pub session_status: Option<String>,
+    // Save the shoe with the session, so a restored session plays on from the same card
+    pub save_shoe: bool,
+    // Session totals as of the last save to the strategy's stats file, so saving twice
+    // doesn't count the same hands twice
+    pub stats_saved: LifetimeStats,
+    pub lifetime_stats: Option<LifetimeStats>,
+    pub hands_per_hour: u32,
+    pub session_hours: f64,
+    pub monte_carlo_sessions: u32,
+    pub monte_carlo_hands: u32,
+    pub monte_carlo_threads: usize,
+    pub monte_carlo: Option<MonteCarloResult>,
+    pub show_comparison: bool,
+    pub show_strategy_check: bool,
+    pub strategy_check: Vec<StrategyCell>,
+    pub chart_export_status: Option<String>,
+    pub comparison_rules: Vec<RulesConfig>,
+    pub comparison_hands: u32,
+    pub comparison: Vec<ComparisonRow>,
+    // Pre-shuffled shoes to deal before shuffling new ones, so compared sessions see the same cards
+    queued_shoes: Vec<Deck>,
+    // Unlogged tables playing other strategies a round at a time alongside this one, from
+    // the same shoes, for the bankroll chart
+    pub what_ifs: Vec<BlackjackApp>,
+    pub auto_reset_on_ruin: bool,
+    // Ruin studies: add this much on bankruptcy and keep going, tallying the money put in
+    pub top_up_on_ruin: Option<f64>,
+    pub total_topped_up: f64,
+    // Goal-based play: stop once the bankroll reaches the target or falls to the limit
+    pub cash_out_target: Option<f64>,
+    pub loss_limit: Option<f64>,
+    // Stop once the bankroll drops this far below the session's peak
+    pub trailing_stop: Option<f64>,
+    pub stop_reason: Option<StopReason>,
+    pub ruin_count: u32,
+    pub action_counts: ActionCounts,
+    pub dealer_outcomes: DealerOutcomes,
+    // Strategy suggestions validate_action had to change, from debugging custom strategies
+    pub illegal_actions: u32,
+    pub side_bets: Vec<(Box<dyn SideBet>, bool)>,
+    // Stake on each enabled side bet, every hand
+    pub side_bet_amount: f64,
+    // This hand's side bet result, settled along with the main bet in pay_bet
+    side_bet_result: f64,
+    pub side_bet_wagered: f64,
+    pub side_bet_net: f64,
+    // This hand's insurance result, settled in pay_bet like the side bets but tallied apart
+    insurance_result: f64,
+    pub insurance_taken: u32,
+    pub insurance_wagered: f64,
+    pub insurance_net: f64,
+    // Events since the last drain_events() call
+    events: Vec<GameEvent>,
+    // This hand's face-down dealer card, until it's revealed
+    hole_card: Option<Card>,
+    pub event_log: Vec<String>,
+    pub show_events: bool,
+    #[cfg(feature = "profiling")]
+    last_profile: Option<BatchProfile>,
+}
+
+impl Default for BlackjackApp {
+    fn default() -> Self {
+        let mut rng = table_rng(None);
+        let mut new_deck = Deck::new(DEFAULT_DECKS);
+        new_deck.shuffle(&mut rng);
+        Self {
+            last_game_result: None,            
+            games_played: 0,
+            wins: 0,
+            losses: 0,
+            pushes: 0,
+            deck: new_deck,
+            seed: None,
+            seed_input: 0,
+            rng,
+            shoe_preset: ShoePreset::Standard,
+            cut_fraction: 0.0,
+            rules: RulesConfig::default(),
+            bankroll: 1000.0,
+            bet_amount: 10.0,
+            round_bet: 10.0,
+            spot_thresholds: Vec::new(),
+            player_spots: 1,
+            seat_position: SeatPosition::FirstBase,
+            table_min: 10.0,
+            bet_increment: 10.0,
+            bet_sizing: BetSizing::Flat,
+            bet_ramp: Vec::new(),
+            ramp_risk_of_ruin: DEFAULT_RAMP_RISK_OF_RUIN,
+            progression_bet: None,
+            progression_shortfall: ProgressionShortfall::Continue,
+            reset_progression_on_shuffle: false,
+            bet_short: false,
+            max_bet_fraction: None,
+            bet_shortfall: BetShortfallPolicy::TableMinimum,
+            show_units: false,
+            card_style: CardStyle::Unicode,
+            strategy: Box::new(BasicStrategy::new()),
+            other_seats: Vec::new(),
+            evaluator: std::sync::Arc::new(StandardEvaluator),
+            strategy_kind: StrategyKind::Basic,
+            strategy_stand_total: 17,
+            soft_stand_total: None,
+            hi_lo: false,
+            count_system: CountSystem::HiLo,
+            insurance_true_count: DEFAULT_INSURANCE_TRUE_COUNT,
+            insurance_fraction: MAX_INSURANCE_FRACTION,
+            insure_by_tens: false,
+            surrender_indices: Vec::new(),
+            count_lag: 0,
+            deck_estimation: DeckEstimation::Exact,
+            count_observers: Vec::new(),
+            play_error_rate: None,
+            show_basic_hint: false,
+            reference_strategy: BasicStrategy::new(),
+            starting_bankroll: 1000.0,
+            total_wagered: 0.0,
+            largest_bet: 0.0,
+            net_won: 0.0,
+            peak_bankroll: 1000.0,
+            max_drawdown: 0.0,
+            show_summary: false,
+            pending_games: 0,
+            pending_shoes: 0,
+            shoes_to_play: 10,
+            hands_this_shoe: 0,
+            completed_shoes: 0,
+            completed_shoe_hands: 0,
+            autoplay_hands: 20,
+            autoplay_delay_ms: 500,
+            autoplay: None,
+            batch_start: None,
+            batch_banner: None,
+            streaks: StreakTracker::default(),
+            streak_milestone: 5,
+            milestone_banner: None,
+            logging_enabled: true,
+            log_rotation: None,
+            log_format: LogFormat::Text,
+            game_record: None,
+            log_hands: 0,
+            log_shoe_order: false,
+            exact_cents: false,
+            show_monte_carlo: false,
+            show_cards_seen: false,
+            show_count_calibration: false,
+            count_calibration: Vec::new(),
+            show_detailed_stats: false,
+            outcome_histogram: OutcomeHistogram::default(),
+            results_by_count: TrueCountResults::default(),
+            round_true_count: None,
+            bankroll_history: BankrollHistory::default(),
+            chart_window: None,
+            record_games: true,
+            record_batch_games: false,
+            game_rows: VecDeque::new(),
+            export_status: None,
+            session_status: None,
+            save_shoe: false,
+            stats_saved: LifetimeStats::default(),
+            lifetime_stats: None,
+            hands_per_hour: 80,
+            session_hours: 4.0,
+            monte_carlo_sessions: 100,
+            monte_carlo_hands: 1000,
+            monte_carlo_threads: std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+            monte_carlo: None,
+            show_comparison: false,
+            show_strategy_check: false,
+            strategy_check: Vec::new(),
+            chart_export_status: None,
+            comparison_rules: Vec::new(),
+            comparison_hands: 10_000,
+            comparison: Vec::new(),
+            queued_shoes: Vec::new(),
+            what_ifs: Vec::new(),
+            auto_reset_on_ruin: false,
+            top_up_on_ruin: None,
+            total_topped_up: 0.0,
+            cash_out_target: None,
+            loss_limit: None,
+            trailing_stop: None,
+            stop_reason: None,
+            ruin_count: 0,
+            action_counts: ActionCounts::default(),
+            dealer_outcomes: DealerOutcomes::default(),
+            illegal_actions: 0,
+            side_bets: available_side_bets(),
+            side_bet_amount: 5.0,
+            side_bet_result: 0.0,
+            side_bet_wagered: 0.0,
+            side_bet_net: 0.0,
+            insurance_result: 0.0,
+            insurance_taken: 0,
+            insurance_wagered: 0.0,
+            insurance_net: 0.0,
+            events: Vec::new(),
+            hole_card: None,
+            event_log: Vec::new(),
+            show_events: false,
+            #[cfg(feature = "profiling")]
+            last_profile: None,
+        }
+    }
+}
+
+pub struct SessionSummary {
+    fingerprint: String,
+    rules: Vec<String>,
+    strategy: String,
+    pub hands: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub pushes: u32,
+    pub net_result: f64,
+    pub main_net: f64,
+    pub house_edge: f64,
+    pub ev_per_100: f64,
+    pub peak_bankroll: f64,
+    pub max_drawdown: f64,
+    pub ruin_count: u32,
+    pub total_topped_up: f64,
+    pub longest_win_streak: u32,
+    pub longest_loss_streak: u32,
+    pub shoes_completed: u32,
+    pub hands_per_shoe: Option<f64>,
+    cash_out_target: Option<f64>,
+    loss_limit: Option<f64>,
+    trailing_stop: Option<f64>,
+    stop_reason: Option<StopReason>,
+    pub action_counts: ActionCounts,
+    pub dealer_outcomes: DealerOutcomes,
+    pub illegal_actions: u32,
+    pub side_bet_wagered: f64,
+    pub side_bet_net: f64,
+    pub insurance_taken: u32,
+    pub insurance_wagered: f64,
+    pub insurance_net: f64,
+    pub theoretical_loss: Option<f64>,
+    hands_per_hour: u32,
+    pub session_hours: f64,
+    pub hourly_loss: f64,
+    pub big_bets: Option<BigBetStats>,
+}
+
+impl std::fmt::Display for SessionSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Rules: {} ({})", self.fingerprint, self.rules.join(", "))?;
+        writeln!(f, "Strategy: {}", self.strategy)?;
+        writeln!(f, "Hands: {}", self.hands)?;
+        writeln!(f, "Wins / Losses / Pushes: {} / {} / {}", self.wins, self.losses, self.pushes)?;
+        writeln!(f, "Net Result: ${:.2}", self.net_result)?;
+        writeln!(f, "Main Game Net: ${:.2}", self.main_net)?;
+        writeln!(f, "House Edge: {:.2}%", self.house_edge * 100.0)?;
+        writeln!(f, "EV per 100 Hands: ${:.2}", self.ev_per_100)?;
+        match self.theoretical_loss {
+            Some(theoretical) => writeln!(f, "Theoretical Loss: ${:.2} (actual ${:.2}, delta ${:.2})",
+                theoretical, -self.main_net, -self.main_net - theoretical)?,
+            None => writeln!(f, "Theoretical Loss: unknown for these rules")?,
+        }
+        writeln!(f, "Expected Loss: ${:.2}/hour, ${:.2} per {}-hour session at {} hands/hour",
+            self.hourly_loss, self.hourly_loss * self.session_hours, self.session_hours, self.hands_per_hour)?;
+        if let Some(big_bets) = &self.big_bets {
+            writeln!(f, "Net: {:+.2} average bets, {:+.2} big bets", big_bets.net_average_bets, big_bets.net_big_bets)?;
+            writeln!(f, "SD per 100 Hands: {:.2} bets", big_bets.sd_per_100)?;
+            match big_bets.n0 {
+                Some(n0) => writeln!(f, "DI: {:.2}, SCORE: {:.2}, N0: {:.0} hands", big_bets.desirability_index, big_bets.score(), n0)?,
+                None => writeln!(f, "DI: {:.2}, SCORE: {:.2}", big_bets.desirability_index, big_bets.score())?,
+            }
+        }
+        writeln!(f, "Peak Bankroll: ${:.2}", self.peak_bankroll)?;
+        writeln!(f, "Max Drawdown: ${:.2}", self.max_drawdown)?;
+        writeln!(f, "Bankruptcies: {}", self.ruin_count)?;
+        if self.total_topped_up > 0.0 {
+            writeln!(f, "Total Topped Up: ${:.2}", self.total_topped_up)?;
+        }
+        writeln!(f, "Longest Streaks: {} wins, {} losses", self.longest_win_streak, self.longest_loss_streak)?;
+        if let Some(per_shoe) = self.hands_per_shoe {
+            writeln!(f, "Shoes Completed: {} ({:.1} hands per shoe)", self.shoes_completed, per_shoe)?;
+        }
+        if let Some(target) = self.cash_out_target {
+            writeln!(f, "Cash-out Target: ${:.2}", target)?;
+        }
+        if let Some(limit) = self.loss_limit {
+            writeln!(f, "Loss Limit: ${:.2}", limit)?;
+        }
+        if let Some(trail) = self.trailing_stop {
+            writeln!(f, "Trailing Stop: ${:.2} below peak", trail)?;
+        }
+        if let Some(reason) = self.stop_reason {
+            writeln!(f, "Stopped: {}", reason.label())?;
+        }
+        writeln!(f, "Illegal Actions Attempted: {}", self.illegal_actions)?;
+        if self.side_bet_wagered > 0.0 {
+            writeln!(f, "Side Bets: ${:.2} wagered, net ${:.2}", self.side_bet_wagered, self.side_bet_net)?;
+        }
+        if self.insurance_taken > 0 {
+            writeln!(f, "Insurance: taken {} times, ${:.2} wagered, net ${:.2}", self.insurance_taken, self.insurance_wagered, self.insurance_net)?;
+        }
+        writeln!(f, "Dealer Outcomes: {}", self.dealer_outcomes.distribution())?;
+        write!(f, "Actions: {}", self.action_counts.distribution())
+    }
+}
+
+// Counters' figures of merit. Per-hand mean and variance come from the outcome histogram,
+// in units of the initial bet: DI is 1000 * EV / SD per hand, SCORE is DI squared, and N0 is
+// the number of hands before the expected win equals one standard deviation.
+#[derive(Debug, Clone)]
+pub struct BigBetStats {
+    pub net_average_bets: f64,
+    pub net_big_bets: f64,
+    pub sd_per_100: f64,
+    pub desirability_index: f64,
+    pub n0: Option<f64>,
+}
+
+impl BigBetStats {
+    fn new(net_result: f64, average_bet: f64, largest_bet: f64, histogram: &OutcomeHistogram) -> Option<BigBetStats> {
+        let (mean, variance) = histogram.mean_and_variance()?;
+        if average_bet <= 0.0 || variance <= 0.0 {
+            return None;
+        }
+        let sd = variance.sqrt();
+        Some(BigBetStats {
+            net_average_bets: net_result / average_bet,
+            net_big_bets: net_result / largest_bet,
+            sd_per_100: sd * 10.0,
+            desirability_index: 1000.0 * mean / sd,
+            n0: (mean != 0.0).then(|| variance / (mean * mean)),
+        })
+    }
+
+    // Signed, so a negative-expectation game reads as a negative SCORE rather than a good one
+    pub fn score(&self) -> f64 {
+        self.desirability_index * self.desirability_index.abs()
+    }
+}
+
+// Results accumulated for one strategy at one rule set across many sessions
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LifetimeStats {
+    pub hands: u32,
+    wins: u32,
+    losses: u32,
+    pushes: u32,
+    total_wagered: f64,
+    pub net_won: f64,
+}
+
+impl LifetimeStats {
+    fn merge(&mut self, other: &LifetimeStats) {
+        self.hands += other.hands;
+        self.wins += other.wins;
+        self.losses += other.losses;
+        self.pushes += other.pushes;
+        self.total_wagered += other.total_wagered;
+        self.net_won += other.net_won;
+    }
+
+    // What has been played since an earlier snapshot of the same session
+    fn since(&self, earlier: &LifetimeStats) -> LifetimeStats {
+        LifetimeStats {
+            hands: self.hands - earlier.hands,
+            wins: self.wins - earlier.wins,
+            losses: self.losses - earlier.losses,
+            pushes: self.pushes - earlier.pushes,
+            total_wagered: self.total_wagered - earlier.total_wagered,
+            net_won: self.net_won - earlier.net_won,
+        }
+    }
+
+    pub fn house_edge(&self) -> f64 {
+        if self.total_wagered > 0.0 { -self.net_won / self.total_wagered } else { 0.0 }
+    }
+}
+
+// One JSON file per strategy, mapping rule fingerprints to that strategy's lifetime stats
+pub fn strategy_stats_path(strategy_name: &str) -> std::path::PathBuf {
+    let slug: String = strategy_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    std::path::Path::new(STRATEGY_STATS_DIR).join(format!("{}.json", slug))
+}
+
+fn load_strategy_stats(path: &std::path::Path) -> Result<BTreeMap<String, LifetimeStats>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).map_err(|err| format!("{} is not a stats file: {}", path.display(), err)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(err) => Err(format!("Could not read {}: {}", path.display(), err)),
+    }
+}
+
+// Adds the session's stats to whatever the file already holds for the same rules
+fn merge_strategy_stats(path: &std::path::Path, fingerprint: &str, session: &LifetimeStats) -> Result<LifetimeStats, String> {
+    let mut stored = load_strategy_stats(path)?;
+    let lifetime = stored.entry(fingerprint.to_string()).or_default();
+    lifetime.merge(session);
+    let lifetime = lifetime.clone();
+    let json = serde_json::to_string_pretty(&stored).map_err(|err| err.to_string())?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|err| format!("Could not create {}: {}", dir.display(), err))?;
+    }
+    std::fs::write(path, json).map_err(|err| format!("Could not write {}: {}", path.display(), err))?;
+    Ok(lifetime)
+}
+
+// The running totals and the table they were played at, carried from one run of the app
+// to the next
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SavedSession {
+    bankroll: f64,
+    starting_bankroll: f64,
+    total_topped_up: f64,
+    games_played: u32,
+    wins: u32,
+    losses: u32,
+    pushes: u32,
+    total_wagered: f64,
+    net_won: f64,
+    strategy_kind: StrategyKind,
+    strategy_stand_total: u8,
+    soft_stand_total: Option<u8>,
+    hi_lo: bool,
+    play_error_rate: Option<f64>,
+    rules: RulesConfig,
+    // The shoe as it stood, dealt cards and all, when the player chose to keep it
+    shoe: Option<Deck>,
+}
+
+// None if nothing has been saved yet
+fn load_session(path: &str) -> Result<Option<SavedSession>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).map(Some).map_err(|err| format!("{} is not a saved session: {}", path, err)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(format!("Could not read {}: {}", path, err)),
+    }
+}
+
+// The Hi-Lo count after one card of a calibration run through the shoe
+pub struct CountStep {
+    pub card: Card,
+    pub running_count: i32,
+    pub cards_remaining: usize,
+    pub true_count: f64,
+}
+
+// One rule set's result from a comparison run
+pub struct ComparisonRow {
+    pub fingerprint: String,
+    pub hands: u32,
+    pub net_won: f64,
+    total_wagered: f64,
+    pub approximate_edge: Option<f64>,
+}
+
+impl ComparisonRow {
+    pub fn house_edge(&self) -> f64 {
+        if self.total_wagered > 0.0 { -self.net_won / self.total_wagered } else { 0.0 }
+    }
+}
+
+// Final bankrolls of independent sessions, kept sorted so percentiles are simple lookups
+pub struct MonteCarloResult {
+    starting_bankroll: f64,
+    final_bankrolls: Vec<f64>,
+    // Sessions that stopped by reaching the cash-out target or the loss limit
+    pub goals_reached: u32,
+    pub loss_limits_hit: u32,
+    pub trailing_stops_hit: u32,
+}
+
+impl MonteCarloResult {
+    // Folds in sessions run on another thread, keeping final_bankrolls sorted
+    fn combine(&mut self, other: MonteCarloResult) {
+        self.final_bankrolls.extend(other.final_bankrolls);
+        self.final_bankrolls.sort_by(f64::total_cmp);
+        self.goals_reached += other.goals_reached;
+        self.loss_limits_hit += other.loss_limits_hit;
+        self.trailing_stops_hit += other.trailing_stops_hit;
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.final_bankrolls.iter().sum::<f64>() / self.final_bankrolls.len().max(1) as f64
+    }
+
+    // Nearest-rank percentile, p in 0..=100
+    pub fn percentile(&self, p: f64) -> f64 {
+        if self.final_bankrolls.is_empty() {
+            return 0.0;
+        }
+        let rank = (p / 100.0 * self.final_bankrolls.len() as f64).ceil() as usize;
+        self.final_bankrolls[rank.clamp(1, self.final_bankrolls.len()) - 1]
+    }
+
+    pub fn median(&self) -> f64 {
+        self.percentile(50.0)
+    }
+
+    pub fn probability_goal_reached(&self) -> f64 {
+        self.goals_reached as f64 / self.final_bankrolls.len().max(1) as f64
+    }
+
+    pub fn probability_loss_limit(&self) -> f64 {
+        self.loss_limits_hit as f64 / self.final_bankrolls.len().max(1) as f64
+    }
+
+    pub fn probability_trailing_stop(&self) -> f64 {
+        self.trailing_stops_hit as f64 / self.final_bankrolls.len().max(1) as f64
+    }
+
+    pub fn probability_ahead(&self) -> f64 {
+        let ahead = self.final_bankrolls.iter().filter(|&&b| b > self.starting_bankroll).count();
+        ahead as f64 / self.final_bankrolls.len().max(1) as f64
+    }
+
+    // Returns (bin centre, bin width, count) for evenly sized bins across the observed range
+    pub fn histogram(&self, bins: usize) -> Vec<(f64, f64, u32)> {
+        let (Some(&low), Some(&high)) = (self.final_bankrolls.first(), self.final_bankrolls.last()) else {
+            return Vec::new();
+        };
+        let width = ((high - low) / bins as f64).max(1.0);
+        let mut counts = vec![0; bins];
+        for &bankroll in &self.final_bankrolls {
+            let bin = (((bankroll - low) / width) as usize).min(bins - 1);
+            counts[bin] += 1;
+        }
+        counts.iter()
+            .enumerate()
+            .map(|(i, &count)| (low + width * (i as f64 + 0.5), width, count))
+            .collect()
+    }
+}
+
+// Counters at the moment a batch was queued, so its own result can be reported at the end
+pub struct BatchStart {
+    net_won: f64,
+    games_played: u32,
+    wins: u32,
+    losses: u32,
+    pushes: u32,
+}
+
+impl BatchStart {
+    fn capture(app: &BlackjackApp) -> BatchStart {
+        BatchStart {
+            net_won: app.net_won,
+            games_played: app.games_played,
+            wins: app.wins,
+            losses: app.losses,
+            pushes: app.pushes,
+        }
+    }
+
+    pub fn describe(&self, app: &BlackjackApp) -> String {
+        format!("Batch finished: {} hands, {} / {} / {} W/L/P, net {}",
+            app.games_played - self.games_played,
+            app.wins - self.wins,
+            app.losses - self.losses,
+            app.pushes - self.pushes,
+            app.format_amount(app.net_won - self.net_won))
+    }
+}
+
+// Paces hands for watching play unfold, as opposed to the flat-out batch queue
+pub struct AutoPlay {
+    pub remaining: u32,
+    delay: std::time::Duration,
+    next_at: std::time::Instant,
+}
+
+impl AutoPlay {
+    pub fn start(hands: u32, delay: std::time::Duration, now: std::time::Instant) -> Self {
+        Self { remaining: hands, delay, next_at: now }
+    }
+
+    fn is_due(&self, now: std::time::Instant) -> bool {
+        self.remaining > 0 && now >= self.next_at
+    }
+
+    fn mark_played(&mut self, now: std::time::Instant) {
+        self.remaining = self.remaining.saturating_sub(1);
+        self.next_at = now + self.delay;
+    }
+
+    fn wait(&self, now: std::time::Instant) -> std::time::Duration {
+        self.next_at.saturating_duration_since(now)
+    }
+}
+
+// Only compiled with the "profiling" feature so normal builds pay nothing for timing
+#[cfg(feature = "profiling")]
+pub struct BatchProfile {
+    hands: u32,
+    elapsed: std::time::Duration,
+}
+
+#[cfg(feature = "profiling")]
+impl BatchProfile {
+    fn average_per_hand(&self) -> std::time::Duration {
+        self.elapsed / self.hands.max(1)
+    }
+
+    fn hands_per_second(&self) -> f64 {
+        self.hands as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+#[cfg(feature = "profiling")]
+impl std::fmt::Display for BatchProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} hands in {:.3?} ({:.0} hands/s, {:.2?} per hand)",
+            self.hands, self.elapsed, self.hands_per_second(), self.average_per_hand())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Action {
+    Hit,
+    Stand,
+    DoubleDown,
+    Split,
+    Surrender,
+}
+
+// One settled hand, as written to the per-game CSV
+#[derive(Debug, Clone)]
+pub struct GameRow {
+    game: u32,
+    // Which of the player's spots, counted from the left from zero
+    spot: usize,
+    player_total: u8,
+    dealer_total: u8,
+    result: GameResult,
+    wagered: f64,
+    bankroll: f64,
+}
+
+// One game as a line of the JSON log. Every seat's hands are in it; the bankroll is the
+// player's once the round is settled.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GameRecord {
+    game: u32,
+    // Each hand as dealt, including a split hand once it has its second card
+    hands: Vec<HandRecord>,
+    dealer_cards: Vec<String>,
+    actions: Vec<PlayRecord>,
+    dealer_draws: Vec<DrawRecord>,
+    results: Vec<ResultRecord>,
+    bankroll: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct HandRecord {
+    seat: usize,
+    hand: usize,
+    cards: Vec<String>,
+    total: u8,
+}
+
+// A decision and where it left the hand; card is the one it drew, if any
+#[derive(Debug, Clone, Serialize)]
+struct PlayRecord {
+    seat: usize,
+    hand: usize,
+    action: Action,
+    card: Option<String>,
+    total: u8,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct DrawRecord {
+    card: String,
+    total: u8,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ResultRecord {
+    seat: usize,
+    hand: usize,
+    result: GameResult,
+}
+
+// Bankroll after each hand as (game, bankroll) points for the chart. Only every stride-th
+// game is kept; when the points fill up the stride doubles and the points in between are
+// dropped, so memory stays bounded however long the session runs. The most recent games
+// are also kept unthinned, for the scrolling window.
+#[derive(Debug, Clone, Default)]
+pub struct BankrollHistory {
+    points: Vec<[f64; 2]>,
+    stride: u32,
+    recent: VecDeque<[f64; 2]>,
+}
+
+impl BankrollHistory {
+    fn record(&mut self, game: u32, bankroll: f64) {
+        self.stride = self.stride.max(1);
+        if self.points.len() >= BANKROLL_HISTORY_POINTS {
+            self.stride *= 2;
+            let stride = self.stride;
+            self.points.retain(|point| (point[0] as u32).is_multiple_of(stride));
+        }
+        if game.is_multiple_of(self.stride) {
+            self.points.push([game as f64, bankroll]);
+        }
+        if self.recent.len() >= MAX_CHART_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back([game as f64, bankroll]);
+    }
+
+    pub fn points(&self) -> &[[f64; 2]] {
+        &self.points
+    }
+
+    // Every game of the last `hands`, or of as many as have been played
+    pub fn last(&self, hands: usize) -> Vec<[f64; 2]> {
+        self.recent.range(self.recent.len().saturating_sub(hands)..).copied().collect()
+    }
+}
+
+// Hands bucketed by net result in multiples of the bet, to the nearest half bet
+#[derive(Debug, Clone, Default)]
+pub struct OutcomeHistogram {
+    half_bets: BTreeMap<i32, u32>,
+}
+
+impl OutcomeHistogram {
+    fn record(&mut self, net_bets: f64) {
+        *self.half_bets.entry((net_bets * 2.0).round() as i32).or_insert(0) += 1;
+    }
+
+    fn buckets(&self) -> impl Iterator<Item = (f64, u32)> + '_ {
+        self.half_bets.iter().map(|(&half_bets, &count)| (half_bets as f64 / 2.0, count))
+    }
+
+    // Sample mean and variance of the net per hand, None until there are two hands
+    fn mean_and_variance(&self) -> Option<(f64, f64)> {
+        let hands: u32 = self.half_bets.values().sum();
+        if hands < 2 {
+            return None;
+        }
+        let mean = self.buckets().map(|(net_bets, count)| net_bets * count as f64).sum::<f64>() / hands as f64;
+        let squares = self.buckets().map(|(net_bets, count)| (net_bets - mean).powi(2) * count as f64).sum::<f64>();
+        Some((mean, squares / (hands - 1) as f64))
+    }
+
+    pub fn describe(&self) -> String {
+        self.buckets()
+            .map(|(net_bets, count)| format!("{:+}: {}", net_bets, count))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    // The leading comment records the rules the outcomes were played under
+    pub fn to_csv(&self, fingerprint: &str) -> String {
+        let mut csv = format!("# {}\nnet_bets,hands\n", fingerprint);
+        for (net_bets, count) in self.buckets() {
+            csv.push_str(&format!("{},{}\n", csv_amount(net_bets), count));
+        }
+        csv
+    }
+}
+
+// Each true count point is worth about half a percent to the player, from the house edge
+// at a count of zero
+pub fn edge_at_true_count(true_count: f64, house_edge: f64) -> f64 {
+    true_count * EDGE_PER_TRUE_COUNT - house_edge
+}
+
+// The player's hands bucketed by the true count, to the nearest point, when they were dealt
+#[derive(Debug, Clone, Default)]
+pub struct TrueCountResults {
+    buckets: BTreeMap<i32, CountBucket>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct CountBucket {
+    hands: u32,
+    wins: u32,
+    pushes: u32,
+    losses: u32,
+    net_bets: f64,
+}
+
+impl TrueCountResults {
+    fn record(&mut self, true_count: f64, result: &GameResult, net_bets: f64) {
+        let bucket = self.buckets.entry(true_count.round() as i32).or_default();
+        bucket.hands += 1;
+        match result {
+            GameResult::PlayerWin | GameResult::DoubledWin | GameResult::PlayerBlackjack | GameResult::EvenMoney => bucket.wins += 1,
+            GameResult::Push => bucket.pushes += 1,
+            _ => bucket.losses += 1,
+        }
+        bucket.net_bets += net_bets;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    // The leading comment records the rules the hands were played under; each row sets the
+    // measured net per hand beside what theory expects at that count
+    pub fn to_csv(&self, fingerprint: &str, theoretical_edge: impl Fn(f64) -> f64) -> String {
+        let mut csv = format!("# {}\ntrue_count,hands,wins,pushes,losses,win_rate,net_bets,net_bets_per_hand,theoretical_per_hand\n", fingerprint);
+        for (true_count, bucket) in &self.buckets {
+            let hands = bucket.hands as f64;
+            csv.push_str(&format!("{},{},{},{},{},{:.4},{},{:.4},{:.4}\n", true_count, bucket.hands, bucket.wins, bucket.pushes,
+                bucket.losses, bucket.wins as f64 / hands, csv_amount(bucket.net_bets), bucket.net_bets / hands,
+                theoretical_edge(*true_count as f64)));
+        }
+        csv
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ActionCounts {
+    hits: u32,
+    stands: u32,
+    doubles: u32,
+    splits: u32,
+    surrenders: u32,
+}
+
+impl Action {
+    // One-letter chart code
+    pub fn code(&self) -> &'static str {
+        match self {
+            Action::Hit => "H",
+            Action::Stand => "S",
+            Action::DoubleDown => "D",
+            Action::Split => "P",
+            Action::Surrender => "R",
+        }
+    }
+}
+
+impl ActionCounts {
+    fn record(&mut self, action: &Action) {
+        match action {
+            Action::Hit => self.hits += 1,
+            Action::Stand => self.stands += 1,
+            Action::DoubleDown => self.doubles += 1,
+            Action::Split => self.splits += 1,
+            Action::Surrender => self.surrenders += 1,
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        self.hits + self.stands + self.doubles + self.splits + self.surrenders
+    }
+
+    pub fn distribution(&self) -> String {
+        let total = self.total().max(1) as f64;
+        [("Hit", self.hits), ("Stand", self.stands), ("Double", self.doubles), ("Split", self.splits), ("Surrender", self.surrenders)]
+            .iter()
+            .map(|(name, count)| format!("{} {} ({:.1}%)", name, count, *count as f64 / total * 100.0))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+// Final dealer hands, recorded whenever the dealer's hand reaches a resolution
+#[derive(Debug, Clone, Default)]
+pub struct DealerOutcomes {
+    blackjacks: u32,
+    made: BTreeMap<u8, u32>,
+    busts: BTreeMap<u8, u32>,
+}
+
+impl DealerOutcomes {
+    fn record(&mut self, dealer_hand: &Hand, evaluator: &dyn Evaluator) {
+        if evaluator.is_natural(dealer_hand) {
+            self.blackjacks += 1;
+        } else if evaluator.is_bust(dealer_hand) {
+            *self.busts.entry(dealer_hand.total()).or_insert(0) += 1;
+        } else {
+            *self.made.entry(dealer_hand.total()).or_insert(0) += 1;
+        }
+    }
+
+    fn total(&self) -> u32 {
+        self.blackjacks + self.made.values().sum::<u32>() + self.busts.values().sum::<u32>()
+    }
+
+    pub fn distribution(&self) -> String {
+        let total = self.total().max(1) as f64;
+        let percent = |count: u32| count as f64 / total * 100.0;
+        let busts: u32 = self.busts.values().sum();
+        std::iter::once(format!("BJ {:.1}%", percent(self.blackjacks)))
+            .chain(self.made.iter().map(|(dealer_total, &count)| format!("{} {:.1}%", dealer_total, percent(count))))
+            .chain(std::iter::once(format!("Bust {:.1}%", percent(busts))))
+            .chain(self.busts.iter().map(|(dealer_total, &count)| format!("{} {:.1}%", dealer_total, percent(count))))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+// How hands are scored, so variants with another target or bust rule can replace the
+// standard one. compare is only asked about two hands that both stand.
+pub trait Evaluator: Send + Sync {
+    fn is_bust(&self, hand: &Hand) -> bool;
+    fn is_natural(&self, hand: &Hand) -> bool;
+    fn compare(&self, player_hand: &Hand, dealer_hand: &Hand) -> std::cmp::Ordering;
+    // The total no card can improve on, so the table refuses a hit there
+    fn target(&self) -> u8;
+}
+
+pub struct StandardEvaluator;
+
+impl Evaluator for StandardEvaluator {
+    fn is_bust(&self, hand: &Hand) -> bool {
+        hand.is_busted()
+    }
+
+    fn is_natural(&self, hand: &Hand) -> bool {
+        hand.is_blackjack()
+    }
+
+    fn compare(&self, player_hand: &Hand, dealer_hand: &Hand) -> std::cmp::Ordering {
+        player_hand.total().cmp(&dealer_hand.total())
+    }
+
+    fn target(&self) -> u8 {
+        21
+    }
+}
+
+// Send + Sync so Monte Carlo threads can share the configured table
+pub trait PlayStrategy: Send + Sync {
+    fn name(&self) -> &str;
+    fn determine_action(&self, player_hand: &Hand, dealer_upcard: &Card) -> Action;
+    fn determine_first_action(&self, player_hand: &Hand, dealer_upcard: &Card) -> Action;
+    // With both dealer cards exposed the whole dealer hand is passed in; strategies that
+    // don't override these play from the upcard alone, as if the hole card were hidden
+    fn determine_first_action_exposed(&self, player_hand: &Hand, dealer_hand: &Hand) -> Action {
+        self.determine_first_action(player_hand, &dealer_hand.cards[0])
+    }
+    fn determine_action_exposed(&self, player_hand: &Hand, dealer_hand: &Hand) -> Action {
+        self.determine_action(player_hand, &dealer_hand.cards[0])
+    }
+    // A hand made by splitting: split aces take their one card and stand unless they make
+    // another pair of aces to split, and surrender is gone once a hand is split, so a
+    // strategy that asks for it hits or stands instead
+    fn determine_split_action(&self, player_hand: &Hand, dealer_upcard: &Card, is_split_aces: bool) -> Action {
+        if is_split_aces {
+            return match self.determine_first_action(player_hand, dealer_upcard) {
+                Action::Split if player_hand.is_pair() => Action::Split,
+                _ => Action::Stand,
+            };
+        }
+        match self.determine_first_action(player_hand, dealer_upcard) {
+            Action::Surrender => self.determine_action(player_hand, dealer_upcard),
+            action => action,
+        }
+    }
+    // Only asked when the table offers double down rescue, after the doubled card is dealt
+    fn rescue_double(&self, _player_hand: &Hand, _dealer_upcard: &Card) -> bool {
+        false
+    }
+    // Only asked when the dealer shows an ace
+    fn take_insurance(&self, _player_hand: &Hand, _dealer_upcard: &Card) -> bool {
+        false
+    }
+    // Only asked holding a blackjack against an ace, in place of insurance
+    fn take_even_money(&self, _player_hand: &Hand, _dealer_upcard: &Card) -> bool {
+        false
+    }
+    // Counting strategies see each card as it is turned face up, and are told the shoe size
+    // whenever a new shoe is started; everything else ignores both
+    fn observe_card(&mut self, _card: &Card) {}
+    fn observe_shuffle(&mut self, _shoe_cards: usize) {}
+    fn running_count(&self) -> Option<i32> {
+        None
+    }
+    fn true_count(&self) -> Option<f64> {
+        None
+    }
+}
+
+
+pub struct BasicStrategy {
+    // Experiment: always stand on soft totals from this one up, instead of hitting soft 18 vs 9-A
+    soft_stand_total: Option<u8>,
+    // Double Exposure: a tie with an exposed dealer total loses, so it's worth drawing to
+    pub ties_lose: bool,
+    // Some small pairs are only worth splitting when the split hands may be doubled
+    pub double_after_split: bool,
+    name: String,
+}
+
+impl Default for BasicStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BasicStrategy {
+    pub fn new() -> BasicStrategy {
+        BasicStrategy { soft_stand_total: None, ties_lose: false, double_after_split: true, name: "Basic Strategy".to_string() }
+    }
+
+    pub fn with_soft_stand(soft_stand_total: u8) -> BasicStrategy {
+        BasicStrategy {
+            soft_stand_total: Some(soft_stand_total),
+            ties_lose: false,
+            double_after_split: true,
+            name: format!("Basic Strategy, stand on soft {}", soft_stand_total),
+        }
+    }
+}
+
+impl PlayStrategy for BasicStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Rough double exposure play: chase a made dealer hand, never bust against a stiff one
+    fn determine_action_exposed(&self, player_hand: &Hand, dealer_hand: &Hand) -> Action {
+        let player_total = player_hand.total();
+        let dealer_total = dealer_hand.total();
+        if dealer_total >= 17 {
+            let behind = player_total < dealer_total || self.ties_lose && player_total == dealer_total;
+            return if behind { Action::Hit } else { Action::Stand };
+        }
+        if dealer_total >= 12 {
+            let can_bust = !player_hand.is_soft() && player_total >= 12;
+            return if can_bust || player_total >= 18 { Action::Stand } else { Action::Hit };
+        }
+        self.determine_action(player_hand, &dealer_hand.cards[0])
+    }
+
+    // A busted double loses both bets, and a doubled stiff against 9 or better loses a
+    // little over one, so both cost more than the usual one-bet forfeit
+    fn rescue_double(&self, player_hand: &Hand, dealer_upcard: &Card) -> bool {
+        let stiff = !player_hand.is_soft() && player_hand.total() <= 16;
+        player_hand.is_busted() || stiff && dealer_upcard.value() >= 9
+    }
+
+    // Without a count both are bets on the hole card that the house wins; even money is just
+    // insurance on a blackjack
+    fn take_insurance(&self, _player_hand: &Hand, _dealer_upcard: &Card) -> bool {
+        false
+    }
+
+    fn take_even_money(&self, _player_hand: &Hand, _dealer_upcard: &Card) -> bool {
+        false
+    }
+
+    fn determine_first_action(&self, player_hand: &Hand, dealer_upcard: &Card) -> Action {
+        let player_total = player_hand.total();
+        let dealer_value = dealer_upcard.value();
+        if player_hand.is_soft() {
+            if player_hand.cards[0].rank == Rank::Ace && player_hand.cards[1].rank == Rank::Ace {
+                return Action::Split;
+            }
+            if player_total == 19 && dealer_value == 6 {
+                return Action::DoubleDown;
+            }
+            if player_total == 18 && dealer_value <= 6 {
+                return Action::DoubleDown;
+            }
+            if player_total == 17 && (3..=6).contains(&dealer_value) {
+                return Action::DoubleDown;
+            }
+            if (player_total == 16 || player_total == 15) && (4..=6).contains(&dealer_value) {
+                return Action::DoubleDown;
+            }
+            if (player_total == 14 || player_total == 13) && (5..=6).contains(&dealer_value) {
+                return Action::DoubleDown;
+            }    
+        }        
+        if player_hand.cards[0].rank == player_hand.cards[1].rank {
+            // 9-9 stands on 18 against a 7, and against a ten or an ace it is already beaten
+            if player_total == 18 && matches!(dealer_value, 2..=6 | 8 | 9) {
+                return Action::Split;
+            }
+            if player_total == 16 {
+                return Action::Split;
+            }
+            if player_total == 14 && dealer_value <= 7 {
+                return Action::Split;
+            }
+            if player_total == 12 && (3..=6).contains(&dealer_value) {
+                return Action::Split;
+            }
+            // 2-2 and 3-3 split against 4 to 7, and against a 2 or 3 too when the split hands
+            // may be doubled
+            let low_upcards = if self.double_after_split { 2..=7 } else { 4..=7 };
+            if (player_total == 6 || player_total == 4) && low_upcards.contains(&dealer_value) {
+                return Action::Split;
+            }
+            // 4-4 makes a good start to a double against a 5 or 6, but only if it's allowed
+            if player_total == 8 && self.double_after_split && (5..=6).contains(&dealer_value) {
+                return Action::Split;
+            }
+        }
+
+        // Only stiff hands surrender; a soft 15 or 16 can't bust on the next card
+        if !player_hand.is_soft() {
+            if player_total == 16 && (9..= 11).contains(&dealer_value) {
+                return Action::Surrender;
+            }
+            if player_total == 15 && dealer_value == 10 {
+                return Action::Surrender;
+            }
+        }
+        if player_total == 11 {
+            return Action::DoubleDown;
+        }
+        if player_total == 10 && dealer_value < 10 {
+            return Action::DoubleDown;
+        }
+        if player_total == 9 && (3..= 6).contains(&dealer_value) {
+            return Action::DoubleDown;
+        }
+
+        Action::Stand
+    }
+        
+    fn determine_action(&self, player_hand: &Hand, dealer_upcard: &Card) -> Action {
+        let player_total = player_hand.total();
+        let dealer_value = dealer_upcard.value();
+       
+        if player_hand.is_soft() {
+            if self.soft_stand_total.is_some_and(|soft_stand_total| player_total >= soft_stand_total) {
+                return Action::Stand;
+            }
+            if player_total <= 17 {
+                return Action::Hit;
+            }
+            if player_total == 18 && dealer_value >= 9 {
+                return Action::Hit;
+            }
+            // Soft 19 and up always stand; stated here rather than left to the hard-total checks
+            return Action::Stand;
+        }
+
+        if player_total <= 11 {
+            return Action::Hit;
+        }
+        if player_total == 12 && !(4..=6).contains(&dealer_value) {
+            return Action::Hit;
+        }
+        if (13..= 16).contains(&player_total) && dealer_value >= 7 {
+            return Action::Hit;
+        }
+
+        Action::Stand
+    }
+}
+
+// Naive "always stand on N" play for comparing against basic strategy; it never doubles,
+// splits or surrenders
+struct ThresholdStrategy {
+    stand_total: u8,
+    name: String,
+}
+
+impl ThresholdStrategy {
+    fn new(stand_total: u8) -> ThresholdStrategy {
+        ThresholdStrategy { stand_total, name: format!("Stand on {}", stand_total) }
+    }
+}
+
+impl PlayStrategy for ThresholdStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn determine_first_action(&self, player_hand: &Hand, dealer_upcard: &Card) -> Action {
+        self.determine_action(player_hand, dealer_upcard)
+    }
+
+    fn determine_action(&self, player_hand: &Hand, _dealer_upcard: &Card) -> Action {
+        if player_hand.total() < self.stand_total {
+            Action::Hit
+        } else {
+            Action::Stand
+        }
+    }
+}
+
+// Each kind as the other seats play it: basic strategy straight off the chart, without
+// the player's soft-stand and counting options
+fn simple_strategy(kind: StrategyKind, stand_total: u8, rules: &RulesConfig) -> Box<dyn PlayStrategy> {
+    match kind {
+        StrategyKind::Basic => Box::new(BasicStrategy {
+            ties_lose: rules.dealer_wins_ties,
+            double_after_split: rules.double_after_split,
+            ..BasicStrategy::new()
+        }),
+        StrategyKind::StandOn => Box::new(ThresholdStrategy::new(stand_total)),
+        StrategyKind::DealerMimic => Box::new(DealerMimicStrategy::new(rules.dealer)),
+        StrategyKind::NeverBust => Box::new(NeverBustStrategy),
+    }
+}
+
+// Baseline that plays the player's hand by the table's dealer rule, never doubling,
+// splitting or surrendering
+struct DealerMimicStrategy {
+    dealer: DealerRule,
+    name: String,
+}
+
+impl DealerMimicStrategy {
+    fn new(dealer: DealerRule) -> DealerMimicStrategy {
+        DealerMimicStrategy { dealer, name: format!("Dealer mimic ({})", dealer.describe()) }
+    }
+}
+
+impl PlayStrategy for DealerMimicStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn determine_first_action(&self, player_hand: &Hand, dealer_upcard: &Card) -> Action {
+        self.determine_action(player_hand, dealer_upcard)
+    }
+
+    fn determine_action(&self, player_hand: &Hand, _dealer_upcard: &Card) -> Action {
+        if self.dealer.should_hit(player_hand) { Action::Hit } else { Action::Stand }
+    }
+}
+
+// Baseline that never risks a bust: stands on any hard 12 or more, and hits soft hands
+// (which can't bust) up to soft 17
+struct NeverBustStrategy;
+
+impl PlayStrategy for NeverBustStrategy {
+    fn name(&self) -> &str {
+        "Never bust"
+    }
+
+    fn determine_first_action(&self, player_hand: &Hand, dealer_upcard: &Card) -> Action {
+        self.determine_action(player_hand, dealer_upcard)
+    }
+
+    fn determine_action(&self, player_hand: &Hand, _dealer_upcard: &Card) -> Action {
+        let total = player_hand.total();
+        let stand_at = if player_hand.is_soft() { 18 } else { 12 };
+        if total < stand_at { Action::Hit } else { Action::Stand }
+    }
+}
+
+// A late surrender taken on this hard total against this upcard once the true count reaches
+// true_count, and refused below it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurrenderIndex {
+    pub total: u8,
+    // 2 through 10, and 11 for an ace
+    pub upcard: u8,
+    pub true_count: f64,
+}
+
+// The "Fab 4" surrender indices for a six-deck S17 shoe
+pub const FAB_FOUR_SURRENDERS: [SurrenderIndex; 4] = [
+    SurrenderIndex { total: 14, upcard: 10, true_count: 3.0 },
+    SurrenderIndex { total: 15, upcard: 10, true_count: 0.0 },
+    SurrenderIndex { total: 15, upcard: 9, true_count: 2.0 },
+    SurrenderIndex { total: 15, upcard: 11, true_count: 1.0 },
+];
+
+// Card counter: basic strategy, switched to the well-known Hi-Lo index plays once the true
+// count (running count per deck still in the shoe) reaches each play's index
+struct CountingStrategy {
+    base: BasicStrategy,
+    system: CountSystem,
+    // True count from which insurance is taken
+    insurance_true_count: f64,
+    // Insure off the side count of tens instead of the true count
+    insure_by_tens: bool,
+    surrender_indices: Vec<SurrenderIndex>,
+    deck_estimation: DeckEstimation,
+    running_count: i32,
+    // Cards not yet seen, the hole card included until it is turned over
+    cards_remaining: usize,
+    // Side count: tens (and faces) among the unseen cards
+    tens_remaining: usize,
+    // Cards seen but not yet counted: each one waits until count_lag more have come out
+    count_lag: usize,
+    uncounted: VecDeque<Card>,
+    name: String,
+}
+
+impl CountingStrategy {
+    fn new(base: BasicStrategy, system: CountSystem, insurance_true_count: f64) -> CountingStrategy {
+        let name = format!("{}, {} count", base.name, system.label());
+        let cards_remaining = DEFAULT_DECKS as usize * 52;
+        let running_count = system.initial_running_count(cards_remaining);
+        let tens_remaining = cards_remaining * 4 / 13;
+        CountingStrategy {
+            base,
+            system,
+            insurance_true_count,
+            insure_by_tens: false,
+            surrender_indices: Vec::new(),
+            deck_estimation: DeckEstimation::Exact,
+            running_count,
+            cards_remaining,
+            tens_remaining,
+            count_lag: 0,
+            uncounted: VecDeque::new(),
+            name,
+        }
+    }
+
+    // Share of the unseen cards that are tens
+    fn tens_fraction(&self) -> f64 {
+        if self.cards_remaining == 0 { 0.0 } else { self.tens_remaining as f64 / self.cards_remaining as f64 }
+    }
+
+    // Never divides by less than half a deck, so the last few cards can't blow the count up
+    fn current_true_count(&self) -> f64 {
+        let decks = self.deck_estimation.estimate(self.cards_remaining).max(0.5);
+        self.system.true_count(self.running_count, decks)
+    }
+}
+
+impl PlayStrategy for CountingStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn determine_first_action(&self, player_hand: &Hand, dealer_upcard: &Card) -> Action {
+        let true_count = self.current_true_count();
+        if !player_hand.is_soft() && !player_hand.is_pair() {
+            let (total, upcard) = (player_hand.total(), dealer_upcard.value());
+            if let Some(index) = self.surrender_indices.iter().find(|index| (index.total, index.upcard) == (total, upcard)) {
+                if true_count >= index.true_count {
+                    return Action::Surrender;
+                }
+                return match self.base.determine_first_action(player_hand, dealer_upcard) {
+                    Action::Surrender => self.base.determine_action(player_hand, dealer_upcard),
+                    action => action,
+                };
+            }
+            match (total, upcard) {
+                (10, 10 | 11) if true_count >= 4.0 => return Action::DoubleDown,
+                (9, 2) if true_count >= 1.0 => return Action::DoubleDown,
+                (9, 7) if true_count >= 3.0 => return Action::DoubleDown,
+                _ => {}
+            }
+        }
+        self.base.determine_first_action(player_hand, dealer_upcard)
+    }
+
+    fn determine_action(&self, player_hand: &Hand, dealer_upcard: &Card) -> Action {
+        let true_count = self.current_true_count();
+        if !player_hand.is_soft() {
+            match (player_hand.total(), dealer_upcard.value()) {
+                (16, 10) if true_count >= 0.0 => return Action::Stand,
+                (15, 10) if true_count >= 4.0 => return Action::Stand,
+                (12, 2) if true_count >= 3.0 => return Action::Stand,
+                (12, 3) if true_count >= 2.0 => return Action::Stand,
+                (12, 4) if true_count < 0.0 => return Action::Hit,
+                (13, 2) if true_count < -1.0 => return Action::Hit,
+                _ => {}
+            }
+        }
+        self.base.determine_action(player_hand, dealer_upcard)
+    }
+
+    // Insurance is a good bet once a third of the unseen cards are tens, about +3; the side
+    // count says so directly, where the true count only estimates it
+    fn take_insurance(&self, _player_hand: &Hand, _dealer_upcard: &Card) -> bool {
+        if self.insure_by_tens {
+            self.tens_fraction() > 1.0 / 3.0
+        } else {
+            self.current_true_count() >= self.insurance_true_count
+        }
+    }
+
+    // Even money pays the same as insuring the blackjack, so it goes by the same count
+    fn take_even_money(&self, player_hand: &Hand, dealer_upcard: &Card) -> bool {
+        self.take_insurance(player_hand, dealer_upcard)
+    }
+
+    fn observe_card(&mut self, card: &Card) {
+        self.uncounted.push_back(*card);
+        while self.uncounted.len() > self.count_lag {
+            let card = self.uncounted.pop_front().unwrap();
+            self.running_count += self.system.tag(&card);
+            self.cards_remaining = self.cards_remaining.saturating_sub(1);
+            if card.value() == 10 {
+                self.tens_remaining = self.tens_remaining.saturating_sub(1);
+            }
+        }
+    }
+
+    fn observe_shuffle(&mut self, shoe_cards: usize) {
+        self.uncounted.clear();
+        self.running_count = self.system.initial_running_count(shoe_cards);
+        self.cards_remaining = shoe_cards;
+        self.tens_remaining = shoe_cards * 4 / 13;
+    }
+
+    fn running_count(&self) -> Option<i32> {
+        Some(self.running_count)
+    }
+
+    fn true_count(&self) -> Option<f64> {
+        Some(self.current_true_count())
+    }
+}
+
+// Models human mistakes: with probability error_rate the base strategy's action is swapped
+// for a different legal one, so the cost of imperfect play shows up in the results
+struct ErrorProneStrategy {
+    base: Box<dyn PlayStrategy>,
+    error_rate: f64,
+    double_rule: DoubleRule,
+    // Seeded by the table, so a seeded session makes the same mistakes every run
+    rng: std::sync::Mutex<StdRng>,
+    name: String,
+}
+
+impl ErrorProneStrategy {
+    fn new(base: Box<dyn PlayStrategy>, error_rate: f64, double_rule: DoubleRule, seed: u64) -> ErrorProneStrategy {
+        let name = format!("{}, {:.1}% errors", base.name(), error_rate * 100.0);
+        let rng = std::sync::Mutex::new(StdRng::seed_from_u64(seed));
+        ErrorProneStrategy { base, error_rate, double_rule, rng, name }
+    }
+
+    fn maybe_err(&self, player_hand: &Hand, correct: Action, first_action: bool) -> Action {
+        let mut rng = self.rng.lock().unwrap();
+        if !rng.random_bool(self.error_rate) {
+            return correct;
+        }
+        let mut alternatives = vec![Action::Stand];
+        if player_hand.total() < 21 {
+            alternatives.push(Action::Hit);
+        }
+        if first_action && self.double_rule.allows(player_hand) {
+            alternatives.push(Action::DoubleDown);
+        }
+        alternatives.retain(|action| *action != correct);
+        if alternatives.is_empty() {
+            return correct;
+        }
+        alternatives.swap_remove(rng.random_range(0..alternatives.len()))
+    }
+}
+
+impl PlayStrategy for ErrorProneStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn determine_first_action(&self, player_hand: &Hand, dealer_upcard: &Card) -> Action {
+        self.maybe_err(player_hand, self.base.determine_first_action(player_hand, dealer_upcard), true)
+    }
+
+    fn determine_action(&self, player_hand: &Hand, dealer_upcard: &Card) -> Action {
+        self.maybe_err(player_hand, self.base.determine_action(player_hand, dealer_upcard), false)
+    }
+
+    fn determine_first_action_exposed(&self, player_hand: &Hand, dealer_hand: &Hand) -> Action {
+        self.maybe_err(player_hand, self.base.determine_first_action_exposed(player_hand, dealer_hand), true)
+    }
+
+    fn determine_action_exposed(&self, player_hand: &Hand, dealer_hand: &Hand) -> Action {
+        self.maybe_err(player_hand, self.base.determine_action_exposed(player_hand, dealer_hand), false)
+    }
+
+    fn rescue_double(&self, player_hand: &Hand, dealer_upcard: &Card) -> bool {
+        self.base.rescue_double(player_hand, dealer_upcard)
+    }
+
+    fn take_insurance(&self, player_hand: &Hand, dealer_upcard: &Card) -> bool {
+        self.base.take_insurance(player_hand, dealer_upcard)
+    }
+
+    fn take_even_money(&self, player_hand: &Hand, dealer_upcard: &Card) -> bool {
+        self.base.take_even_money(player_hand, dealer_upcard)
+    }
+
+    fn observe_card(&mut self, card: &Card) {
+        self.base.observe_card(card);
+    }
+
+    fn observe_shuffle(&mut self, shoe_cards: usize) {
+        self.base.observe_shuffle(shoe_cards);
+    }
+
+    fn running_count(&self) -> Option<i32> {
+        self.base.running_count()
+    }
+
+    fn true_count(&self) -> Option<f64> {
+        self.base.true_count()
+    }
+}
+
+// Basic strategy generated from the rules by brute-force EV, in an infinite deck where every
+// card value is equally likely except tens at four times the odds. Hands are (hard, ace)
+// pairs: the total counting aces as one, and whether an ace is there to count as eleven.
+// Exposed dealer cards, double rescue and splitting aren't modelled, so pairs are left out
+// rather than compared against EVs that ignore the split.
+fn card_odds() -> impl Iterator<Item = (u8, f64)> {
+    (2..=11).map(|value| (value, if value == 10 { 4.0 / 13.0 } else { 1.0 / 13.0 }))
+}
+
+fn draw(hard: u8, ace: bool, value: u8) -> (u8, bool) {
+    if value == 11 { (hard + 1, true) } else { (hard + value, ace) }
+}
+
+fn best_total(hard: u8, ace: bool) -> (u8, bool) {
+    if ace && hard + 10 <= 21 { (hard + 10, true) } else { (hard, false) }
+}
+
+// A hand in the (hard, ace) state, for asking the evaluator about it
+fn state_hand(hard: u8, ace: bool) -> Hand {
+    let mut values = Vec::new();
+    let mut remaining = hard;
+    if ace {
+        values.push(11);
+        remaining -= 1;
+    }
+    while remaining >= 12 {
+        values.push(10);
+        remaining -= 10;
+    }
+    match remaining {
+        0 => {}
+        1 => values.push(11),
+        11 => values.extend([9, 2]),
+        value => values.push(value),
+    }
+    hand_of(&values)
+}
+
+// Where the dealer finishes, over totals 17 to 26, given the upcard isn't under a blackjack
+struct DealerOdds {
+    finals: [f64; 32],
+    blackjack: f64,
+}
+
+impl DealerOdds {
+    fn new(rule: &DealerRule, evaluator: &dyn Evaluator, upcard: u8) -> DealerOdds {
+        let mut finals = [0.0; 32];
+        let mut blackjack = 0.0;
+        let (hard, ace) = draw(0, false, upcard);
+        for (hole, chance) in card_odds() {
+            if evaluator.is_natural(&hand_of(&[upcard, hole])) {
+                blackjack += chance;
+            } else {
+                let (hard, ace) = draw(hard, ace, hole);
+                DealerOdds::play_out(rule, evaluator, hard, ace, chance, &mut finals);
+            }
+        }
+        for final_chance in &mut finals {
+            *final_chance /= 1.0 - blackjack;
+        }
+        DealerOdds { finals, blackjack }
+    }
+
+    fn play_out(rule: &DealerRule, evaluator: &dyn Evaluator, hard: u8, ace: bool, chance: f64, finals: &mut [f64; 32]) {
+        let (total, soft) = best_total(hard, ace);
+        if evaluator.is_bust(&state_hand(hard, ace)) || !rule.should_hit_total(total, soft) {
+            finals[total as usize] += chance;
+            return;
+        }
+        for (value, draw_chance) in card_odds() {
+            let (hard, ace) = draw(hard, ace, value);
+            DealerOdds::play_out(rule, evaluator, hard, ace, chance * draw_chance, finals);
+        }
+    }
+}
+
+// Every action's EV for one upcard, per unit of the original bet
+struct ActionEvs<'a> {
+    rules: &'a RulesConfig,
+    evaluator: &'a dyn Evaluator,
+    dealer: DealerOdds,
+    unpeeked_blackjack: f64,
+    // Best of hit and stand from each (hard, ace) state, filled from high totals down
+    best: [[f64; 2]; 32],
+}
+
+impl<'a> ActionEvs<'a> {
+    fn new(rules: &'a RulesConfig, evaluator: &'a dyn Evaluator, upcard: u8) -> ActionEvs<'a> {
+        let dealer = DealerOdds::new(&rules.dealer, evaluator, upcard);
+        let upcard_card = Card { rank: if upcard == 11 { Rank::Ace } else { Rank::ALL[upcard as usize - 1] }, suit: Suit::Spades };
+        let unpeeked_blackjack = if rules.peeks_under(&upcard_card) { 0.0 } else { dealer.blackjack };
+        let mut evs = ActionEvs { rules, evaluator, dealer, unpeeked_blackjack, best: [[0.0; 2]; 32] };
+        for hard in (2..=21).rev() {
+            for ace in [false, true] {
+                evs.best[hard as usize][ace as usize] = evs.stand(hard, ace, 1.0).max(evs.hit(hard, ace));
+            }
+        }
+        evs
+    }
+
+    // Standing for `stake` units; a dealer blackjack found only now takes the doubled stake
+    // too, unless the table returns it
+    fn stand(&self, hard: u8, ace: bool, stake: f64) -> f64 {
+        let hand = state_hand(hard, ace);
+        let against_dealer: f64 = self.dealer.finals.iter().enumerate()
+            .filter(|(_, chance)| **chance > 0.0)
+            .map(|(dealer_total, chance)| {
+                let dealer_hand = state_hand(dealer_total as u8, false);
+                let outcome = if self.evaluator.is_bust(&dealer_hand) {
+                    if self.rules.dealer_push_on_22 && dealer_total == 22 { 0.0 } else { 1.0 }
+                } else {
+                    match self.evaluator.compare(&hand, &dealer_hand) {
+                        std::cmp::Ordering::Greater => 1.0,
+                        std::cmp::Ordering::Equal if !self.rules.dealer_wins_ties => 0.0,
+                        _ => -1.0,
+                    }
+                };
+                chance * outcome
+            })
+            .sum();
+        let blackjack_loss = if self.rules.original_bets_only { 1.0 } else { stake };
+        (1.0 - self.unpeeked_blackjack) * stake * against_dealer - self.unpeeked_blackjack * blackjack_loss
+    }
+
+    fn hit(&self, hard: u8, ace: bool) -> f64 {
+        card_odds()
+            .map(|(value, chance)| {
+                let (hard, ace) = draw(hard, ace, value);
+                chance * if self.evaluator.is_bust(&state_hand(hard, ace)) { -1.0 } else { self.best[hard as usize][ace as usize] }
+            })
+            .sum()
+    }
+
+    fn double(&self, hard: u8, ace: bool) -> f64 {
+        card_odds()
+            .map(|(value, chance)| {
+                let (hard, ace) = draw(hard, ace, value);
+                chance * if self.evaluator.is_bust(&state_hand(hard, ace)) { -2.0 } else { self.stand(hard, ace, 2.0) }
+            })
+            .sum()
+    }
+
+    fn ev(&self, hand: &Hand, action: &Action) -> f64 {
+        let hard = hand.cards.iter().map(|card| if card.rank == Rank::Ace { 1 } else { card.value() }).sum();
+        let ace = hand.cards.iter().any(|card| card.rank == Rank::Ace);
+        match action {
+            Action::Stand => self.stand(hard, ace, 1.0),
+            Action::Hit => self.hit(hard, ace),
+            Action::DoubleDown => self.double(hard, ace),
+            Action::Surrender => -0.5,
+            Action::Split => f64::NAN,
+        }
+    }
+}
+
+// One row and upcard of the generated chart, beside what the hardcoded chart plays there
+pub struct StrategyCell {
+    pub row: String,
+    pub first_decision: bool,
+    pub upcard: u8,
+    pub best: Action,
+    pub best_ev: f64,
+    pub chart: Action,
+    pub chart_ev: f64,
+}
+
+impl StrategyCell {
+    pub fn disagrees(&self) -> bool {
+        self.chart != self.best && self.chart_ev < self.best_ev - 1e-9
+    }
+}
+
+fn hand_of(values: &[u8]) -> Hand {
+    let mut hand = Hand::new();
+    for &value in values {
+        let rank = match value {
+            11 => Rank::Ace,
+            10 => Rank::Ten,
+            value => Rank::ALL[value as usize - 1],
+        };
+        hand.add_card(Card { rank, suit: Suit::Spades });
+    }
+    hand
+}
+
+// A representative two-card hand for every non-pair chart row
+fn chart_rows() -> Vec<(String, Hand)> {
+    let mut rows: Vec<(String, Hand)> = Vec::new();
+    rows.extend((5..=11).map(|total| (format!("Hard {}", total), hand_of(&[2, total - 2]))));
+    rows.extend((12..=19).map(|total| (format!("Hard {}", total), hand_of(&[10, total - 10]))));
+    // Two tens of different ranks, so strategies don't read it as a pair
+    let mut hard_20 = hand_of(&[10, 10]);
+    hard_20.cards[1].rank = Rank::King;
+    rows.push(("Hard 20".to_string(), hard_20));
+    rows.extend((13..=20).map(|total| (format!("Soft {}", total), hand_of(&[11, total - 11]))));
+    rows
+}
+
+// The first decision as the engine would apply it: a first-decision Hit or Stand, or a double
+// the rules refuse, defers to determine_action
+fn applied_first_action(strategy: &dyn PlayStrategy, hand: &Hand, upcard: &Card, rules: &RulesConfig) -> Action {
+    match strategy.determine_first_action(hand, upcard) {
+        Action::DoubleDown if rules.double_rule.allows(hand) => Action::DoubleDown,
+        Action::Surrender if rules.surrender != SurrenderRule::None => Action::Surrender,
+        Action::Split => Action::Split,
+        _ => strategy.determine_action(hand, upcard),
+    }
+}
+
+// Every chart row plus the pairs, against each upcard, as the active strategy plays them
+pub fn strategy_chart_csv(strategy: &dyn PlayStrategy, rules: &RulesConfig) -> String {
+    let mut rows = chart_rows();
+    rows.extend((2..=11).map(|value| {
+        let label = match value {
+            11 => "A".to_string(),
+            10 => "T".to_string(),
+            value => value.to_string(),
+        };
+        (format!("Pair {}", label), hand_of(&[value, value]))
+    }));
+    let mut csv = format!("# {} ({})\nhand,2,3,4,5,6,7,8,9,10,A\n", strategy.name(), rules.fingerprint());
+    for (row, hand) in rows {
+        let codes: Vec<_> = (2..=11)
+            .map(|upcard| applied_first_action(strategy, &hand, &hand_of(&[upcard]).cards[0], rules).code())
+            .collect();
+        csv.push_str(&format!("{},{}\n", row, codes.join(",")));
+    }
+    csv
+}
+
+// Checks the hardcoded BasicStrategy against EV-optimal play, both on the first decision
+// (where doubling and surrender are open) and after a hit (hit or stand only)
+pub fn generate_strategy_table(rules: &RulesConfig, evaluator: &dyn Evaluator) -> Vec<StrategyCell> {
+    let chart = BasicStrategy { ties_lose: rules.dealer_wins_ties, double_after_split: rules.double_after_split, ..BasicStrategy::new() };
+    let rows = chart_rows();
+    let mut cells = Vec::new();
+    for upcard in 2..=11 {
+        let evs = ActionEvs::new(rules, evaluator, upcard);
+        let upcard_card = hand_of(&[upcard]).cards[0];
+        for (row, hand) in &rows {
+            for first_decision in [true, false] {
+                let mut options = vec![Action::Stand, Action::Hit];
+                let chart_action = if first_decision {
+                    if rules.surrender != SurrenderRule::None {
+                        options.push(Action::Surrender);
+                    }
+                    if rules.double_rule.allows(hand) {
+                        options.push(Action::DoubleDown);
+                    }
+                    applied_first_action(&chart, hand, &upcard_card, rules)
+                } else {
+                    chart.determine_action(hand, &upcard_card)
+                };
+                let (best, best_ev) = options.into_iter()
+                    .map(|action| { let ev = evs.ev(hand, &action); (action, ev) })
+                    .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .unwrap();
+                cells.push(StrategyCell {
+                    row: row.clone(),
+                    first_decision,
+                    upcard,
+                    chart_ev: evs.ev(hand, &chart_action),
+                    chart: chart_action,
+                    best,
+                    best_ev,
+                });
+            }
+        }
+    }
+    cells
+}
+
+// Optional bets settled on the opening cards. resolve returns the net result per unit
+// staked: -1.0 for a loss, 25.0 for a 25:1 win.
+// Send + Sync so Monte Carlo threads can share the configured table
+pub trait SideBet: Send + Sync {
+    fn name(&self) -> &str;
+    fn resolve(&self, player_hand: &Hand, dealer_hand: &Hand, shoe: &Deck) -> f64;
+}
+
+// Pays on the player's first two cards forming a pair: mixed, coloured or perfect
+struct PerfectPairs;
+
+impl SideBet for PerfectPairs {
+    fn name(&self) -> &str {
+        "Perfect Pairs"
+    }
+
+    fn resolve(&self, player_hand: &Hand, _dealer_hand: &Hand, _shoe: &Deck) -> f64 {
+        let (first, second) = (&player_hand.cards[0], &player_hand.cards[1]);
+        let is_red = |card: &Card| matches!(card.suit, Suit::Hearts | Suit::Diamonds);
+        if first.rank != second.rank {
+            -1.0
+        } else if first.suit == second.suit {
+            25.0
+        } else if is_red(first) == is_red(second) {
+            12.0
+        } else {
+            6.0
+        }
+    }
+}
+
+// Poker hand from the player's two cards and the dealer's upcard. Payouts are to one and
+// default to a common paytable; aces play high or low in straights.
+struct TwentyOnePlusThree {
+    flush: f64,
+    straight: f64,
+    three_of_a_kind: f64,
+    straight_flush: f64,
+    suited_trips: f64,
+}
+
+impl Default for TwentyOnePlusThree {
+    fn default() -> Self {
+        Self { flush: 5.0, straight: 10.0, three_of_a_kind: 30.0, straight_flush: 40.0, suited_trips: 100.0 }
+    }
+}
+
+impl SideBet for TwentyOnePlusThree {
+    fn name(&self) -> &str {
+        "21+3"
+    }
+
+    fn resolve(&self, player_hand: &Hand, dealer_hand: &Hand, _shoe: &Deck) -> f64 {
+        let cards = [player_hand.cards[0], player_hand.cards[1], dealer_hand.cards[0]];
+        let mut ranks = cards.map(|card| card.rank.number());
+        ranks.sort_unstable();
+        let flush = cards.iter().all(|card| card.suit == cards[0].suit);
+        let trips = ranks[0] == ranks[2];
+        let straight = (ranks[1] == ranks[0] + 1 && ranks[2] == ranks[1] + 1) || ranks == [1, 12, 13];
+        match (trips, straight, flush) {
+            (true, _, true) => self.suited_trips,
+            (_, true, true) => self.straight_flush,
+            (true, _, false) => self.three_of_a_kind,
+            (_, true, false) => self.straight,
+            (_, false, true) => self.flush,
+            _ => -1.0,
+        }
+    }
+}
+
+// Every side bet the table offers, each starting switched off
+fn available_side_bets() -> Vec<(Box<dyn SideBet>, bool)> {
+    vec![
+        (Box::new(PerfectPairs), false),
+        (Box::new(TwentyOnePlusThree::default()), false),
+    ]
+}
+
+// A batch of hands run from the command line instead of the window, e.g.
+// `--simulate 100000 --decks 6 --bet 10`; anything not given keeps the table default
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationArgs {
+    pub hands: u32,
+    pub decks: Option<u8>,
+    pub bet: Option<f64>,
+    pub seed: Option<u64>,
+}
+
+impl SimulationArgs {
+    // None without --simulate, so the GUI starts as usual
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<SimulationArgs>, String> {
+        fn value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+            let value = value.ok_or_else(|| format!("{} needs a value", flag))?;
+            value.parse().map_err(|_| format!("Invalid value for {}: {}", flag, value))
+        }
+        let mut hands = None;
+        let mut decks = None;
+        let mut bet = None;
+        let mut seed = None;
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            match flag.as_str() {
+                "--simulate" => hands = Some(value(&flag, args.next())?),
+                "--decks" => decks = Some(value(&flag, args.next())?),
+                "--bet" => bet = Some(value(&flag, args.next())?),
+                "--seed" => seed = Some(value(&flag, args.next())?),
+                _ => return Err(format!("Unknown argument: {}", flag)),
+            }
+        }
+        if decks == Some(0) {
+            return Err("--decks must be at least 1".to_string());
+        }
+        if bet.is_some_and(|bet: f64| bet.is_nan() || bet <= 0.0) {
+            return Err("--bet must be positive".to_string());
+        }
+        match hands {
+            Some(hands) => Ok(Some(SimulationArgs { hands, decks, bet, seed })),
+            None if decks.is_some() || bet.is_some() || seed.is_some() => {
+                Err("--decks, --bet and --seed only apply with --simulate".to_string())
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+// Decks in the shoe unless the rules ask for another count
+const DEFAULT_DECKS: u8 = 6;
+
+// Where insurance turns positive for a Hi-Lo counter
+const DEFAULT_INSURANCE_TRUE_COUNT: f64 = 3.0;
+
+// Most spots a counting player opens beyond the first
+pub const MAX_EXTRA_SPOTS: usize = 2;
+
+// Insurance is capped at half the main bet
+pub const MAX_INSURANCE_FRACTION: f64 = 0.5;
+
+// Eleven cards is the most a hand can hold without busting, so the draw loops stop there
+// even if a custom strategy keeps asking for cards
+const MAX_HAND_CARDS: usize = 11;
+
+// Fewest cards a hand is ever started from, whatever the penetration: enough for the
+// player's hand and the dealer's to both reach the card cap without emptying the shoe.
+// Deep resplits could in principle need more; draw_card shuffles a fresh shoe if one runs dry.
+const MIN_CARDS_TO_DEAL: usize = 2 * MAX_HAND_CARDS;
+
+// The rough model of a counted game behind Kelly sizing and the theoretical edge: each true count point is worth about half
+// a percent to the player, off the rules' house edge (or a typical one when the rules have
+// no published figure), and a hand's variance is about 1.3 bets squared
+const EDGE_PER_TRUE_COUNT: f64 = 0.005;
+const KELLY_BASE_EDGE: f64 = 0.005;
+const KELLY_HAND_VARIANCE: f64 = 1.3;
+
+// Highest true count a recommended ramp has its own step for
+pub const MAX_RAMP_TRUE_COUNT: i32 = 6;
+const DEFAULT_RAMP_RISK_OF_RUIN: f64 = 0.05;
+
+// Three splits, so a pair can become at most four hands
+
+// Size of the tracked slug shown in the cards-seen window, one suit's worth of cards
+pub const SLUG_SIZE: usize = 13;
+
+// Share of decisions validate_action changes before the UI flags the strategy as suspect
+pub const ILLEGAL_ACTION_WARNING: f64 = 0.05;
+
+// How long the end-of-batch banner stays up
+pub const BANNER_DURATION: std::time::Duration = std::time::Duration::from_secs(5);
+
+// How many recent events the events window keeps
+pub const EVENT_LOG_LINES: usize = 200;
+
+const LOG_PATH: &str = "blackjack_log.txt";
+
+const JSON_LOG_PATH: &str = "blackjack_log.jsonl";
+
+pub const OUTCOME_HISTOGRAM_PATH: &str = "outcome_histogram.csv";
+
+pub const RESULTS_BY_COUNT_PATH: &str = "results_by_count.csv";
+
+// Most points the bankroll chart keeps before thinning them out
+const BANKROLL_HISTORY_POINTS: usize = 2000;
+// Widest window of recent hands the bankroll chart can scroll through
+pub const MAX_CHART_WINDOW: usize = 5000;
+// Most other strategies charted alongside the player's on the same shoes
+pub const MAX_WHAT_IFS: usize = 3;
+
+const STRATEGY_STATS_DIR: &str = "strategy_stats";
+
+pub const STRATEGY_CHART_PATH: &str = "strategy_chart.csv";
+
+pub const GAMES_CSV_PATH: &str = "games.csv";
+// Most recent games the per-game CSV keeps
+pub const GAME_ROWS_KEPT: usize = 10_000;
+
+pub const SESSION_PATH: &str = "session.json";
+
+// How long a frame may spend playing queued games before yielding to the UI
+const FRAME_BUDGET: std::time::Duration = std::time::Duration::from_millis(12);
+
+impl BlackjackApp {
+    // Plays a round, then the same round at each what-if table
+    pub fn play_game(&mut self) {
+        self.play_round();
+        for what_if in &mut self.what_ifs {
+            if what_if.check_bankroll() {
+                what_if.play_game();
+            }
+        }
+    }
+
+    fn play_round(&mut self) {
+        if self.needs_reshuffle() {
+            self.completed_shoes += 1;
+            self.completed_shoe_hands += self.hands_this_shoe;
+            self.change_shoe();
+        }
+        let bet_note = match self.place_bet() {
+            Ok(note) => note,
+            Err(note) => {
+                self.append_log(&note);
+                return;
+            }
+        };
+        self.hands_this_shoe += 1;
+        self.player_spots = self.open_spots();
+        self.round_true_count = self.strategy.true_count();
+        for seat in &mut self.other_seats {
+            seat.round_bet = seat.bet.min(seat.bankroll).max(self.table_min);
+        }
+        self.events.push(GameEvent::HandStarted { game: self.games_played + 1 });
+        self.rotate_log();
+        self.game_record = (self.logging_enabled && self.log_format == LogFormat::Json)
+            .then(|| GameRecord { game: self.games_played + 1, ..GameRecord::default() });
+
+        // Seats in the order they act: the player's spots before or after the rest, which keep
+        // other_seats order; a seat that can't cover the table minimum sits the round out
+        let first = self.first_spot();
+        let mut seats: Vec<SeatRound> = (0..self.player_spots + self.other_seats.len())
+            .map(|seat| SeatRound::new(self.other_seat(seat).is_none_or(|other| self.other_seats[other].bankroll >= self.table_min)))
+            .collect();
+        let mut dealer_hand = Hand::new();
+
+        match self.rules.deal_order {
+            DealOrder::Alternating => {
+                for round in &mut seats {
+                    if let Some(hand) = round.hands.first_mut() {
+                        self.deal_to(hand, false);
+                    }
+                }
+                self.deal_to(&mut dealer_hand, true);
+                for round in &mut seats {
+                    if let Some(hand) = round.hands.first_mut() {
+                        self.deal_to(hand, false);
+                    }
+                }
+                self.deal_hole_card(&mut dealer_hand);
+            }
+            DealOrder::PlayerFirst => {
+                for round in &mut seats {
+                    if let Some(hand) = round.hands.first_mut() {
+                        self.deal_to(hand, false);
+                        self.deal_to(hand, false);
+                    }
+                }
+                self.deal_to(&mut dealer_hand, true);
+                self.deal_hole_card(&mut dealer_hand);
+            }
+        }
+        self.resolve_side_bets(&seats[first].hands[0], &dealer_hand);
+        let upcard = dealer_hand.cards[0];
+        let insurance = if upcard.value() == 11
+            && self.evaluator.is_natural(&seats[first].hands[0])
+            && self.strategy.take_even_money(&seats[first].hands[0], &upcard)
+        {
+            // Paid before the peek, so the dealer's hole card no longer matters to this hand
+            let hand = &mut seats[first].hands[0];
+            hand.live = false;
+            hand.first_action = false;
+            seats[first].results[0] = Some(GameResult::EvenMoney);
+            "Player takes even money.\n".to_string()
+        } else {
+            self.offer_insurance(&seats[first].hands[0], &dealer_hand)
+        };
+
+        let mut log = String::new();
+        log.push_str(&format!("*** Game {} ***\n", self.games_played + 1));
+        if let Some(note) = bet_note {
+            log.push_str(&note);
+        }
+        for (seat, round) in seats.iter().enumerate() {
+            if let Some(hand) = round.hands.first() {
+                log.push_str(&format!("{}'s hand: {} (Total: {})\n", self.seat_name(seat), hand.display(self.card_style), hand.total()));
+                self.record_hand(seat, 0, hand);
+            }
+        }
+        let card_style = self.card_style;
+        if let Some(record) = &mut self.game_record {
+            record.dealer_cards = dealer_hand.cards.iter().map(|card| card.format(card_style)).collect();
+        }
+        if self.rules.dealer_exposes_both {
+            log.push_str(&format!("Dealer shows: {} (Total: {})\n", dealer_hand.display(self.card_style), dealer_hand.total()));
+        } else {
+            log.push_str(&format!("Dealer shows: {}\n", dealer_hand.cards[0].format(self.card_style)));
+        }
+        log.push_str(&insurance);
+
+        // Early surrender is decided before the dealer checks the hole card, so it holds
+        // even against a dealer blackjack
+        if self.rules.surrender == SurrenderRule::Early {
+            for (seat, round) in seats.iter_mut().enumerate() {
+                let Some(hand) = round.hands.first_mut() else {
+                    continue;
+                };
+                if !self.evaluator.is_natural(hand) && self.first_action(seat, hand, &dealer_hand) == Action::Surrender {
+                    self.record_action(seat, &Action::Surrender);
+                    self.record_play(seat, 0, Action::Surrender, hand, false);
+                    log.push_str(&format!("{} surrenders ({}).\n", self.seat_name(seat), self.rules.surrender.label().to_lowercase()));
+                    hand.live = false;
+                    hand.first_action = false;
+                    round.results[0] = Some(GameResult::Surrender);
+                }
+            }
+        }
+
+        // A peeked dealer blackjack ends the round before anyone acts, as does one the dealer
+        // checks for against a player's blackjack. It takes every bet but a blackjack's, which
+        // pushes, or under dealer-wins-ties is still paid.
+        let dealer_natural = self.evaluator.is_natural(&dealer_hand);
+        let natural_at_table = seats.iter().any(|round| round.hands.first().is_some_and(|hand| self.evaluator.is_natural(hand)));
+        if dealer_natural && (self.rules.peeks_under(&dealer_hand.cards[0]) || natural_at_table && !self.rules.dealer_wins_ties) {
+            log.push_str(&format!("Dealer's hand: {} (Total: {})\n", dealer_hand.display(self.card_style), dealer_hand.total()));
+            // The dealer's outcome only counts when it decided some hand, not one that had
+            // already surrendered or was paid out as a blackjack regardless
+            let mut contested = false;
+            for (seat, round) in seats.iter_mut().enumerate() {
+                if round.hands.is_empty() || round.results[0].is_some() {
+                    continue;
+                }
+                let (result, message) = match (self.evaluator.is_natural(&round.hands[0]), self.rules.dealer_wins_ties) {
+                    (true, true) => (GameResult::PlayerBlackjack, "Blackjack! Player wins!"),
+                    (true, false) => (GameResult::Push, "Both have Blackjack! Push!"),
+                    (false, _) => (GameResult::DealerWin, "Blackjack! Dealer wins!"),
+                };
+                log.push_str(&format!("{}{}\n", self.seat_prefix(seat), message));
+                contested |= result != GameResult::PlayerBlackjack;
+                round.results[0] = Some(result);
+            }
+            if contested {
+                self.dealer_outcomes.record(&dealer_hand, &*self.evaluator);
+            }
+            self.finish_round(seats, &dealer_hand, &log);
+            return;
+        }
+        for (seat, round) in seats.iter_mut().enumerate() {
+            if let Some(hand) = round.hands.first_mut()
+                && round.results[0].is_none()
+                && self.evaluator.is_natural(hand)
+            {
+                log.push_str(&format!("{}Blackjack! Player wins!\n", self.seat_prefix(seat)));
+                hand.live = false;
+                hand.first_action = false;
+                round.results[0] = Some(GameResult::PlayerBlackjack);
+            }
+        }
+
+        for (seat, round) in seats.iter_mut().enumerate() {
+            self.play_seat(seat, round, &dealer_hand, &mut log);
+        }
+
+        // A late surrender only stands once the dealer is known not to have blackjack; with
+        // no peek that's only settled now, and a hidden blackjack takes the whole bet
+        if self.rules.surrender == SurrenderRule::Late && dealer_natural {
+            for (seat, round) in seats.iter_mut().enumerate() {
+                for result in round.results.iter_mut().filter(|result| **result == Some(GameResult::Surrender)) {
+                    log.push_str(&format!("{}Dealer has Blackjack! The late surrender loses the whole bet.\n", self.seat_prefix(seat)));
+                    *result = Some(GameResult::DealerWin);
+                }
+            }
+        }
+        // Under OBO a hidden blackjack takes only the original bet: every split hand gets its
+        // stake back, even one that busted, and a busted double loses just the one bet
+        if dealer_natural && self.rules.original_bets_only {
+            for round in &mut seats {
+                for (number, result) in round.results.iter_mut().enumerate() {
+                    match result {
+                        Some(GameResult::DealerWin | GameResult::DoubledLose | GameResult::Rescued) if number > 0 => *result = Some(GameResult::Push),
+                        Some(GameResult::DoubledLose) => *result = Some(GameResult::DealerWin),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        let any_stood = seats.iter().flat_map(|round| &round.hands).any(|hand| hand.stood);
+
+        // Without a peek the hole card is only checked once the player has finished acting
+        if dealer_natural && any_stood {
+            log.push_str(&format!("Dealer's hand: {} (Total: {})\n", dealer_hand.display(self.card_style), dealer_hand.total()));
+            log.push_str("Dealer reveals Blackjack! Dealer wins!\n");
+            for round in &mut seats {
+                for (number, hand) in round.hands.iter().enumerate().filter(|(_, hand)| hand.stood) {
+                    // Under OBO only the original hand's bet is taken; split hands get their stakes back
+                    round.results[number] = Some(if self.rules.original_bets_only && number > 0 {
+                        GameResult::Push
+                    } else if hand.doubled && !self.rules.original_bets_only {
+                        GameResult::DoubledLose
+                    } else {
+                        GameResult::DealerWin
+                    });
+                }
+            }
+        } else {
+            self.reveal_hole_card();
+            // Once every hand has busted or surrendered the dealer's hand can't change the outcome
+            let dealer_plays = any_stood || self.rules.dealer_plays_out;
+            while dealer_plays && self.rules.dealer.should_hit(&dealer_hand) && dealer_hand.cards.len() < MAX_HAND_CARDS {
+                self.deal_to(&mut dealer_hand, true);
+                let card = dealer_hand.cards.last().unwrap().format(self.card_style);
+                log.push_str(&format!("Dealer hits: {} (Total: {})\n", card, dealer_hand.total()));
+                if let Some(record) = &mut self.game_record {
+                    record.dealer_draws.push(DrawRecord { card, total: dealer_hand.total() });
+                }
+                if self.evaluator.is_bust(&dealer_hand) {
+                    break;
+                }
+            }
+            let push_on_22 = self.rules.dealer_push_on_22 && dealer_hand.total() == 22;
+            if dealer_plays {
+                if push_on_22 && any_stood {
+                    log.push_str("Dealer makes 22! Push!\n");
+                } else if self.evaluator.is_bust(&dealer_hand) {
+                    log.push_str("Dealer busts!\n");
+                } else {
+                    log.push_str("Dealer stands.\n");
+                }
+                self.dealer_outcomes.record(&dealer_hand, &*self.evaluator);
+            }
+            log.push_str(&format!("Dealer's hand: {} (Total: {})\n", dealer_hand.display(self.card_style), dealer_hand.total()));
+            for (seat, round) in seats.iter_mut().enumerate() {
+                for (number, hand) in round.hands.iter().enumerate().filter(|(_, hand)| hand.stood) {
+                    let (result, message) = self.settle_against_dealer(hand, &dealer_hand, push_on_22);
+                    if round.hands.len() > 1 {
+                        log.push_str(&format!("{}: {}\n", self.hand_name(seat, number), message));
+                    } else {
+                        log.push_str(&format!("{}{}\n", self.seat_prefix(seat), message));
+                    }
+                    round.results[number] = Some(result);
+                }
+            }
+        }
+
+        self.finish_round(seats, &dealer_hand, &log);
+    }
+
+    // Plays out each of a seat's unsettled hands in turn. Splitting adds hands to the right
+    // of the one being played, each played in its own turn; results[i] holds the outcome of
+    // any hand settled before the dealer acts.
+    fn play_seat(&mut self, seat: usize, round: &mut SeatRound, dealer_hand: &Hand, log: &mut String) {
+        let who = self.seat_name(seat);
+        let mut index = 0;
+        while index < round.hands.len() {
+            if round.results[index].is_some() {
+                index += 1;
+                continue;
+            }
+            let mut player_hand = std::mem::take(&mut round.hands[index]);
+            // A hand split off to the right only gets its second card when its turn comes
+            if player_hand.cards.len() == 1 {
+                self.deal_to(&mut player_hand, false);
+                log.push_str(&format!("{}: {} (Total: {})\n", self.hand_name(seat, index), player_hand.display(self.card_style), player_hand.total()));
+                self.record_hand(seat, index, &player_hand);
+            }
+
+            while player_hand.first_action {
+                // Split aces take one card each, though another ace may be split again within
+                // the table's limit
+                let split_aces = player_hand.split && player_hand.cards[0].rank == Rank::Ace;
+                let can_resplit = player_hand.is_pair() && round.hands.len() <= self.rules.max_ace_splits as usize;
+                let action = (!split_aces || can_resplit).then(|| self.first_action(seat, &player_hand, dealer_hand));
+                let Some(action) = action.filter(|action| !split_aces || *action == Action::Split) else {
+                    log.push_str(&format!("Split aces take one card each. {} stands.\n", who));
+                    player_hand.live = false;
+                    player_hand.stood = true;
+                    player_hand.first_action = false;
+                    break;
+                };
+                if self.other_seat(seat).is_none() {
+                    self.log_basic_hint(log, &player_hand, dealer_hand, &action, true);
+                }
+                let action = self.validate_action(seat, &player_hand, dealer_hand, action, round.hands.len());
+                match action {
+                    Action::DoubleDown => {
+                        self.record_action(seat, &Action::DoubleDown);
+                        self.deal_to(&mut player_hand, false);
+                        self.record_play(seat, index, Action::DoubleDown, &player_hand, true);
+                        log.push_str(&format!("{} doubles down: {} (Total: {})\n", who, player_hand.cards.last().unwrap().format(self.card_style), player_hand.total()));
+                        player_hand.doubled = true;
+                        player_hand.live = false;
+                        if self.rules.double_rescue && self.seat_strategy(seat).rescue_double(&player_hand, &dealer_hand.cards[0]) {
+                            self.record_action(seat, &Action::Surrender);
+                            self.record_play(seat, index, Action::Surrender, &player_hand, false);
+                            log.push_str(&format!("{} rescues the double and surrenders.\n", who));
+                            round.results[index] = Some(GameResult::Rescued);
+                        } else if self.evaluator.is_bust(&player_hand) {
+                            log.push_str(&format!("{} busts!\n", who));
+                            round.results[index] = Some(GameResult::DoubledLose);
+                        } else {
+                            player_hand.stood = true;
+                        }
+                    }
+                    Action::Surrender => {
+                        self.record_action(seat, &Action::Surrender);
+                        self.record_play(seat, index, Action::Surrender, &player_hand, false);
+                        log.push_str(&format!("{} surrenders ({}).\n", who, self.rules.surrender.label().to_lowercase()));
+                        round.results[index] = Some(GameResult::Surrender);
+                        player_hand.live = false;
+                    }
+                    Action::Split => {
+                        self.record_action(seat, &Action::Split);
+                        let mut split_hand = Hand::new();
+                        split_hand.split = true;
+                        split_hand.add_card(player_hand.cards.pop().unwrap());
+                        player_hand.split = true;
+                        self.deal_to(&mut player_hand, false);
+                        self.record_play(seat, index, Action::Split, &player_hand, true);
+                        log.push_str(&format!("{} splits. {}: {} (Total: {})\n", who, self.hand_name(seat, index), player_hand.display(self.card_style), player_hand.total()));
+                        if self.rules.split_play_order == SplitPlayOrder::DealAcross {
+                            self.deal_to(&mut split_hand, false);
+                            log.push_str(&format!("{}: {} (Total: {})\n", self.hand_name(seat, index + 1), split_hand.display(self.card_style), split_hand.total()));
+                            self.record_hand(seat, index + 1, &split_hand);
+                        }
+                        round.hands.insert(index + 1, split_hand);
+                        round.results.insert(index + 1, None);
+                        // The new two-card hand gets a first decision of its own, which may split again
+                        continue;
+                    }
+                    _ => {
+                        log.push_str(&format!("{} chooses to hit or stand.\n", who));
+                    }
+                }
+                player_hand.first_action = false;
+            }
+
+            while player_hand.live {
+                if player_hand.cards.len() >= MAX_HAND_CARDS {
+                    log.push_str(&format!("Error: hand reached the maximum card count. {} stands.\n", who));
+                    player_hand.live = false;
+                    player_hand.stood = true;
+                    break;
+                }
+                let action = self.strategy_action(seat, &player_hand, dealer_hand);
+                if self.other_seat(seat).is_none() {
+                    self.log_basic_hint(log, &player_hand, dealer_hand, &action, false);
+                }
+                let action = self.validate_action(seat, &player_hand, dealer_hand, action, round.hands.len());
+                match action {
+                    Action::Hit => {
+                        self.record_action(seat, &Action::Hit);
+                        self.deal_to(&mut player_hand, false);
+                        self.record_play(seat, index, Action::Hit, &player_hand, true);
+                        log.push_str(&format!("{} hits: {} (Total: {})\n", who, player_hand.cards.last().unwrap().format(self.card_style), player_hand.total()));
+                        if self.evaluator.is_bust(&player_hand) {
+                            log.push_str(&format!("{} busts!\n", who));
+                            round.results[index] = Some(GameResult::DealerWin);
+                            player_hand.live = false;
+                        }
+                    }
+                    Action::Stand => {
+                        self.record_action(seat, &Action::Stand);
+                        self.record_play(seat, index, Action::Stand, &player_hand, false);
+                        log.push_str(&format!("{} stands.\n", who));
+                        player_hand.live = false;
+                        player_hand.stood = true;
+                    }
+                    _ => {
+                        self.record_action(seat, &Action::Stand);
+                        self.record_play(seat, index, Action::Stand, &player_hand, false);
+                        log.push_str(&format!("Invalid action during main turn. {} stands.\n", who));
+                        player_hand.live = false;
+                        player_hand.stood = true;
+                    }
+                }
+            }
+            round.hands[index] = player_hand;
+            index += 1;
+        }
+    }
+
+    // Logs the round and settles every hand at the table. Each hand the player's split
+    // produces is settled, and counted, as a game of its own; the other seats keep their
+    // own tallies.
+    fn finish_round(&mut self, seats: Vec<SeatRound>, dealer_hand: &Hand, log: &str) {
+        self.append_log(log);
+        let mut round_net = 0.0;
+        for (seat, round) in seats.into_iter().enumerate() {
+            for (number, (hand, result)) in round.hands.iter().zip(round.results).enumerate() {
+                let Some(result) = result else {
+                    continue;
+                };
+                if let Some(record) = &mut self.game_record {
+                    record.results.push(ResultRecord { seat, hand: number, result: result.clone() });
+                }
+                if let Some(other) = self.other_seat(seat) {
+                    self.other_seats[other].settle(&result, &self.rules);
+                    continue;
+                }
+                match result {
+                    GameResult::PlayerWin | GameResult::DoubledWin | GameResult::PlayerBlackjack | GameResult::EvenMoney => self.wins += 1,
+                    GameResult::Push => self.pushes += 1,
+                    _ => self.losses += 1,
+                }
+                self.games_played += 1;
+                round_net += hand_net(&result, self.round_bet, &self.rules);
+                self.settle_hand(seat - self.first_spot(), &result, hand, dealer_hand);
+                self.last_game_result = Some(result);
+            }
+        }
+        self.advance_progression(round_net);
+        self.append_record();
+    }
+
+    // Outcome of a stood hand once the dealer has finished drawing, with the line to log
+    pub fn settle_against_dealer(&self, hand: &Hand, dealer_hand: &Hand, push_on_22: bool) -> (GameResult, &'static str) {
+        let (win, lose) = if hand.doubled {
+            (GameResult::DoubledWin, GameResult::DoubledLose)
+        } else {
+            (GameResult::PlayerWin, GameResult::DealerWin)
+        };
+        if push_on_22 {
+            return (GameResult::Push, "Push!");
+        }
+        if self.evaluator.is_bust(dealer_hand) {
+            return (win, "Player wins!");
+        }
+        match self.evaluator.compare(hand, dealer_hand) {
+            std::cmp::Ordering::Greater => (win, "Player wins!"),
+            std::cmp::Ordering::Less => (lose, "Dealer wins!"),
+            std::cmp::Ordering::Equal if self.rules.dealer_wins_ties => (lose, "Tie goes to the dealer!"),
+            std::cmp::Ordering::Equal => (GameResult::Push, "Push!"),
+        }
+    }
+
+    // Up to half a bet against the dealer holding blackjack, offered under an ace. It is settled on
+    // the hole card straight away, peek or no peek, since nothing the player does can change
+    // it; returns the line for the hand log
+    fn offer_insurance(&mut self, player_hand: &Hand, dealer_hand: &Hand) -> String {
+        let upcard = dealer_hand.cards[0];
+        if upcard.value() != 11 {
+            return String::new();
+        }
+        if !self.strategy.take_insurance(player_hand, &upcard) {
+            return "Player declines insurance.\n".to_string();
+        }
+        let stake = self.round_bet * self.insurance_fraction.clamp(0.0, MAX_INSURANCE_FRACTION);
+        self.insurance_taken += 1;
+        self.insurance_wagered += stake;
+        let dealer_blackjack = self.evaluator.is_natural(dealer_hand);
+        self.insurance_result += if dealer_blackjack { stake * 2.0 } else { -stake };
+        format!("Player takes insurance for {}: {}.\n", self.format_amount(stake),
+            if dealer_blackjack { "dealer has blackjack, insurance pays 2:1" } else { "no dealer blackjack, insurance lost" })
+    }
+
+    fn resolve_side_bets(&mut self, player_hand: &Hand, dealer_hand: &Hand) {
+        for (side_bet, enabled) in &self.side_bets {
+            if *enabled {
+                self.side_bet_wagered += self.side_bet_amount;
+                self.side_bet_result += self.side_bet_amount * side_bet.resolve(player_hand, dealer_hand, &self.deck);
+            }
+        }
+    }
+
+    // Like a dealer shuffling the discards mid-round, an empty shoe is replaced rather than
+    // leaving the round unfinished; only long multi-split rounds past the cut card get here
+    fn draw_card(&mut self) -> Card {
+        if self.deck.cards.is_empty() {
+            // The hole card came out of the old shoe, so it is turned over and counted there
+            // before the count restarts, rather than landing in the new shoe's count
+            self.reveal_hole_card();
+            self.change_shoe();
+        }
+        self.deck.deal_card().unwrap()
+    }
+
+    fn deal_to(&mut self, hand: &mut Hand, to_dealer: bool) {
+        let card = self.draw_card();
+        hand.add_card(card);
+        self.observe_card(&card);
+        self.events.push(GameEvent::CardDealt { card, to_dealer });
+    }
+
+    // Unless the table exposes both dealer cards, the hole card is dealt without being seen;
+    // reveal_hole_card shows it at the dealer's turn, or when the hand settles without one
+    fn deal_hole_card(&mut self, hand: &mut Hand) {
+        if self.rules.dealer_exposes_both {
+            self.deal_to(hand, true);
+            return;
+        }
+        let card = self.draw_card();
+        hand.add_card(card);
+        self.hole_card = Some(card);
+        self.events.push(GameEvent::HoleCardDealt);
+    }
+
+    fn reveal_hole_card(&mut self) {
+        if let Some(card) = self.hole_card.take() {
+            self.observe_card(&card);
+            self.events.push(GameEvent::HoleCardRevealed { card });
+        }
+    }
+
+    // Every card turned face up is seen by every strategy at the table
+    fn observe_card(&mut self, card: &Card) {
+        self.strategy.observe_card(card);
+        for observer in &mut self.count_observers {
+            observer.observe_card(card);
+        }
+        for seat in &mut self.other_seats {
+            seat.strategy.observe_card(card);
+        }
+    }
+
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    // What the bet sizing asks to put down on the next hand, before the table's limits
+    fn stake(&self) -> f64 {
+        match self.bet_sizing {
+            BetSizing::Flat => self.bet_amount,
+            BetSizing::FixedFraction(fraction) => self.in_chips(self.bankroll * fraction),
+            BetSizing::Kelly(fraction) => self.kelly_stake(fraction),
+            BetSizing::Martingale => self.progression_bet.unwrap_or(self.bet_amount),
+            // Betting k times Kelly, the bankroll halves at some point with chance
+            // (1/2)^(2/k - 1), so k = 2 / (1 + log2(1 / risk))
+            BetSizing::ConstantRisk(risk) => self.kelly_stake(2.0 / (1.0 + (1.0 / risk.clamp(1e-6, 0.5)).log2())),
+            BetSizing::Ramp => self.ramp_stake(),
+        }
+    }
+
+    fn ramp_stake(&self) -> f64 {
+        match self.strategy.true_count() {
+            Some(_) if self.bet_ramp.is_empty() => self.bet_amount,
+            Some(true_count) => self.bet_ramp.iter()
+                .rev()
+                .find(|step| step.true_count <= true_count.floor() as i32)
+                .map_or(self.table_min, |step| step.bet),
+            None => self.bet_amount,
+        }
+    }
+
+    // The ramp for this bankroll and these rules at the given risk of ruin, for BetSizing::Ramp
+    pub fn recommend_ramp(&self, risk_of_ruin: f64) -> Vec<RampStep> {
+        let house_edge = self.rules.approximate_house_edge().unwrap_or(KELLY_BASE_EDGE);
+        recommended_ramp(risk_of_ruin, self.bankroll, EDGE_PER_TRUE_COUNT, house_edge, self.table_min, self.bet_increment)
+    }
+
+    // The player's expected result per unit bet at this true count, off the rules' edge at
+    // a neutral count
+    pub fn theoretical_edge(&self, true_count: f64) -> f64 {
+        let house_edge = self.rules.approximate_house_edge().unwrap_or(KELLY_BASE_EDGE);
+        edge_at_true_count(true_count, house_edge)
+    }
+
+    fn kelly_stake(&self, fraction: f64) -> f64 {
+        match self.strategy.true_count() {
+            Some(true_count) => {
+                let advantage = self.theoretical_edge(true_count);
+                self.in_chips(self.bankroll * fraction * advantage.max(0.0) / KELLY_HAND_VARIANCE)
+            }
+            None => self.bet_amount,
+        }
+    }
+
+    fn in_chips(&self, amount: f64) -> f64 {
+        (amount / self.bet_increment).floor() * self.bet_increment
+    }
+
+    // The stake, held to the bankroll share cap, then to the table minimum and the bankroll
+    // under the shortfall policy; returns the log line when the cap cut it, or Err with the
+    // line for a hand sat out
+    fn place_bet(&mut self) -> Result<Option<String>, String> {
+        let requested = self.stake();
+        let bet = self.capped_stake();
+        let capped = bet < requested;
+        self.round_bet = if bet > self.bankroll && self.bankroll >= self.table_min {
+            match self.bet_shortfall {
+                BetShortfallPolicy::TableMinimum => self.table_min,
+                BetShortfallPolicy::AllIn => self.bankroll,
+                // Sitting out leaves the progression where it was, so it would never be covered
+                BetShortfallPolicy::SitOut if self.progression_bet.take().is_some() => {
+                    let note = format!("Progression bet of {} can't be covered, starting over.\n", self.format_amount(bet));
+                    return self.place_bet().map(|capped| Some(note + capped.as_deref().unwrap_or("")));
+                }
+                BetShortfallPolicy::SitOut => {
+                    self.events.push(GameEvent::SatOut { requested: bet });
+                    return Err(format!("Player sits out, short of a {} bet.\n", self.format_amount(bet)));
+                }
+            }
+        } else {
+            bet.min(self.bankroll).max(self.table_min)
+        };
+        self.bet_short = self.round_bet < requested;
+        Ok(capped.then(|| {
+            self.events.push(GameEvent::BetCapped { requested, placed: self.round_bet });
+            format!("Bet of {} capped at {}.\n", self.format_amount(requested), self.format_amount(self.round_bet))
+        }))
+    }
+
+    // The next bet goes back to the Bet amount
+    pub fn reset_progression(&mut self) {
+        self.progression_bet = None;
+    }
+
+    // The stake held to the bankroll share cap
+    fn capped_stake(&self) -> f64 {
+        let requested = self.stake();
+        self.max_bet_fraction.map_or(requested, |fraction| requested.min(self.in_chips(self.bankroll * fraction)))
+    }
+
+    // Whether the player would sit out the next round. Nothing is dealt while they do, so
+    // the next round asks for the same bet and play can't go on until the bet changes.
+    pub fn short_of_bet(&self) -> bool {
+        self.bet_shortfall == BetShortfallPolicy::SitOut
+            && self.progression_bet.is_none()
+            && self.bankroll >= self.table_min
+            && self.capped_stake() > self.bankroll
+    }
+
+    // Moves the progression on from the round just settled, doubling what was actually placed
+    // rather than what was asked for
+    fn advance_progression(&mut self, round_net: f64) {
+        if self.bet_short && self.progression_shortfall == ProgressionShortfall::Reset {
+            self.progression_bet = None;
+        } else if round_net < 0.0 {
+            self.progression_bet = Some(self.round_bet * 2.0);
+        } else if round_net > 0.0 {
+            self.progression_bet = None;
+        }
+    }
+
+    // One spot, plus one more for each spot threshold the true count has reached, as many as
+    // the bankroll covers at round_bet each
+    fn open_spots(&self) -> usize {
+        let Some(true_count) = self.strategy.true_count() else {
+            return 1;
+        };
+        let wanted = 1 + self.spot_thresholds.iter().filter(|threshold| true_count >= **threshold).count();
+        let affordable = (self.bankroll / self.round_bet).floor() as usize;
+        wanted.min(affordable).max(1)
+    }
+
+    // The round's seat index of the player's first spot
+    fn first_spot(&self) -> usize {
+        match self.seat_position {
+            SeatPosition::FirstBase => 0,
+            SeatPosition::ThirdBase => self.other_seats.len(),
+        }
+    }
+
+    // A round's seat index into other_seats, or None for one of the player's spots
+    fn other_seat(&self, seat: usize) -> Option<usize> {
+        match self.seat_position {
+            SeatPosition::FirstBase => seat.checked_sub(self.player_spots),
+            SeatPosition::ThirdBase => (seat < self.other_seats.len()).then_some(seat),
+        }
+    }
+
+    // Other seats are numbered around the table, the player taking seat 1 at first base
+    pub fn other_seat_number(&self, other: usize) -> usize {
+        match self.seat_position {
+            SeatPosition::FirstBase => other + 2,
+            SeatPosition::ThirdBase => other + 1,
+        }
+    }
+
+    fn seat_strategy(&self, seat: usize) -> &dyn PlayStrategy {
+        match self.other_seat(seat) {
+            Some(other) => &*self.other_seats[other].strategy,
+            None => &*self.strategy,
+        }
+    }
+
+    // How the log names a seat: the player, each of the player's spots when there are
+    // several, or the other seats by position
+    fn seat_name(&self, seat: usize) -> String {
+        match self.other_seat(seat) {
+            Some(other) => format!("Seat {}", self.other_seat_number(other)),
+            None if self.player_spots == 1 => "Player".to_string(),
+            None => format!("Player spot {}", seat - self.first_spot() + 1),
+        }
+    }
+
+    // Start of a result line; a lone player spot reads as it always has
+    fn seat_prefix(&self, seat: usize) -> String {
+        match self.other_seat(seat) {
+            Some(other) => format!("Seat {}: ", self.other_seat_number(other)),
+            None if self.player_spots == 1 => String::new(),
+            None => format!("Spot {}: ", seat - self.first_spot() + 1),
+        }
+    }
+
+    fn hand_name(&self, seat: usize, index: usize) -> String {
+        match self.other_seat(seat) {
+            Some(other) => format!("Seat {} hand {}", self.other_seat_number(other), index + 1),
+            None if self.player_spots == 1 => format!("Hand {}", index + 1),
+            None => format!("Spot {} hand {}", seat - self.first_spot() + 1, index + 1),
+        }
+    }
+
+    // The action counts are the player's, like the rest of the session stats
+    fn record_action(&mut self, seat: usize, action: &Action) {
+        if self.other_seat(seat).is_none() {
+            self.action_counts.record(action);
+        }
+    }
+
+    fn first_action(&self, seat: usize, player_hand: &Hand, dealer_hand: &Hand) -> Action {
+        if self.rules.dealer_exposes_both {
+            self.seat_strategy(seat).determine_first_action_exposed(player_hand, dealer_hand)
+        } else if player_hand.split {
+            let is_split_aces = player_hand.cards[0].rank == Rank::Ace;
+            self.seat_strategy(seat).determine_split_action(player_hand, &dealer_hand.cards[0], is_split_aces)
+        } else {
+            self.seat_strategy(seat).determine_first_action(player_hand, &dealer_hand.cards[0])
+        }
+    }
+
+    fn strategy_action(&self, seat: usize, player_hand: &Hand, dealer_hand: &Hand) -> Action {
+        if self.rules.dealer_exposes_both {
+            self.seat_strategy(seat).determine_action_exposed(player_hand, dealer_hand)
+        } else {
+            self.seat_strategy(seat).determine_action(player_hand, &dealer_hand.cards[0])
+        }
+    }
+
+    // What basic strategy would play here at this table: a double or surrender the rules
+    // refuse falls back to its hit-or-stand play, as validate_action would apply it
+    fn basic_hint(&self, player_hand: &Hand, dealer_hand: &Hand, first_action: bool) -> Action {
+        let basic = &self.reference_strategy;
+        let exposed = self.rules.dealer_exposes_both;
+        let later_action = || match exposed {
+            true => basic.determine_action_exposed(player_hand, dealer_hand),
+            false => basic.determine_action(player_hand, &dealer_hand.cards[0]),
+        };
+        if !first_action {
+            return later_action();
+        }
+        let action = match exposed {
+            true => basic.determine_first_action_exposed(player_hand, dealer_hand),
+            false => basic.determine_first_action(player_hand, &dealer_hand.cards[0]),
+        };
+        match action {
+            Action::DoubleDown if !self.rules.double_rule.allows(player_hand) => later_action(),
+            Action::Surrender if player_hand.split || self.rules.surrender == SurrenderRule::None => later_action(),
+            action => action,
+        }
+    }
+
+    fn log_basic_hint(&self, log: &mut String, player_hand: &Hand, dealer_hand: &Hand, action: &Action, first_action: bool) {
+        if !self.show_basic_hint {
+            return;
+        }
+        let basic = self.basic_hint(player_hand, dealer_hand, first_action);
+        if basic != *action {
+            log.push_str(&format!("{} plays {:?}; basic strategy would {:?}.\n", self.strategy.name(), action, basic));
+        }
+    }
+
+    // Strategies only suggest an action; this enforces what the table actually allows and
+    // counts every suggestion of the player's it had to change
+    fn validate_action(&mut self, seat: usize, hand: &Hand, dealer_hand: &Hand, attempted: Action, hands_in_play: usize) -> Action {
+        let mut action = match attempted {
+            // Doubling is only offered on the first decision, never after a hit
+            Action::DoubleDown if !hand.first_action => Action::Hit,
+            // Surrender is only offered before the player draws, so a late one just stands
+            Action::Surrender if !hand.first_action => Action::Stand,
+            Action::Split if !hand.first_action => Action::Stand,
+            // A double the rules refuse falls back to the strategy's own hit-or-stand play,
+            // which for soft hands like soft 18 depends on the upcard
+            Action::DoubleDown if !self.rules.double_rule.allows(hand) || hand.split && !self.rules.double_after_split => {
+                self.strategy_action(seat, hand, dealer_hand)
+            }
+            // Surrender is only offered on the hand as dealt, not once it has been split, and
+            // only where the rules offer it at all; otherwise the strategy hits or stands
+            Action::Surrender if hand.split || self.rules.surrender == SurrenderRule::None => self.strategy_action(seat, hand, dealer_hand),
+            Action::Split if !hand.is_pair() || hands_in_play > self.rules.split_limit(hand) as usize => self.strategy_action(seat, hand, dealer_hand),
+            _ => attempted.clone(),
+        };
+        // Nothing can improve on the target, so a hit there is always a strategy bug
+        if action == Action::Hit && hand.total() == self.evaluator.target() {
+            action = Action::Stand;
+        }
+        if action != attempted && self.other_seat(seat).is_none() {
+            self.illegal_actions += 1;
+            self.events.push(GameEvent::IllegalAction { attempted, applied: action.clone() });
+        }
+        action
+    }
+
+    fn append_log(&self, log: &str) {
+        if !self.logging_enabled || self.log_format != LogFormat::Text {
+            return;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(LOG_PATH)
+            .unwrap();
+        writeln!(file, "{}", log).unwrap();
+    }
+
+    // Writes the finished game to the JSON log as a single line
+    fn append_record(&mut self) {
+        let Some(mut record) = self.game_record.take() else {
+            return;
+        };
+        record.bankroll = self.bankroll;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(JSON_LOG_PATH)
+            .unwrap();
+        writeln!(file, "{}", serde_json::to_string(&record).unwrap()).unwrap();
+    }
+
+    fn record_hand(&mut self, seat: usize, hand: usize, player_hand: &Hand) {
+        let card_style = self.card_style;
+        if let Some(record) = &mut self.game_record {
+            let cards = player_hand.cards.iter().map(|card| card.format(card_style)).collect();
+            record.hands.push(HandRecord { seat, hand, cards, total: player_hand.total() });
+        }
+    }
+
+    // drew says whether the action dealt the hand's last card
+    fn record_play(&mut self, seat: usize, hand: usize, action: Action, player_hand: &Hand, drew: bool) {
+        let card_style = self.card_style;
+        if let Some(record) = &mut self.game_record {
+            let card = drew.then(|| player_hand.cards.last().unwrap().format(card_style));
+            record.actions.push(PlayRecord { seat, hand, action, card, total: player_hand.total() });
+        }
+    }
+
+    fn rotate_log(&mut self) {
+        if !self.logging_enabled {
+            return;
+        }
+        if self.log_rotation.is_some_and(|rotation| rotation.rotate(self.log_hands, self.log_format.path())) {
+            self.log_hands = 0;
+        }
+        self.log_hands += 1;
+    }
+
+    // One unit is the table minimum, so unit mode reads independently of stake size
+    pub fn format_amount(&self, amount: f64) -> String {
+        if self.show_units {
+            format!("{:.2} units", amount / self.table_min)
+        } else {
+            format!("${:.2}", amount)
+        }
+    }
+
+    // Plays the next auto-play hand if it's due; returns how long until the one after, for
+    // the UI to schedule a repaint
+    pub fn run_autoplay(&mut self) -> Option<std::time::Duration> {
+        let now = std::time::Instant::now();
+        let autoplay = self.autoplay.as_ref()?;
+        if autoplay.is_due(now) {
+            if !self.check_bankroll() {
+                self.autoplay = None;
+                self.show_summary = true;
+                return None;
+            }
+            self.play_game();
+            if let Some(autoplay) = &mut self.autoplay {
+                autoplay.mark_played(now);
+            }
+        }
+        match &self.autoplay {
+            Some(autoplay) if autoplay.remaining > 0 => Some(autoplay.wait(now)),
+            _ => {
+                self.autoplay = None;
+                None
+            }
+        }
+    }
+
+    // A batch already running keeps its original starting point
+    pub fn start_batch(&mut self) {
+        if self.batch_start.is_none() {
+            self.batch_start = Some(BatchStart::capture(self));
+        }
+    }
+
+    // Queues whole shoes: the batch starts on a fresh shoe and ends at a cut card
+    pub fn start_shoe_batch(&mut self, shoes: u32) {
+        self.reshuffle();
+        self.pending_shoes = shoes;
+        self.start_batch();
+    }
+
+    // The seed only drives strategies that make random choices
+    pub fn build_strategy(&self, seed: u64) -> Box<dyn PlayStrategy> {
+        let strategy: Box<dyn PlayStrategy> = match self.strategy_kind {
+            StrategyKind::StandOn | StrategyKind::DealerMimic | StrategyKind::NeverBust => {
+                simple_strategy(self.strategy_kind, self.strategy_stand_total, &self.rules)
+            }
+            StrategyKind::Basic => {
+                let mut strategy = match self.soft_stand_total {
+                    Some(soft_stand_total) => BasicStrategy::with_soft_stand(soft_stand_total),
+                    None => BasicStrategy::new(),
+                };
+                strategy.ties_lose = self.rules.dealer_wins_ties;
+                strategy.double_after_split = self.rules.double_after_split;
+                if self.hi_lo {
+                    let mut counter = CountingStrategy::new(strategy, self.count_system, self.insurance_true_count);
+                    counter.insure_by_tens = self.insure_by_tens;
+                    counter.surrender_indices = self.surrender_indices.clone();
+                    counter.count_lag = self.count_lag;
+                    counter.deck_estimation = self.deck_estimation;
+                    Box::new(counter)
+                } else {
+                    Box::new(strategy)
+                }
+            }
+        };
+        match self.play_error_rate {
+            Some(error_rate) => Box::new(ErrorProneStrategy::new(strategy, error_rate, self.rules.double_rule, seed)),
+            None => strategy,
+        }
+    }
+
+    // Scores every hand from here on under a variant's evaluator; sessions run from this
+    // table share it
+    pub fn set_evaluator(&mut self, evaluator: Box<dyn Evaluator>) {
+        self.evaluator = evaluator.into();
+    }
+
+    pub fn evaluator(&self) -> &dyn Evaluator {
+        &*self.evaluator
+    }
+
+    // Installs a strategy mid-shoe, catching a counting strategy up on the cards already seen
+    pub fn set_strategy(&mut self, mut strategy: Box<dyn PlayStrategy>) {
+        strategy.observe_shuffle(self.deck.cards.len() + self.deck.dealt.len());
+        for card in &self.deck.dealt {
+            strategy.observe_card(card);
+        }
+        self.strategy = strategy;
+    }
+
+    // Starts keeping another count beside the strategy's, caught up on the cards already seen
+    pub fn add_count_observer(&mut self, system: CountSystem) {
+        let mut observer = CountObserver::new(system, self.deck.cards.len() + self.deck.dealt.len());
+        for card in &self.deck.dealt {
+            observer.observe_card(card);
+        }
+        self.count_observers.push(observer);
+    }
+
+    pub fn rebuild_strategy(&mut self) {
+        let seed = self.rng.random();
+        self.set_strategy(self.build_strategy(seed));
+        self.rebuild_seat_strategies();
+    }
+
+    // After a seat's strategy or the rules change; like set_strategy, each is caught up on
+    // the cards already seen
+    pub fn rebuild_seat_strategies(&mut self) {
+        for seat in &mut self.other_seats {
+            let mut strategy = simple_strategy(seat.strategy_kind, seat.stand_total, &self.rules);
+            strategy.observe_shuffle(self.deck.cards.len() + self.deck.dealt.len());
+            for card in &self.deck.dealt {
+                strategy.observe_card(card);
+            }
+            seat.strategy = strategy;
+        }
+    }
+
+    // Adds seats playing basic strategy at the player's bet, or stands the last ones up
+    pub fn set_seat_count(&mut self, count: usize) {
+        self.other_seats.truncate(count);
+        while self.other_seats.len() < count {
+            self.other_seats.push(Seat::new(StrategyKind::Basic, self.bet_amount, self.starting_bankroll, &self.rules));
+        }
+        self.rebuild_seat_strategies();
+    }
+
+    // Starts the table's random stream over: from the seed, or from the OS when unseeded.
+    // The shoe and any random strategy are rebuilt from it, so a seeded table replays
+    // the same hands from here on.
+    pub fn reseed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+        self.rng = table_rng(seed);
+        self.reshuffle();
+        self.rebuild_strategy();
+    }
+
+    // Finishing the player's count over the cards never dealt has to land on the system's
+    // full-shoe count; anything else means a card was counted twice or missed
+    fn check_count_drift(&self) {
+        // A lagging count is still catching up on the last few cards
+        let Some(running_count) = self.strategy.running_count().filter(|_| self.count_lag == 0) else {
+            return;
+        };
+        let shoe: Vec<Card> = self.deck.dealt.iter().chain(&self.deck.cards).copied().collect();
+        if !is_whole_decks(&shoe) {
+            return;
+        }
+        let unseen: i32 = self.deck.cards.iter().chain(&self.hole_card).map(|card| self.count_system.tag(card)).sum();
+        debug_assert_eq!(running_count + unseen, self.count_system.full_shoe_running_count(shoe.len()),
+            "{} count drifted over the shoe", self.count_system.label());
+    }
+
+    // Builds, shuffles and cuts a shoe with the current composition and shuffle method
+    fn new_shoe(&mut self) -> Deck {
+        let mut deck = self.shoe_preset.build_deck(self.rules.decks);
+        deck.shuffle_method = self.deck.shuffle_method;
+        deck.deal_position = self.deck.deal_position;
+        deck.shuffle(&mut self.rng);
+        deck.cut(self.cut_fraction);
+        deck
+    }
+
+    fn needs_reshuffle(&self) -> bool {
+        let shoe_cards = self.deck.cards.len() + self.deck.dealt.len();
+        self.deck.cards.len() < self.reshuffle_below(shoe_cards)
+    }
+
+    // The rules' cut card, moved up when the table is too full for it
+    pub fn reshuffle_below(&self, shoe_cards: usize) -> usize {
+        self.rules.reshuffle_below(shoe_cards).max(self.cards_for_a_round())
+    }
+
+    // Enough for every hand the round could deal, the dealer's and every spot the count could
+    // open included, to reach the card cap. Only a round heavy with splits can still run the
+    // shoe dry, and draw_card shuffles mid-hand for that.
+    fn cards_for_a_round(&self) -> usize {
+        let hands = 1 + self.spot_thresholds.len() + self.other_seats.len() + 1;
+        hands * MAX_HAND_CARDS
+    }
+
+    // How far play has got from the top of the shoe to the cut card, 0.0 to 1.0
+    pub fn shoe_progress(&self) -> f32 {
+        let shoe_cards = self.deck.cards.len() + self.deck.dealt.len();
+        let playable = shoe_cards.saturating_sub(self.reshuffle_below(shoe_cards)).max(1);
+        (self.deck.dealt.len() as f32 / playable as f32).min(1.0)
+    }
+
+    // Runs a Hi-Lo count through the current shoe from its first card to its last, in
+    // the order it is dealt, so the true-count division can be checked card by card
+    pub fn run_count_calibration(&self) -> Vec<CountStep> {
+        let shoe: Vec<Card> = self.deck.dealt.iter().chain(self.deck.cards.iter().rev()).copied().collect();
+        let mut counter = CountingStrategy::new(BasicStrategy::new(), self.count_system, self.insurance_true_count);
+        counter.observe_shuffle(shoe.len());
+        shoe.iter()
+            .enumerate()
+            .map(|(dealt, card)| {
+                counter.observe_card(card);
+                CountStep {
+                    card: *card,
+                    running_count: counter.running_count,
+                    cards_remaining: shoe.len() - dealt - 1,
+                    true_count: counter.current_true_count(),
+                }
+            })
+            .collect()
+    }
+
+    // A shuffle asked for off the table, which every what-if table follows onto the new shoe
+    pub fn reshuffle(&mut self) {
+        self.change_shoe();
+        for what_if in &mut self.what_ifs {
+            what_if.queued_shoes = self.queued_shoes.clone();
+            what_if.queued_shoes.push(self.deck.clone());
+            what_if.reshuffle();
+        }
+    }
+
+    fn change_shoe(&mut self) {
+        if cfg!(debug_assertions) {
+            self.check_count_drift();
+        }
+        self.hands_this_shoe = 0;
+        if self.reset_progression_on_shuffle {
+            self.reset_progression();
+        }
+        self.deck = self.queued_shoes.pop().unwrap_or_else(|| self.new_shoe());
+        self.share_next_shoe();
+        self.strategy.observe_shuffle(self.deck.cards.len());
+        for seat in &mut self.other_seats {
+            seat.strategy.observe_shuffle(self.deck.cards.len());
+        }
+        for observer in &mut self.count_observers {
+            *observer = CountObserver::new(observer.system, self.deck.cards.len());
+        }
+        self.events.push(GameEvent::Reshuffled);
+        if self.log_shoe_order {
+            self.append_log(&format!("*** Shuffle ***\nShoe from the top: {}\n", self.deck.order_from_top(self.card_style)));
+        }
+    }
+
+    // With what-if tables running, the shoe after this one is shuffled ahead and queued at
+    // every table, so a what-if table that reaches its cut card first still deals it next
+    fn share_next_shoe(&mut self) {
+        if self.what_ifs.is_empty() || !self.queued_shoes.is_empty() {
+            return;
+        }
+        let next = self.new_shoe();
+        for what_if in &mut self.what_ifs {
+            what_if.queued_shoes.insert(0, next.clone());
+        }
+        self.queued_shoes.push(next);
+    }
+
+    // Starts a table playing another strategy from here on: the same shoe at the same card,
+    // the same bankroll, bets and rules
+    pub fn add_what_if(&mut self, strategy_kind: StrategyKind) {
+        let seed = self.rng.random();
+        let mut what_if = self.new_session(seed);
+        what_if.strategy_kind = strategy_kind;
+        what_if.deck = self.deck.clone();
+        what_if.queued_shoes = self.queued_shoes.clone();
+        what_if.bankroll = self.bankroll;
+        what_if.peak_bankroll = self.bankroll;
+        what_if.games_played = self.games_played;
+        what_if.bankroll_history.record(self.games_played, self.bankroll);
+        what_if.rebuild_strategy();
+        self.what_ifs.push(what_if);
+        self.share_next_shoe();
+    }
+
+    // A fresh, unlogged session at the same table as this one, for Monte Carlo runs, drawing
+    // its cards from its own seed
+    fn new_session(&self, seed: u64) -> BlackjackApp {
+        let mut session = BlackjackApp {
+            shoe_preset: self.shoe_preset,
+            cut_fraction: self.cut_fraction,
+            rules: self.rules.clone(),
+            bet_amount: self.bet_amount,
+            table_min: self.table_min,
+            bet_increment: self.bet_increment,
+            bet_sizing: self.bet_sizing,
+            bet_ramp: self.bet_ramp.clone(),
+            max_bet_fraction: self.max_bet_fraction,
+            progression_shortfall: self.progression_shortfall,
+            reset_progression_on_shuffle: self.reset_progression_on_shuffle,
+            bet_shortfall: self.bet_shortfall,
+            spot_thresholds: self.spot_thresholds.clone(),
+            seat_position: self.seat_position,
+            strategy_kind: self.strategy_kind,
+            strategy_stand_total: self.strategy_stand_total,
+            soft_stand_total: self.soft_stand_total,
+            hi_lo: self.hi_lo,
+            count_system: self.count_system,
+            insurance_true_count: self.insurance_true_count,
+            insurance_fraction: self.insurance_fraction,
+            insure_by_tens: self.insure_by_tens,
+            surrender_indices: self.surrender_indices.clone(),
+            count_lag: self.count_lag,
+            deck_estimation: self.deck_estimation,
+            play_error_rate: self.play_error_rate,
+            cash_out_target: self.cash_out_target,
+            loss_limit: self.loss_limit,
+            trailing_stop: self.trailing_stop,
+            exact_cents: self.exact_cents,
+            evaluator: self.evaluator.clone(),
+            bankroll: self.starting_bankroll,
+            starting_bankroll: self.starting_bankroll,
+            peak_bankroll: self.starting_bankroll,
+            logging_enabled: false,
+            record_games: false,
+            seed: Some(seed),
+            rng: StdRng::seed_from_u64(seed),
+            ..BlackjackApp::default()
+        };
+        session.deck.shuffle_method = self.deck.shuffle_method;
+        session.deck.deal_position = self.deck.deal_position;
+        session.deck = session.new_shoe();
+        let strategy_seed = session.rng.random();
+        session.set_strategy(self.build_strategy(strategy_seed));
+        session.other_seats = self.other_seats.iter()
+            .map(|seat| Seat { stand_total: seat.stand_total, ..Seat::new(seat.strategy_kind, seat.bet, self.starting_bankroll, &self.rules) })
+            .collect();
+        session.rebuild_seat_strategies();
+        for ((_, enabled), (_, source)) in session.side_bets.iter_mut().zip(&self.side_bets) {
+            *enabled = *source;
+        }
+        session.side_bet_amount = self.side_bet_amount;
+        session
+    }
+
+    // Sessions are independent, so they're split as evenly as possible across the worker threads
+    pub fn run_monte_carlo(&mut self, sessions: u32, hands: u32) -> MonteCarloResult {
+        let threads = self.monte_carlo_threads.clamp(1, sessions.max(1) as usize) as u32;
+        // Session n is seeded with base_seed + n, whichever thread plays it
+        let base_seed: u64 = self.rng.random();
+        let table = &*self;
+        let mut results = std::thread::scope(|scope| {
+            let mut first = 0;
+            let workers: Vec<_> = (0..threads)
+                .map(|thread| {
+                    let share = sessions / threads + u32::from(thread < sessions % threads);
+                    let seeds = base_seed.wrapping_add(first as u64);
+                    first += share;
+                    scope.spawn(move || table.run_sessions(share, hands, seeds))
+                })
+                .collect();
+            workers.into_iter().map(|worker| worker.join().unwrap()).collect::<Vec<_>>().into_iter()
+        });
+        let mut combined = results.next().unwrap();
+        for result in results {
+            combined.combine(result);
+        }
+        combined
+    }
+
+    fn run_sessions(&self, sessions: u32, hands: u32, first_seed: u64) -> MonteCarloResult {
+        let mut final_bankrolls = Vec::with_capacity(sessions as usize);
+        let mut goals_reached = 0;
+        let mut loss_limits_hit = 0;
+        let mut trailing_stops_hit = 0;
+        for n in 0..sessions {
+            let mut session = self.new_session(first_seed.wrapping_add(n as u64));
+            for _ in 0..hands {
+                if !session.check_bankroll() {
+                    break;
+                }
+                session.play_game();
+            }
+            match session.stop_reason {
+                Some(StopReason::CashOut) => goals_reached += 1,
+                Some(StopReason::LossLimit) => loss_limits_hit += 1,
+                Some(StopReason::TrailingStop) => trailing_stops_hit += 1,
+                _ => {}
+            }
+            final_bankrolls.push(session.bankroll);
+        }
+        final_bankrolls.sort_by(f64::total_cmp);
+        MonteCarloResult { starting_bankroll: self.starting_bankroll, final_bankrolls, goals_reached, loss_limits_hit, trailing_stops_hit }
+    }
+
+    // Sets the table up for a command-line run and plays it out, stopping early if the
+    // bankroll does. Nothing is logged, as a large run would swamp the log file.
+    pub fn run_headless(&mut self, args: &SimulationArgs) {
+        self.logging_enabled = false;
+        self.record_games = false;
+        if let Some(decks) = args.decks {
+            self.rules.decks = decks;
+        }
+        if let Some(bet) = args.bet {
+            self.bet_amount = bet;
+        }
+        self.reseed(args.seed.or(self.seed));
+        #[cfg(feature = "profiling")]
+        let started = std::time::Instant::now();
+        for _ in 0..args.hands {
+            if !self.check_bankroll() {
+                break;
+            }
+            self.play_game();
+        }
+        #[cfg(feature = "profiling")]
+        {
+            self.last_profile = Some(BatchProfile { hands: self.games_played, elapsed: started.elapsed() });
+        }
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        if self.games_played == 0 { 0.0 } else { self.wins as f64 / self.games_played as f64 }
+    }
+
+    // Plays every saved rule set from the same sequence of shoes with the current strategy,
+    // so the differences between rows come from the rules rather than the cards
+    pub fn run_rule_comparison(&mut self) -> Vec<ComparisonRow> {
+        let shoe_cards = self.deck.cards.len() + self.deck.dealt.len();
+        let usable_cards = self.comparison_rules.iter()
+            .map(|rules| shoe_cards - rules.reshuffle_below(shoe_cards))
+            .min()
+            .unwrap_or(shoe_cards)
+            .max(1);
+        // About five and a half cards go out per hand, more with other seats playing; twice
+        // that many shoes leaves ample margin, and a session that still runs dry just
+        // shuffles its own
+        let cards_per_round = 11 * (self.other_seats.len() + 1 + self.spot_thresholds.len());
+        let shoe_count = self.comparison_hands as usize * cards_per_round / usable_cards + 2;
+        let shoes: Vec<Deck> = (0..shoe_count).map(|_| self.new_shoe()).collect();
+        let seeds: Vec<u64> = self.comparison_rules.iter().map(|_| self.rng.random()).collect();
+        self.comparison_rules.iter().zip(seeds)
+            .map(|(rules, seed)| {
+                let mut session = self.comparison_session(rules, seed, &shoes);
+                for _ in 0..self.comparison_hands {
+                    if !session.check_bankroll() {
+                        break;
+                    }
+                    session.play_game();
+                }
+                ComparisonRow {
+                    fingerprint: rules.fingerprint(),
+                    hands: session.games_played,
+                    net_won: session.net_won,
+                    total_wagered: session.total_wagered,
+                    approximate_edge: rules.approximate_house_edge(),
+                }
+            })
+            .collect()
+    }
+
+    // A session under one compared rule set, playing the current strategy from the shared shoes
+    fn comparison_session(&self, rules: &RulesConfig, seed: u64, shoes: &[Deck]) -> BlackjackApp {
+        let mut session = self.new_session(seed);
+        session.rules = rules.clone();
+        // A rule set with another deck count can't share the shoes, so it deals its own
+        if rules.decks == self.rules.decks && !shoes.is_empty() {
+            session.queued_shoes = shoes.to_vec();
+            session.deck = session.queued_shoes.pop().unwrap();
+        } else {
+            session.deck = session.new_shoe();
+        }
+        session.rebuild_strategy();
+        session.auto_reset_on_ruin = self.auto_reset_on_ruin;
+        session.top_up_on_ruin = self.top_up_on_ruin;
+        session
+    }
+
+    // Plays queued games or shoes for one frame's time budget so the panel keeps redrawing
+    // mid-batch. A queued shoe counts down when its cut card comes out.
+    pub fn run_pending_games(&mut self) {
+        let started = std::time::Instant::now();
+        #[cfg(feature = "profiling")]
+        let games_before = self.games_played;
+        while (self.pending_games > 0 || self.pending_shoes > 0) && started.elapsed() < FRAME_BUDGET {
+            if !self.check_bankroll() {
+                self.pending_games = 0;
+                self.pending_shoes = 0;
+                self.show_summary = true;
+                break;
+            }
+            self.play_game();
+            if self.pending_shoes > 0 {
+                if self.needs_reshuffle() {
+                    self.pending_shoes -= 1;
+                }
+            } else {
+                self.pending_games -= 1;
+            }
+        }
+        #[cfg(feature = "profiling")]
+        self.record_profile(self.games_played - games_before, started.elapsed());
+    }
+
+    // Throughput of the current or most recent batch
+    #[cfg(feature = "profiling")]
+    pub fn last_profile(&self) -> Option<&BatchProfile> {
+        self.last_profile.as_ref()
+    }
+
+    #[cfg(feature = "profiling")]
+    pub fn clear_profile(&mut self) {
+        self.last_profile = None;
+    }
+
+    #[cfg(feature = "profiling")]
+    fn record_profile(&mut self, hands: u32, elapsed: std::time::Duration) {
+        let profile = self.last_profile.get_or_insert(BatchProfile { hands: 0, elapsed: std::time::Duration::ZERO });
+        profile.hands += hands;
+        profile.elapsed += elapsed;
+        if self.pending_games == 0 && self.pending_shoes == 0 {
+            eprintln!("Batch throughput: {}", profile);
+        }
+    }
+
+    pub fn build_summary(&self) -> SessionSummary {
+        let main_net = self.main_net();
+        let house_edge = self.house_edge();
+        SessionSummary {
+            fingerprint: self.rules.fingerprint(),
+            rules: self.rules.describe(),
+            strategy: self.strategy.name().to_string(),
+            hands: self.games_played,
+            wins: self.wins,
+            losses: self.losses,
+            pushes: self.pushes,
+            net_result: self.net_result(),
+            main_net,
+            house_edge,
+            ev_per_100: self.ev_per_100(),
+            peak_bankroll: self.peak_bankroll,
+            max_drawdown: self.max_drawdown,
+            ruin_count: self.ruin_count,
+            cash_out_target: self.cash_out_target,
+            loss_limit: self.loss_limit,
+            trailing_stop: self.trailing_stop,
+            stop_reason: self.stop_reason,
+            total_topped_up: self.total_topped_up,
+            longest_win_streak: self.streaks.longest_win,
+            longest_loss_streak: self.streaks.longest_loss,
+            shoes_completed: self.completed_shoes,
+            hands_per_shoe: (self.completed_shoes > 0)
+                .then(|| self.completed_shoe_hands as f64 / self.completed_shoes as f64),
+            action_counts: self.action_counts.clone(),
+            dealer_outcomes: self.dealer_outcomes.clone(),
+            illegal_actions: self.illegal_actions,
+            side_bet_wagered: self.side_bet_wagered,
+            side_bet_net: self.side_bet_net,
+            insurance_taken: self.insurance_taken,
+            insurance_wagered: self.insurance_wagered,
+            insurance_net: self.insurance_net,
+            theoretical_loss: self.theoretical_loss(),
+            hands_per_hour: self.hands_per_hour,
+            session_hours: self.session_hours,
+            hourly_loss: expected_hourly_loss(house_edge, self.average_bet(), self.hands_per_hour),
+            big_bets: BigBetStats::new(main_net, self.average_bet(), self.largest_bet, &self.outcome_histogram),
+        }
+    }
+
+    fn session_stats(&self) -> LifetimeStats {
+        LifetimeStats {
+            hands: self.games_played,
+            wins: self.wins,
+            losses: self.losses,
+            pushes: self.pushes,
+            total_wagered: self.total_wagered,
+            net_won: self.net_won,
+        }
+    }
+
+    pub fn save_strategy_stats(&mut self) -> Result<LifetimeStats, String> {
+        let current = self.session_stats();
+        let path = strategy_stats_path(self.strategy.name());
+        let lifetime = merge_strategy_stats(&path, &self.rules.fingerprint(), &current.since(&self.stats_saved))?;
+        self.stats_saved = current;
+        Ok(lifetime)
+    }
+
+    // Money won or lost at the table, leaving out anything topped up or reset after a ruin
+    pub fn net_result(&self) -> f64 {
+        self.bankroll - self.starting_bankroll - self.total_topped_up
+    }
+
+    // The net result from the main bets alone; side bets and insurance are staked outside
+    // total_wagered, so they're kept out of the edge
+    pub fn main_net(&self) -> f64 {
+        self.net_result() - self.side_bet_net - self.insurance_net
+    }
+
+    // Element-of-risk edge: the share of every dollar wagered, doubles and split hands
+    // included, that the house has kept
+    pub fn house_edge(&self) -> f64 {
+        if self.total_wagered > 0.0 { -self.main_net() / self.total_wagered } else { 0.0 }
+    }
+
+    // Average main-game result per 100 hands, in dollars
+    pub fn ev_per_100(&self) -> f64 {
+        if self.games_played == 0 { 0.0 } else { self.main_net() * 100.0 / self.games_played as f64 }
+    }
+
+    pub fn save_session(&self) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&self.saved_session()).map_err(|err| err.to_string())?;
+        std::fs::write(SESSION_PATH, json).map_err(|err| format!("Could not write {}: {}", SESSION_PATH, err))
+    }
+
+    fn saved_session(&self) -> SavedSession {
+        SavedSession {
+            bankroll: self.bankroll,
+            starting_bankroll: self.starting_bankroll,
+            total_topped_up: self.total_topped_up,
+            games_played: self.games_played,
+            wins: self.wins,
+            losses: self.losses,
+            pushes: self.pushes,
+            total_wagered: self.total_wagered,
+            net_won: self.net_won,
+            strategy_kind: self.strategy_kind,
+            strategy_stand_total: self.strategy_stand_total,
+            soft_stand_total: self.soft_stand_total,
+            hi_lo: self.hi_lo,
+            play_error_rate: self.play_error_rate,
+            rules: self.rules.clone(),
+            shoe: self.save_shoe.then(|| self.deck.clone()),
+        }
+    }
+
+    // A table picking up where the last saved session left off; a missing file starts
+    // fresh, and an unreadable one starts fresh with the reason in session_status
+    pub fn restore_session() -> BlackjackApp {
+        let mut app = BlackjackApp::default();
+        match load_session(SESSION_PATH) {
+            Ok(Some(saved)) => app.resume(saved),
+            Ok(None) => {}
+            Err(err) => app.session_status = Some(format!("{}; starting a new session", err)),
+        }
+        app
+    }
+
+    // A kept shoe carries on where it was, the count rebuilt from the cards already dealt;
+    // otherwise the session resumes on a fresh shoe
+    fn resume(&mut self, saved: SavedSession) {
+        self.bankroll = saved.bankroll;
+        self.starting_bankroll = saved.starting_bankroll;
+        self.total_topped_up = saved.total_topped_up;
+        self.peak_bankroll = saved.bankroll;
+        self.games_played = saved.games_played;
+        self.wins = saved.wins;
+        self.losses = saved.losses;
+        self.pushes = saved.pushes;
+        self.total_wagered = saved.total_wagered;
+        self.net_won = saved.net_won;
+        self.strategy_kind = saved.strategy_kind;
+        self.strategy_stand_total = saved.strategy_stand_total;
+        self.soft_stand_total = saved.soft_stand_total;
+        self.hi_lo = saved.hi_lo;
+        self.play_error_rate = saved.play_error_rate;
+        self.rules = saved.rules;
+        self.reference_strategy.ties_lose = self.rules.dealer_wins_ties;
+        self.reference_strategy.double_after_split = self.rules.double_after_split;
+        match saved.shoe {
+            Some(shoe) => {
+                self.save_shoe = true;
+                self.deck = shoe;
+            }
+            None => self.reshuffle(),
+        }
+        self.rebuild_strategy();
+    }
+
+    fn average_bet(&self) -> f64 {
+        if self.games_played == 0 { 0.0 } else { self.total_wagered / self.games_played as f64 }
+    }
+
+    // What the table's edge says the player should have lost on the amount wagered so far
+    fn theoretical_loss(&self) -> Option<f64> {
+        if self.shoe_preset != ShoePreset::Standard {
+            return None;
+        }
+        self.rules.approximate_house_edge().map(|edge| self.total_wagered * edge)
+    }
+
+    // Returns whether play can continue, recording why it stopped otherwise; with auto-reset
+    // a ruin is counted and the bankroll restored
+    pub fn check_bankroll(&mut self) -> bool {
+        if self.cash_out_target.is_some_and(|target| self.bankroll >= target) {
+            self.stop_reason = Some(StopReason::CashOut);
+            return false;
+        }
+        if self.loss_limit.is_some_and(|limit| self.bankroll <= limit) {
+            self.stop_reason = Some(StopReason::LossLimit);
+            return false;
+        }
+        if self.trailing_stop.is_some_and(|trail| self.bankroll <= self.peak_bankroll - trail) {
+            self.stop_reason = Some(StopReason::TrailingStop);
+            return false;
+        }
+        if self.short_of_bet() {
+            self.stop_reason = Some(StopReason::ShortOfBet);
+            return false;
+        }
+        if self.bankroll >= self.table_min {
+            return true;
+        }
+        self.events.push(GameEvent::Bankrupt { bankroll: self.bankroll });
+        if let Some(top_up) = self.top_up_on_ruin.filter(|top_up| *top_up > 0.0) {
+            self.ruin_count += 1;
+            while self.bankroll < self.bet_amount.max(self.table_min) {
+                self.bankroll += top_up;
+                self.total_topped_up += top_up;
+            }
+            return true;
+        }
+        if self.auto_reset_on_ruin {
+            self.ruin_count += 1;
+            // The reset is money brought to the table, not won at it
+            self.total_topped_up += self.starting_bankroll - self.bankroll;
+            self.bankroll = self.starting_bankroll;
+            return true;
+        }
+        self.stop_reason = Some(StopReason::Bankrupt);
+        false
+    }
+
+    // Pays the hand and keeps its row for the per-game CSV
+    fn settle_hand(&mut self, spot: usize, result: &GameResult, player_hand: &Hand, dealer_hand: &Hand) {
+        self.pay_bet(result);
+        if self.record_games && (self.batch_start.is_none() || self.record_batch_games) {
+            if self.game_rows.len() >= GAME_ROWS_KEPT {
+                self.game_rows.pop_front();
+            }
+            self.game_rows.push_back(GameRow {
+                game: self.games_played,
+                spot,
+                player_total: player_hand.total(),
+                dealer_total: dealer_hand.total(),
+                result: result.clone(),
+                wagered: wagered_on(result, self.round_bet),
+                bankroll: self.bankroll,
+            });
+        }
+    }
+
+    pub fn game_rows_csv(&self) -> String {
+        let mut csv = format!("# {}\ngame,spot,player_total,dealer_total,result,wagered,bankroll\n", self.rules.fingerprint());
+        for row in &self.game_rows {
+            csv.push_str(&format!("{},{},{},{},{},{},{}\n", row.game, row.spot, row.player_total, row.dealer_total,
+                csv_field(&format!("{:?}", row.result)), csv_amount(row.wagered), csv_amount(row.bankroll)));
+        }
+        csv
+    }
+
+    pub fn pay_bet(&mut self, result: &GameResult) {
+        let bankroll_before = self.bankroll;
+        self.largest_bet = self.largest_bet.max(self.round_bet);
+        self.total_wagered += wagered_on(result, self.round_bet);
+        let main_net = hand_net(result, self.round_bet, &self.rules);
+        self.reveal_hole_card();
+        let side_net = std::mem::take(&mut self.side_bet_result);
+        let insurance_net = std::mem::take(&mut self.insurance_result);
+        if self.exact_cents {
+            // Whole cents are exact integers in an f64, so settling in i64 and converting
+            // back leaves every running total on an exact cent after each hand
+            let (main_cents, side_cents, insurance_cents) = (to_cents(main_net), to_cents(side_net), to_cents(insurance_net));
+            let round_cents = main_cents + side_cents + insurance_cents;
+            self.bankroll = from_cents(to_cents(bankroll_before) + round_cents);
+            self.side_bet_net = from_cents(to_cents(self.side_bet_net) + side_cents);
+            self.insurance_net = from_cents(to_cents(self.insurance_net) + insurance_cents);
+            self.net_won = from_cents(to_cents(self.net_won) + main_cents);
+        } else {
+            self.bankroll += main_net + side_net + insurance_net;
+            self.side_bet_net += side_net;
+            self.insurance_net += insurance_net;
+            self.net_won += main_net;
+        }
+        self.outcome_histogram.record(main_net / self.round_bet);
+        if let Some(true_count) = self.round_true_count {
+            self.results_by_count.record(true_count, result, main_net / self.round_bet);
+        }
+        self.bankroll_history.record(self.games_played, self.bankroll);
+        self.events.push(GameEvent::HandResolved { result: result.clone(), bankroll: self.bankroll });
+        if let Some(description) = self.streaks.record(result, self.streak_milestone) {
+            self.events.push(GameEvent::Milestone { description });
+        }
+        self.peak_bankroll = self.peak_bankroll.max(self.bankroll);
+        self.max_drawdown = self.max_drawdown.max(self.peak_bankroll - self.bankroll);
+    }
+}
+
+// What a hand staked on bet wins or loses
+fn hand_net(result: &GameResult, bet: f64, rules: &RulesConfig) -> f64 {
+    match result {
+        GameResult::PlayerWin => bet,
+        GameResult::DealerWin => -bet,
+        GameResult::Push => 0.0,
+        GameResult::PlayerBlackjack => bet * rules.blackjack_payout.ratio(),
+        // Only the net half-bet leaves the bankroll; the stake is never charged then refunded
+        GameResult::Surrender => -bet / 2.0,
+        GameResult::DoubledWin => bet * 2.0,
+        GameResult::DoubledLose => -bet * 2.0,
+        GameResult::Rescued => -bet * rules.double_rescue_loss,
+        GameResult::EvenMoney => bet,
+    }
+}
+
+fn wagered_on(result: &GameResult, bet: f64) -> f64 {
+    match result {
+        GameResult::DoubledWin | GameResult::DoubledLose | GameResult::Rescued => bet * 2.0,
+        _ => bet,
+    }
+}
+
+// Quotes a CSV field when it holds a comma, quote or line break, doubling any quotes
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Amounts in exports: always two decimals and a dot, whatever the display shows, so any
+// spreadsheet or script reads them the same; a rounded-away loss is written as 0.00
+fn csv_amount(amount: f64) -> String {
+    let formatted = format!("{:.2}", amount);
+    if formatted == "-0.00" { "0.00".to_string() } else { formatted }
+}
+
+fn table_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_os_rng(),
+    }
+}
+
+fn to_cents(amount: f64) -> i64 {
+    (amount * 100.0).round() as i64
+}
+
+fn from_cents(cents: i64) -> f64 {
+    cents as f64 / 100.0
+}
+
+// Real tables deal far slower than the simulator, so hourly figures use a modelled dealing rate
+fn expected_hourly_loss(edge: f64, average_bet: f64, hands_per_hour: u32) -> f64 {
+    edge * average_bet * hands_per_hour as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A seeded table that writes no log and keeps no per-game rows
+    fn quiet_app(seed: u64) -> BlackjackApp {
+        let mut app = BlackjackApp { logging_enabled: false, record_games: false, ..BlackjackApp::default() };
+        app.reseed(Some(seed));
+        app
+    }
+
+    fn card(rank: Rank) -> Card {
+        Card { rank, suit: Suit::Spades }
+    }
+
+    // Puts these cards on top of the shoe so they are the next dealt, in this order
+    fn stack(app: &mut BlackjackApp, cards: &[Card]) {
+        app.deck.cards.extend(cards.iter().rev());
+    }
+
+    #[test]
+    fn auto_reset_counts_each_ruin_and_restores_the_bankroll() {
+        let mut app = quiet_app(1);
+        app.auto_reset_on_ruin = true;
+        for ruins in 1..=3 {
+            app.bankroll = 0.0;
+            assert!(app.check_bankroll());
+            assert_eq!(app.ruin_count, ruins);
+            assert_eq!(app.bankroll, app.starting_bankroll);
+        }
+    }
+
+    #[test]
+    fn auto_reset_money_is_not_counted_as_winnings() {
+        let mut app = quiet_app(2);
+        app.auto_reset_on_ruin = true;
+        app.bet_amount = app.starting_bankroll;
+        app.bet_shortfall = BetShortfallPolicy::AllIn;
+        for _ in 0..500 {
+            assert!(app.check_bankroll());
+            app.play_game();
+        }
+        assert!(app.ruin_count >= 5, "only {} ruins", app.ruin_count);
+        assert!((app.net_result() - app.net_won).abs() < 1e-6, "net_result {} vs net_won {}", app.net_result(), app.net_won);
+        assert!(app.net_result() < 0.0);
+    }
+
+    #[test]
+    fn each_bankruptcy_tops_up_by_the_fixed_amount() {
+        let mut app = quiet_app(470);
+        app.starting_bankroll = app.bet_amount;
+        app.bankroll = app.bet_amount;
+        app.top_up_on_ruin = Some(app.bet_amount);
+        // 17 against a dealt 20 loses the whole bankroll each time
+        for ruins in 1..=2 {
+            stack(&mut app, &[Rank::Ten, Rank::Ten, Rank::Seven, Rank::Queen].map(card));
+            app.play_game();
+            assert_eq!(app.bankroll, 0.0);
+            assert!(app.check_bankroll());
+            assert_eq!(app.ruin_count, ruins);
+            assert_eq!(app.bankroll, app.bet_amount);
+        }
+        assert_eq!(app.total_topped_up, 2.0 * app.bet_amount);
+        assert_eq!(app.net_result(), -2.0 * app.bet_amount);
+    }
+
+    #[test]
+    fn reaching_the_cash_out_target_stops_the_session_as_a_success() {
+        let mut app = quiet_app(413);
+        app.cash_out_target = Some(app.starting_bankroll + app.bet_amount);
+        // 20 beats the dealer's 17, and the rest of the batch is never dealt
+        stack(&mut app, &[Rank::Ten, Rank::Seven, Rank::Ten, Rank::Queen].map(card));
+        app.pending_games = 10;
+        app.run_pending_games();
+        assert_eq!((app.games_played, app.pending_games), (1, 0));
+        assert_eq!(app.stop_reason, Some(StopReason::CashOut));
+        assert!(app.build_summary().to_string().contains(&format!("Stopped: {}", StopReason::CashOut.label())));
+
+        // Every Monte Carlo session that gets there is counted as reaching the goal
+        let mut app = quiet_app(413);
+        app.cash_out_target = Some(app.starting_bankroll + app.bet_amount);
+        let result = app.run_monte_carlo(40, 200);
+        assert!(result.goals_reached > 0);
+        let reached = result.final_bankrolls.iter().filter(|&&bankroll| bankroll >= app.starting_bankroll + app.bet_amount).count();
+        assert_eq!(result.goals_reached as usize, reached);
+    }
+
+    #[test]
+    fn a_session_stops_at_whichever_of_the_win_goal_and_loss_limit_it_reaches() {
+        let (win, loss) = ([Rank::Ten, Rank::Seven, Rank::Ten, Rank::Queen], [Rank::Ten, Rank::Ten, Rank::Seven, Rank::Queen]);
+        for (hands, reason) in [([win, win, loss], StopReason::CashOut), ([loss, loss, win], StopReason::LossLimit)] {
+            let mut app = quiet_app(418);
+            app.cash_out_target = Some(app.starting_bankroll + 2.0 * app.bet_amount);
+            app.loss_limit = Some(app.starting_bankroll - 2.0 * app.bet_amount);
+            for hand in hands.iter().rev() {
+                stack(&mut app, &hand.map(card));
+            }
+            app.pending_games = 10;
+            app.run_pending_games();
+            assert_eq!(app.games_played, 2, "{:?}", reason);
+            assert_eq!(app.stop_reason, Some(reason));
+            assert_eq!(app.pending_games, 0);
+        }
+    }
+
+    #[test]
+    fn falling_100_from_a_1200_high_trips_a_100_trailing_stop() {
+        let mut app = quiet_app(445);
+        app.bet_amount = 100.0;
+        app.trailing_stop = Some(100.0);
+        let (win, loss) = ([Rank::Ten, Rank::Seven, Rank::Ten, Rank::Queen], [Rank::Ten, Rank::Ten, Rank::Seven, Rank::Queen]);
+        for hand in [win, win, loss] {
+            assert!(app.check_bankroll());
+            stack(&mut app, &hand.map(card));
+            app.play_game();
+        }
+        assert_eq!((app.peak_bankroll, app.bankroll), (1200.0, 1100.0));
+        assert!(!app.check_bankroll());
+        assert_eq!(app.stop_reason, Some(StopReason::TrailingStop));
+    }
+
+    #[test]
+    fn hi_lo_takes_insurance_from_the_configured_true_count() {
+        let mut strategy = CountingStrategy::new(BasicStrategy::new(), CountSystem::HiLo, 3.0);
+        strategy.observe_shuffle(104);
+        let hand = hand_of(&[10, 7]);
+        strategy.running_count = 6;
+        assert!(strategy.take_insurance(&hand, &card(Rank::Ace)));
+        strategy.running_count = 4;
+        assert!(!strategy.take_insurance(&hand, &card(Rank::Ace)));
+
+        let mut cautious = CountingStrategy::new(BasicStrategy::new(), CountSystem::HiLo, 4.0);
+        cautious.observe_shuffle(104);
+        cautious.running_count = 6;
+        assert!(!cautious.take_insurance(&hand, &card(Rank::Ace)));
+    }
+
+    #[test]
+    fn insurance_pays_two_to_one_against_a_dealer_blackjack() {
+        let mut app = quiet_app(3);
+        app.hi_lo = true;
+        app.insurance_true_count = -100.0;
+        app.rebuild_strategy();
+        stack(&mut app, &[card(Rank::Ten), card(Rank::Ace), card(Rank::Seven), card(Rank::King)]);
+        let bankroll = app.bankroll;
+        app.play_game();
+        assert_eq!(app.insurance_taken, 1);
+        assert_eq!(app.insurance_net, app.bet_amount);
+        assert_eq!(app.last_game_result, Some(GameResult::DealerWin));
+        assert_eq!(app.bankroll, bankroll);
+    }
+
+    #[test]
+    fn partial_insurance_pays_two_to_one_on_the_insured_amount() {
+        let mut app = quiet_app(3);
+        app.hi_lo = true;
+        app.insurance_true_count = -100.0;
+        app.insurance_fraction = 0.25;
+        app.bet_amount = 20.0;
+        app.rebuild_strategy();
+        stack(&mut app, &[card(Rank::Ten), card(Rank::Ace), card(Rank::Seven), card(Rank::King)]);
+        app.play_game();
+        assert_eq!(app.insurance_wagered, 5.0);
+        assert_eq!(app.insurance_net, 10.0);
+        assert_eq!(app.last_game_result, Some(GameResult::DealerWin));
+        assert_eq!(app.bankroll, app.starting_bankroll - 10.0);
+    }
+
+    #[test]
+    fn lost_insurance_stays_out_of_the_main_result() {
+        let mut app = quiet_app(3);
+        app.hi_lo = true;
+        app.insurance_true_count = -100.0;
+        app.rebuild_strategy();
+        let hand = [card(Rank::Ten), card(Rank::Ace), card(Rank::Nine), card(Rank::Seven)];
+        stack(&mut app, &[hand, hand, hand].concat());
+        for _ in 0..3 {
+            app.play_game();
+            assert_eq!(app.last_game_result, Some(GameResult::PlayerWin));
+        }
+        assert_eq!(app.insurance_taken, 3);
+        assert_eq!(app.insurance_net, -1.5 * app.bet_amount);
+        assert_eq!(app.net_won, 3.0 * app.bet_amount);
+        assert_eq!(app.main_net(), 3.0 * app.bet_amount);
+        assert_eq!(app.net_result(), 1.5 * app.bet_amount);
+        assert_eq!(app.house_edge(), -1.0);
+    }
+
+    #[test]
+    fn game_rows_keep_only_the_most_recent_games() {
+        let mut app = quiet_app(5);
+        app.record_games = true;
+        app.bankroll = 1e9;
+        for _ in 0..GAME_ROWS_KEPT + 20 {
+            app.play_game();
+        }
+        assert_eq!(app.game_rows.len(), GAME_ROWS_KEPT);
+        assert_eq!(app.game_rows.back().unwrap().game, app.games_played);
+    }
+
+    #[test]
+    fn batches_skip_game_rows_unless_asked() {
+        let mut app = quiet_app(6);
+        app.record_games = true;
+        app.start_batch();
+        app.play_game();
+        assert!(app.game_rows.is_empty());
+        app.record_batch_games = true;
+        app.play_game();
+        assert_eq!(app.game_rows.len(), 1);
+    }
+
+    struct TargetTwenty;
+
+    impl Evaluator for TargetTwenty {
+        fn is_bust(&self, hand: &Hand) -> bool {
+            hand.total() > 20
+        }
+
+        fn is_natural(&self, hand: &Hand) -> bool {
+            !hand.split && hand.cards.len() == 2 && hand.total() == 20
+        }
+
+        fn compare(&self, player_hand: &Hand, dealer_hand: &Hand) -> std::cmp::Ordering {
+            player_hand.total().cmp(&dealer_hand.total())
+        }
+
+        fn target(&self) -> u8 {
+            20
+        }
+    }
+
+    // Nothing busts and nothing is out of reach, so only the card cap ends an always-hit hand
+    struct NeverBusts;
+
+    impl Evaluator for NeverBusts {
+        fn is_bust(&self, _hand: &Hand) -> bool {
+            false
+        }
+
+        fn is_natural(&self, _hand: &Hand) -> bool {
+            false
+        }
+
+        fn compare(&self, player_hand: &Hand, dealer_hand: &Hand) -> std::cmp::Ordering {
+            player_hand.total().cmp(&dealer_hand.total())
+        }
+
+        fn target(&self) -> u8 {
+            u8::MAX
+        }
+    }
+
+    #[test]
+    fn twenty_one_busts_under_a_target_of_twenty() {
+        let mut app = quiet_app(8);
+        app.set_evaluator(Box::new(TargetTwenty));
+        assert!(app.evaluator().is_bust(&hand_of(&[10, 6, 5])));
+        // 15 against a 9 hits into 21
+        stack(&mut app, &[card(Rank::Ten), card(Rank::Nine), card(Rank::Five), card(Rank::Seven), card(Rank::Six)]);
+        app.play_game();
+        assert_eq!(app.last_game_result, Some(GameResult::DealerWin));
+        // Two tens are the natural now, and the dealer's A-K is only 21
+        stack(&mut app, &[card(Rank::Ten), card(Rank::Ace), card(Rank::King), card(Rank::King)]);
+        app.play_game();
+        assert_eq!(app.last_game_result, Some(GameResult::PlayerBlackjack));
+    }
+
+    #[test]
+    fn basic_strategy_never_insures_or_takes_even_money() {
+        let strategy = BasicStrategy::new();
+        let ace = card(Rank::Ace);
+        assert!(!strategy.take_even_money(&hand_of(&[11, 10]), &ace));
+        for cards in [[11, 10], [10, 10], [10, 6], [8, 8], [11, 7]] {
+            assert!(!strategy.take_insurance(&hand_of(&cards), &ace));
+        }
+    }
+
+    #[test]
+    fn even_money_pays_one_to_one_whatever_the_hole_card() {
+        let mut app = quiet_app(3);
+        app.hi_lo = true;
+        app.insurance_true_count = -100.0;
+        app.rebuild_strategy();
+        for hole in [Rank::King, Rank::Seven] {
+            stack(&mut app, &[card(Rank::Ace), card(Rank::Ace), card(Rank::King), card(hole)]);
+            let bankroll = app.bankroll;
+            app.play_game();
+            assert_eq!(app.last_game_result, Some(GameResult::EvenMoney));
+            assert_eq!(app.bankroll, bankroll + app.bet_amount);
+        }
+        assert_eq!(app.insurance_taken, 0);
+        assert_eq!(app.total_wagered, 2.0 * app.bet_amount);
+    }
+
+    #[test]
+    fn hand_totals_count_one_ace_high_when_it_fits() {
+        let cases: [(&[u8], u8, bool); 10] = [
+            (&[11, 6], 17, true),
+            (&[11, 6, 10], 17, false),
+            (&[11, 11], 12, true),
+            (&[11, 11, 9], 21, true),
+            (&[11, 11, 10], 12, false),
+            (&[11, 11, 11, 11], 14, true),
+            (&[11, 11, 11, 11, 10, 7], 21, false),
+            (&[10, 6, 11], 17, false),
+            (&[5, 11, 4], 20, true),
+            (&[10, 10, 2], 22, false),
+        ];
+        for (cards, total, soft) in cases {
+            let hand = hand_of(cards);
+            assert_eq!((hand.total(), hand.is_soft()), (total, soft), "{:?}", cards);
+            // A soft total is the hard one with a single ace raised to 11
+            let hard: u8 = cards.iter().map(|&value| if value == 11 { 1 } else { value }).sum();
+            assert_eq!(hand.total(), if hand.is_soft() { hard + 10 } else { hard }, "{:?}", cards);
+        }
+    }
+
+    #[test]
+    fn only_an_unsplit_two_card_21_is_a_blackjack() {
+        assert!(hand_of(&[11, 10]).is_blackjack());
+        assert!(!hand_of(&[11, 5, 5]).is_blackjack());
+        assert!(!hand_of(&[7, 7, 7]).is_blackjack());
+        let mut split_hand = hand_of(&[11, 10]);
+        split_hand.split = true;
+        assert!(!split_hand.is_blackjack());
+        assert_eq!(split_hand.total(), 21);
+        assert!(hand_of(&[10, 10, 2]).is_busted());
+        assert!(!hand_of(&[11, 11, 10]).is_busted());
+    }
+
+    // Pearson's statistic for observed counts against an equal share each
+    fn chi_squared(counts: &[u32]) -> f64 {
+        let expected = counts.iter().sum::<u32>() as f64 / counts.len() as f64;
+        counts.iter().map(|&count| (count as f64 - expected).powi(2) / expected).sum()
+    }
+
+    #[test]
+    fn shuffled_first_cards_are_uniform() {
+        let mut rng = StdRng::seed_from_u64(464);
+        let fresh = Deck::new(1);
+        let mut by_card = [0u32; 52];
+        let mut by_rank = [0u32; 13];
+        for _ in 0..52 * 500 {
+            let mut deck = fresh.clone();
+            deck.shuffle(&mut rng);
+            let first = deck.deal_card().unwrap();
+            by_card[fresh.cards.iter().position(|card| *card == first).unwrap()] += 1;
+            by_rank[Rank::ALL.iter().position(|rank| *rank == first.rank).unwrap()] += 1;
+        }
+        // Well past the 0.01% points of chi-squared with 51 and 12 degrees of freedom (95.8
+        // and 37.7), so only a real bias fails
+        assert!(chi_squared(&by_card) < 100.0, "card chi-squared {}", chi_squared(&by_card));
+        assert!(chi_squared(&by_rank) < 40.0, "rank chi-squared {}", chi_squared(&by_rank));
+        // And a shuffle that leaves the top card where it was does fail
+        let mut stuck = [0u32; 52];
+        stuck[51] = 52 * 500;
+        assert!(chi_squared(&stuck) > 100.0);
+    }
+
+    #[test]
+    fn a_seeded_fisher_yates_repeats_and_a_riffle_keeps_every_card() {
+        let fresh = Deck::new(2);
+        let shuffled = |method, seed| {
+            let mut deck = Deck { shuffle_method: method, ..fresh.clone() };
+            deck.shuffle(&mut StdRng::seed_from_u64(seed));
+            deck
+        };
+        let deck = shuffled(ShuffleMethod::FisherYates, 400);
+        assert_eq!(deck.cards, shuffled(ShuffleMethod::FisherYates, 400).cards);
+        assert_eq!(deck.last_permutation, shuffled(ShuffleMethod::FisherYates, 400).last_permutation);
+        assert_ne!(deck.cards, shuffled(ShuffleMethod::FisherYates, 401).cards);
+
+        // The riffled order is a permutation of the shoe's positions, so each card is still
+        // there exactly once
+        let deck = shuffled(ShuffleMethod::Riffle(3), 400);
+        assert_ne!(deck.cards, fresh.cards);
+        let mut positions = deck.last_permutation.clone();
+        positions.sort_unstable();
+        assert!(positions.into_iter().eq(0..fresh.cards.len()));
+        for (card, &from) in deck.cards.iter().zip(&deck.last_permutation) {
+            assert_eq!(*card, fresh.cards[from]);
+        }
+    }
+
+    #[test]
+    fn a_shoe_built_without_tens_never_deals_one() {
+        let mut counts = [4u8; 13];
+        counts[9..].fill(0);
+        let mut deck = Deck::from_composition(counts);
+        assert_eq!(deck.cards.len(), 36);
+        deck.shuffle(&mut StdRng::seed_from_u64(365));
+        let mut app = quiet_app(365);
+        app.deck = deck;
+        app.play_game();
+        assert!(!app.deck.dealt.is_empty());
+        while let Some(card) = app.deck.deal_card() {
+            assert_ne!(card.value(), 10, "{:?}", card);
+        }
+        assert!(app.deck.dealt.iter().all(|card| card.value() != 10));
+        assert_eq!(app.deck.dealt.iter().filter(|card| card.rank == Rank::Nine).count(), 4);
+    }
+
+    #[test]
+    fn every_rank_keeps_its_value_and_name() {
+        let expected = [
+            (11, "A"), (2, "2"), (3, "3"), (4, "4"), (5, "5"), (6, "6"), (7, "7"),
+            (8, "8"), (9, "9"), (10, "10"), (10, "J"), (10, "Q"), (10, "K"),
+        ];
+        for (number, (&rank, (value, name))) in Rank::ALL.iter().zip(expected).enumerate() {
+            let card = Card { rank, suit: Suit::Hearts };
+            assert_eq!(rank.number() as usize, number + 1);
+            assert_eq!(card.value(), value, "{:?}", rank);
+            assert_eq!(card.name(), format!("{}♥", name));
+            assert_eq!(card.format(CardStyle::Ascii), format!("{}H", name));
+            assert_eq!(Card::from_code(&format!("{}H", name)), Some(card));
+        }
+    }
+
+    #[test]
+    fn a_cut_moves_the_top_of_the_shoe_to_the_bottom() {
+        let fresh = Deck::new(1);
+        let mut deck = fresh.clone();
+        deck.cut(0.25);
+        // The top 13 cards, the end of the list, now sit under the rest
+        let expected: Vec<Card> = fresh.cards[39..].iter().chain(&fresh.cards[..39]).copied().collect();
+        assert_eq!(deck.cards, expected);
+        for rank in Rank::ALL {
+            assert_eq!(deck.cards.iter().filter(|card| card.rank == rank).count(), 4);
+        }
+        let mut whole = fresh.clone();
+        whole.cut(1.0);
+        assert_eq!(whole.cards, fresh.cards);
+
+        // A cut after a shuffle keeps the recorded permutation lined up with the cards
+        deck.shuffle(&mut StdRng::seed_from_u64(416));
+        let before = deck.cards.clone();
+        deck.shuffle(&mut StdRng::seed_from_u64(417));
+        deck.cut(0.4);
+        let replayed: Vec<Card> = deck.last_permutation.iter().map(|&from| before[from]).collect();
+        assert_eq!(replayed, deck.cards);
+    }
+
+    #[test]
+    fn ascii_and_unicode_styles_name_the_ace_of_spades() {
+        let ace = card(Rank::Ace);
+        assert_eq!(ace.format(CardStyle::Ascii), "AS");
+        assert_eq!(ace.format(CardStyle::Unicode), "A♠");
+        assert_eq!(ace.name(), "A♠");
+        let ten = Card { rank: Rank::Ten, suit: Suit::Diamonds };
+        assert_eq!((ten.format(CardStyle::Ascii), ten.format(CardStyle::Unicode)), ("10D".to_string(), "10♦".to_string()));
+    }
+
+    #[test]
+    fn a_logged_shoe_replays_the_same_hands() {
+        let play = |app: &mut BlackjackApp| {
+            (0..30).map(|_| {
+                app.play_game();
+                (app.last_game_result.clone(), app.bankroll)
+            }).collect::<Vec<_>>()
+        };
+        let mut app = quiet_app(424);
+        let logged = app.deck.order_from_top(CardStyle::Ascii);
+        let hands = play(&mut app);
+
+        let cards: Vec<Card> = logged.split(' ').map(|code| Card::from_code(code).unwrap()).collect();
+        let mut replay = quiet_app(425);
+        replay.deck = Deck::from_cards(&cards);
+        assert_eq!(replay.deck.cards.len(), app.rules.shoe_cards());
+        assert_eq!(play(&mut replay), hands);
+        assert_eq!(replay.deck.dealt, app.deck.dealt);
+    }
+
+    #[test]
+    fn the_recorded_permutation_replays_the_shuffle_and_tracks_a_slug() {
+        let mut rng = StdRng::seed_from_u64(401);
+        let mut deck = Deck::new(1);
+        deck.shuffle(&mut rng);
+        for method in [ShuffleMethod::FisherYates, ShuffleMethod::Riffle(2)] {
+            deck.shuffle_method = method;
+            let before = deck.cards.clone();
+            deck.shuffle(&mut rng);
+            let replayed: Vec<Card> = deck.last_permutation.iter().map(|&from| before[from]).collect();
+            assert_eq!(replayed, deck.cards, "{:?}", method);
+
+            // The slug's depths are where its cards now sit, counted from the top
+            deck.cut(0.3);
+            let slug = 0..10;
+            let tops: Vec<usize> = deck.cards.iter().rev().enumerate()
+                .filter(|(_, card)| before[slug.clone()].contains(card))
+                .map(|(depth, _)| depth)
+                .collect();
+            assert_eq!(deck.slug_depths(slug), tops, "{:?}", method);
+        }
+    }
+
+    #[test]
+    fn seeded_sessions_replay_exactly_and_balance() {
+        let play = |seed| {
+            let mut app = quiet_app(seed);
+            let results: Vec<_> = (0..300).map(|_| { app.play_game(); app.last_game_result.clone() }).collect();
+            (app, results)
+        };
+        let (app, results) = play(77);
+        let (replay, replayed) = play(77);
+        assert_eq!(results, replayed);
+        assert_eq!(app.bankroll, replay.bankroll);
+        assert_eq!(app.wins + app.losses + app.pushes, app.games_played);
+        assert!((app.bankroll - app.starting_bankroll - app.net_won).abs() < 1e-6);
+        let shoe = app.deck.cards.len() + app.deck.dealt.len();
+        assert_eq!(shoe, 52 * app.rules.decks as usize);
+        let (other, _) = play(78);
+        assert_ne!(other.bankroll_history.points(), app.bankroll_history.points());
+    }
+
+    #[test]
+    fn stacked_shoes_play_out_to_their_results() {
+        let scenarios: [(&[Rank], GameResult, f64); 4] = [
+            // 11 against a 6 doubles into 20, and the dealer's 16 busts
+            (&[Rank::Five, Rank::Six, Rank::Six, Rank::Ten, Rank::Nine, Rank::Ten], GameResult::DoubledWin, 2.0),
+            (&[Rank::Ace, Rank::Ten, Rank::King, Rank::Seven], GameResult::PlayerBlackjack, 1.5),
+            (&[Rank::Ten, Rank::Ten, Rank::King, Rank::Queen], GameResult::Push, 0.0),
+            (&[Rank::Ten, Rank::Ten, Rank::Seven, Rank::Eight], GameResult::DealerWin, -1.0),
+        ];
+        for (ranks, result, bets) in scenarios {
+            let mut app = quiet_app(507);
+            let cards: Vec<Card> = ranks.iter().map(|&rank| card(rank)).collect();
+            stack(&mut app, &cards);
+            app.play_game();
+            assert_eq!(app.last_game_result, Some(result.clone()), "{:?}", ranks);
+            assert_eq!(app.bankroll, app.starting_bankroll + bets * app.bet_amount, "{:?}", ranks);
+        }
+    }
+
+    #[test]
+    fn pay_bet_settles_each_result_at_its_payout() {
+        let payouts = [
+            (GameResult::PlayerWin, 10.0, 10.0),
+            (GameResult::DealerWin, -10.0, -10.0),
+            (GameResult::Push, 0.0, 0.0),
+            (GameResult::PlayerBlackjack, 15.0, 12.0),
+            (GameResult::Surrender, -5.0, -5.0),
+            (GameResult::DoubledWin, 20.0, 20.0),
+            (GameResult::DoubledLose, -20.0, -20.0),
+            (GameResult::Rescued, -10.0, -10.0),
+            (GameResult::EvenMoney, 10.0, 10.0),
+        ];
+        for (result, three_to_two, six_to_five) in payouts {
+            for (payout, expected) in [(BlackjackPayout::ThreeToTwo, three_to_two), (BlackjackPayout::SixToFive, six_to_five)] {
+                let mut app = quiet_app(1);
+                app.rules.blackjack_payout = payout;
+                app.round_bet = 10.0;
+                app.pay_bet(&result);
+                assert_eq!(app.bankroll - app.starting_bankroll, expected, "{:?} at {}", result, payout.label());
+                let wagered = if matches!(result, GameResult::DoubledWin | GameResult::DoubledLose | GameResult::Rescued) { 20.0 } else { 10.0 };
+                assert_eq!(app.total_wagered, wagered);
+            }
+        }
+    }
+
+    #[test]
+    fn split_play_order_decides_which_hand_gets_which_card() {
+        // 8-8 against a 6: the first hand makes 11 and doubles, and the dealer's 16 busts
+        let shoe = [Rank::Eight, Rank::Six, Rank::Eight, Rank::Ten, Rank::Three, Rank::Ten, Rank::Nine, Rank::Ten];
+        for (order, totals) in [(SplitPlayOrder::HandByHand, [21, 17]), (SplitPlayOrder::DealAcross, [20, 18])] {
+            let mut app = quiet_app(375);
+            app.record_games = true;
+            app.rules.split_play_order = order;
+            let cards: Vec<Card> = shoe.iter().map(|&rank| card(rank)).collect();
+            stack(&mut app, &cards);
+            app.play_game();
+            let played: Vec<u8> = app.game_rows.iter().map(|row| row.player_total).collect();
+            assert_eq!(played, totals, "{:?}", order);
+            assert_eq!(app.game_rows[0].result, GameResult::DoubledWin);
+        }
+    }
+
+    #[test]
+    fn split_hands_stand_on_aces_and_never_surrender() {
+        let strategy = BasicStrategy::new();
+        let mut soft_16 = hand_of(&[11, 5]);
+        soft_16.split = true;
+        assert_eq!(strategy.determine_first_action(&soft_16, &card(Rank::Six)), Action::DoubleDown);
+        assert_eq!(strategy.determine_split_action(&soft_16, &card(Rank::Six), true), Action::Stand);
+        let mut hard_16 = hand_of(&[10, 6]);
+        hard_16.split = true;
+        assert_eq!(strategy.determine_first_action(&hard_16, &card(Rank::Ten)), Action::Surrender);
+        assert_eq!(strategy.determine_split_action(&hard_16, &card(Rank::Ten), false), Action::Hit);
+    }
+
+    #[test]
+    fn bankroll_window_slices_the_most_recent_games() {
+        let mut history = BankrollHistory::default();
+        assert!(history.last(50).is_empty());
+        for game in 1..=100 {
+            history.record(game, game as f64);
+        }
+        let window = history.last(30);
+        assert_eq!(window.len(), 30);
+        assert_eq!((window[0][0], window[29][0]), (71.0, 100.0));
+        assert_eq!(history.last(100).len(), 100);
+        assert_eq!(history.last(1000).len(), 100);
+        assert!(history.last(0).is_empty());
+        for game in 101..=MAX_CHART_WINDOW as u32 + 1000 {
+            history.record(game, game as f64);
+        }
+        let window = history.last(MAX_CHART_WINDOW + 1);
+        assert_eq!(window.len(), MAX_CHART_WINDOW);
+        assert_eq!(window[0][0], 1001.0);
+        // The thinned full history is left as it was
+        assert!(history.points().len() <= BANKROLL_HISTORY_POINTS);
+    }
+
+    #[test]
+    fn red_7_counts_only_the_red_sevens() {
+        let mut counter = CountingStrategy::new(BasicStrategy::new(), CountSystem::Red7, 3.0);
+        counter.observe_shuffle(6 * 52);
+        assert_eq!(counter.running_count(), Some(-12));
+        counter.observe_card(&Card { rank: Rank::Seven, suit: Suit::Hearts });
+        assert_eq!(counter.running_count(), Some(-11));
+        counter.observe_card(&Card { rank: Rank::Seven, suit: Suit::Diamonds });
+        assert_eq!(counter.running_count(), Some(-10));
+        counter.observe_card(&Card { rank: Rank::Seven, suit: Suit::Spades });
+        counter.observe_card(&Card { rank: Rank::Seven, suit: Suit::Clubs });
+        assert_eq!(counter.running_count(), Some(-10));
+        let mut hi_lo = CountingStrategy::new(BasicStrategy::new(), CountSystem::HiLo, 3.0);
+        hi_lo.observe_shuffle(6 * 52);
+        hi_lo.observe_card(&Card { rank: Rank::Seven, suit: Suit::Hearts });
+        assert_eq!(hi_lo.running_count(), Some(0));
+    }
+
+    #[test]
+    fn aces_and_other_pairs_have_their_own_split_limits() {
+        let played = |ranks: &[Rank], max_ace_splits: u8| {
+            let mut app = quiet_app(389);
+            app.record_games = true;
+            app.rules.max_ace_splits = max_ace_splits;
+            let cards: Vec<Card> = ranks.iter().map(|&rank| card(rank)).collect();
+            stack(&mut app, &cards);
+            app.play_game();
+            app.game_rows.iter().map(|row| row.player_total).collect::<Vec<u8>>()
+        };
+        // A-A against a 6 draws another ace, then 9, 10 and 7 for any further hands
+        let aces = [Rank::Ace, Rank::Six, Rank::Ace, Rank::Ten, Rank::Ace, Rank::Nine, Rank::Ten, Rank::Seven, Rank::Ten];
+        assert_eq!(played(&aces, 1), [12, 20]);
+        assert_eq!(played(&aces, 2), [20, 21, 18]);
+        let eights = [Rank::Eight, Rank::Six, Rank::Eight, Rank::Ten, Rank::Eight, Rank::Ten, Rank::Ten, Rank::Ten, Rank::Ten];
+        assert_eq!(played(&eights, 1), [18, 18, 18]);
+    }
+
+    #[test]
+    fn martingale_bets_are_capped_at_the_bankroll_share() {
+        let mut app = quiet_app(394);
+        app.bet_sizing = BetSizing::Martingale;
+        app.bet_amount = 100.0;
+        app.max_bet_fraction = Some(0.25);
+        let losing = [card(Rank::Ten), card(Rank::Ten), card(Rank::Seven), card(Rank::Eight)];
+        stack(&mut app, &losing);
+        app.play_game();
+        assert_eq!(app.progression_bet, Some(200.0));
+        stack(&mut app, &losing);
+        app.play_game();
+        assert_eq!(app.bankroll, 700.0);
+        // 400 is asked for, but only a quarter of the 700 left may go down
+        stack(&mut app, &losing);
+        app.play_game();
+        assert_eq!(app.round_bet, 170.0);
+        assert!(app.events.contains(&GameEvent::BetCapped { requested: 400.0, placed: 170.0 }));
+        assert_eq!(app.bankroll, 530.0);
+    }
+
+    #[test]
+    fn a_capped_martingale_bet_continues_or_resets_the_progression() {
+        let losing = [card(Rank::Ten), card(Rank::Ten), card(Rank::Seven), card(Rank::Eight)];
+        for (shortfall, next_bet, after_next) in [(ProgressionShortfall::Continue, 80.0, 80.0), (ProgressionShortfall::Reset, 10.0, 20.0)] {
+            let mut app = quiet_app(458);
+            app.bet_sizing = BetSizing::Martingale;
+            app.max_bet_fraction = Some(0.05);
+            app.progression_shortfall = shortfall;
+            for _ in 0..3 {
+                stack(&mut app, &losing);
+                app.play_game();
+            }
+            assert_eq!(app.progression_bet, Some(80.0));
+            // 80 is asked for but only 40 of the 930 left may go down
+            stack(&mut app, &losing);
+            app.play_game();
+            assert_eq!(app.round_bet, 40.0);
+            assert_eq!(app.stake(), next_bet, "{}", shortfall.label());
+            // Continuing is capped at 40 again and doubles that; starting over loses 10 and doubles it
+            stack(&mut app, &losing);
+            app.play_game();
+            assert_eq!(app.progression_bet, Some(after_next), "{}", shortfall.label());
+        }
+    }
+
+    #[test]
+    fn shortfall_policy_decides_a_bet_the_bankroll_cannot_cover() {
+        let losing = [card(Rank::Ten), card(Rank::Ten), card(Rank::Seven), card(Rank::Eight)];
+        for (policy, bankroll_after, games) in [
+            (BetShortfallPolicy::TableMinimum, 140.0, 1),
+            (BetShortfallPolicy::AllIn, 0.0, 1),
+            (BetShortfallPolicy::SitOut, 150.0, 0),
+        ] {
+            let mut app = quiet_app(395);
+            app.bankroll = 150.0;
+            app.bet_amount = 200.0;
+            app.bet_shortfall = policy;
+            stack(&mut app, &losing);
+            let cards = app.deck.cards.len();
+            app.play_game();
+            assert_eq!(app.bankroll, bankroll_after, "{}", policy.label());
+            assert_eq!(app.games_played, games, "{}", policy.label());
+            if games == 0 {
+                assert_eq!(app.deck.cards.len(), cards);
+                assert!(app.events.contains(&GameEvent::SatOut { requested: 200.0 }));
+            }
+        }
+    }
+
+    #[test]
+    fn a_shoe_batch_reshuffles_once_a_shoe_and_plays_each_to_its_cut_card() {
+        let mut app = quiet_app(447);
+        for _ in 0..5 {
+            app.play_game();
+        }
+        app.drain_events();
+        let (shoes, hands) = (app.completed_shoes, app.completed_shoe_hands);
+        app.start_shoe_batch(2);
+        while app.pending_shoes > 0 {
+            app.run_pending_games();
+        }
+        let reshuffles = app.drain_events().iter().filter(|event| **event == GameEvent::Reshuffled).count();
+        assert_eq!(reshuffles, 2);
+        // The first shoe was dealt to its cut card before the second was shuffled, and the
+        // second has just reached its own
+        assert_eq!(app.completed_shoes, shoes + 1);
+        assert!(app.completed_shoe_hands > hands);
+        assert!(app.needs_reshuffle());
+        assert!(app.hands_this_shoe > 0);
+    }
+
+    #[test]
+    fn one_deck_cut_off_reshuffles_a_six_deck_shoe_below_52_cards() {
+        let mut app = quiet_app(422);
+        app.rules.decks = 6;
+        app.rules.penetration = Penetration::CardsCutOff(52);
+        app.reshuffle();
+        let burn = |app: &mut BlackjackApp, left: usize| {
+            while app.deck.cards.len() > left {
+                let card = app.deck.cards.pop().unwrap();
+                app.deck.dealt.push(card);
+            }
+        };
+        burn(&mut app, 52);
+        assert!(!app.needs_reshuffle());
+        burn(&mut app, 51);
+        assert!(app.needs_reshuffle());
+        let shoes = app.completed_shoes;
+        app.play_game();
+        assert_eq!(app.completed_shoes, shoes + 1);
+        assert!(app.deck.cards.len() > 300);
+    }
+
+    #[test]
+    fn a_full_table_reshuffles_before_a_hand_the_low_shoe_could_not_cover() {
+        let mut app = quiet_app(428);
+        let burn = |app: &mut BlackjackApp, left: usize| {
+            while app.deck.cards.len() > left {
+                let card = app.deck.cards.pop().unwrap();
+                app.deck.dealt.push(card);
+            }
+        };
+        burn(&mut app, 70);
+        assert!(!app.needs_reshuffle(), "70 cards cover a lone player and the dealer");
+        // Five other seats, the two spots a high count opens and the dealer need 88 cards
+        app.set_seat_count(5);
+        app.spot_thresholds = vec![2.0];
+        assert_eq!(app.cards_for_a_round(), 88);
+        assert!(app.needs_reshuffle());
+        app.drain_events();
+        app.play_game();
+        let events = app.drain_events();
+        assert_eq!(events[0], GameEvent::Reshuffled);
+        assert_eq!(events.iter().filter(|event| **event == GameEvent::Reshuffled).count(), 1);
+        assert!(app.deck.cards.len() > 200);
+    }
+
+    #[test]
+    fn the_batch_banner_adds_up_the_hands_played_in_it() {
+        let mut app = quiet_app(431);
+        for _ in 0..40 {
+            app.play_game();
+        }
+        app.start_batch();
+        let start = app.batch_start.as_ref().unwrap().games_played;
+        let (mut net, mut wins) = (0.0, 0);
+        for _ in 0..150 {
+            let (bankroll, won) = (app.bankroll, app.wins);
+            app.play_game();
+            net += app.bankroll - bankroll;
+            wins += app.wins - won;
+        }
+        let banner = app.batch_start.take().unwrap().describe(&app);
+        assert!(banner.starts_with(&format!("Batch finished: {} hands, {} / ", app.games_played - start, wins)), "{}", banner);
+        assert!(banner.ends_with(&format!("net {}", app.format_amount(net))), "{} vs {}", banner, net);
+    }
+
+    #[test]
+    fn sitting_out_every_round_stops_a_shoe_batch() {
+        let mut app = quiet_app(395);
+        app.bankroll = 150.0;
+        app.bet_amount = 200.0;
+        app.bet_shortfall = BetShortfallPolicy::SitOut;
+        app.pending_shoes = 1;
+        app.run_pending_games();
+        assert_eq!(app.pending_shoes, 0);
+        assert_eq!(app.games_played, 0);
+        assert_eq!(app.stop_reason, Some(StopReason::ShortOfBet));
+
+        app.bet_amount = 100.0;
+        assert!(!app.short_of_bet());
+        app.stop_reason = None;
+        assert!(app.check_bankroll());
+    }
+
+    #[test]
+    fn a_progression_the_bankroll_cannot_cover_starts_over_rather_than_sitting_out() {
+        let mut app = quiet_app(395);
+        app.bankroll = 300.0;
+        app.bet_amount = 20.0;
+        app.bet_sizing = BetSizing::Martingale;
+        app.bet_shortfall = BetShortfallPolicy::SitOut;
+        app.progression_bet = Some(640.0);
+        assert!(app.check_bankroll());
+        stack(&mut app, &[card(Rank::Ten), card(Rank::Ten), card(Rank::Ten), card(Rank::Seven)]);
+        app.play_game();
+        assert_eq!(app.games_played, 1);
+        assert_eq!(app.bankroll, 320.0);
+        assert_eq!(app.progression_bet, None);
+    }
+
+    #[test]
+    fn constant_risk_bets_follow_the_bankroll_at_the_same_edge() {
+        let mut app = counting_app(423);
+        app.bet_sizing = BetSizing::ConstantRisk(0.1);
+        for _ in 0..60 {
+            app.strategy.observe_card(&card(Rank::Five));
+        }
+        app.bankroll = 100_000.0;
+        let full = app.stake();
+        app.bankroll = 50_000.0;
+        let half = app.stake();
+        assert!(full > 1000.0, "bet {}", full);
+        assert!((half - full / 2.0).abs() <= app.bet_increment, "{} then {}", full, half);
+    }
+
+    #[test]
+    fn a_high_count_opens_more_spots_than_a_neutral_one() {
+        let mut neutral = counting_app(426);
+        neutral.spot_thresholds = vec![2.0, 4.0];
+        neutral.play_game();
+        assert_eq!(neutral.player_spots, 1);
+
+        let mut high = counting_app(426);
+        high.spot_thresholds = vec![2.0, 4.0];
+        for _ in 0..60 {
+            high.strategy.observe_card(&card(Rank::Five));
+        }
+        stack(&mut high, &[card(Rank::Ten), card(Rank::Ten), card(Rank::Ten), card(Rank::Seven),
+            card(Rank::Nine), card(Rank::Nine), card(Rank::Nine), card(Rank::Ten)]);
+        high.play_game();
+        assert_eq!(high.player_spots, 3);
+        assert_eq!(high.games_played, 3);
+        assert_eq!(high.wins, 3);
+    }
+
+    #[test]
+    fn a_high_count_hand_lands_in_its_true_count_bucket() {
+        let mut app = counting_app(427);
+        for _ in 0..60 {
+            app.strategy.observe_card(&card(Rank::Five));
+        }
+        let true_count = app.strategy.true_count().unwrap().round() as i32;
+        assert!(true_count >= 10, "true count {}", true_count);
+        stack(&mut app, &[card(Rank::Ten), card(Rank::Seven), card(Rank::Nine), card(Rank::Ten)]);
+        app.play_game();
+        assert_eq!(app.results_by_count.buckets.len(), 1);
+        let bucket = &app.results_by_count.buckets[&true_count];
+        assert_eq!((bucket.hands, bucket.wins, bucket.net_bets), (1, 1, 1.0));
+        assert!(app.results_by_count.to_csv("", |true_count| edge_at_true_count(true_count, 0.0)).contains(&format!("\n{},1,1,0,0,1.0000,1.00,1.0000,", true_count)));
+    }
+
+    #[test]
+    fn a_kept_shoe_resumes_with_the_same_cards_and_count() {
+        let mut app = counting_app(434);
+        for _ in 0..20 {
+            app.play_game();
+        }
+        app.save_shoe = true;
+        let json = serde_json::to_string(&app.saved_session()).unwrap();
+        let mut restored = quiet_app(1);
+        restored.resume(serde_json::from_str(&json).unwrap());
+        assert_eq!(restored.deck.cards, app.deck.cards);
+        assert_eq!(restored.deck.dealt, app.deck.dealt);
+        assert_eq!(restored.strategy.running_count(), app.strategy.running_count());
+        assert_eq!(restored.strategy.true_count(), app.strategy.true_count());
+        assert_eq!(Card::from_code("10H"), Some(Card { rank: Rank::Ten, suit: Suit::Hearts }));
+        assert_eq!(Card::from_code("1H"), None);
+    }
+
+    #[test]
+    fn theoretical_edge_gains_half_a_percent_per_true_count() {
+        assert!((edge_at_true_count(0.0, 0.005) + 0.005).abs() < 1e-12);
+        assert!((edge_at_true_count(1.0, 0.005)).abs() < 1e-12);
+        assert!((edge_at_true_count(4.0, 0.005) - 0.015).abs() < 1e-12);
+        assert!((edge_at_true_count(-2.0, 0.0) + 0.01).abs() < 1e-12);
+    }
+
+    #[test]
+    fn fours_split_against_a_five_only_with_double_after_split() {
+        let mut rules = RulesConfig::default();
+        let five = card(Rank::Five);
+        assert_eq!(applied_first_action(&BasicStrategy::new(), &hand_of(&[4, 4]), &five, &rules), Action::Split);
+
+        rules.double_after_split = false;
+        let mut app = quiet_app(436);
+        app.rules = rules.clone();
+        app.rebuild_strategy();
+        assert_eq!(applied_first_action(&*app.strategy, &hand_of(&[4, 4]), &five, &rules), Action::Hit);
+        assert_chart_row(&*app.strategy, &rules, &[4, 4], "HHHHHHHHHH");
+    }
+
+    #[test]
+    fn twos_and_threes_split_against_a_low_upcard_only_with_double_after_split() {
+        let mut rules = RulesConfig::default();
+        let with_das = BasicStrategy::new();
+        let without_das = BasicStrategy { double_after_split: false, ..BasicStrategy::new() };
+        assert_eq!(applied_first_action(&with_das, &hand_of(&[2, 2]), &card(Rank::Two), &rules), Action::Split);
+        assert_eq!(applied_first_action(&with_das, &hand_of(&[3, 3]), &card(Rank::Three), &rules), Action::Split);
+        rules.double_after_split = false;
+        assert_eq!(applied_first_action(&without_das, &hand_of(&[2, 2]), &card(Rank::Two), &rules), Action::Hit);
+        assert_eq!(applied_first_action(&without_das, &hand_of(&[3, 3]), &card(Rank::Three), &rules), Action::Hit);
+        assert_chart_row(&without_das, &rules, &[2, 2], "HHPPPPHHHH");
+        assert_chart_row(&without_das, &rules, &[3, 3], "HHPPPPHHHH");
+    }
+
+    #[test]
+    fn a_ten_rich_shoe_insures_at_a_modest_true_count() {
+        let mut strategy = CountingStrategy::new(BasicStrategy::new(), CountSystem::HiLo, 3.0);
+        strategy.observe_shuffle(6 * 52);
+        // Sevens, eights and nines count nothing, so only the side count sees the tens pile up
+        for rank in [Rank::Seven, Rank::Eight, Rank::Nine].repeat(30) {
+            strategy.observe_card(&card(rank));
+        }
+        assert_eq!(strategy.running_count, 0);
+        let hand = hand_of(&[10, 7]);
+        assert!(!strategy.take_insurance(&hand, &card(Rank::Ace)));
+        strategy.insure_by_tens = true;
+        assert!((strategy.tens_fraction() - 96.0 / 222.0).abs() < 1e-12);
+        assert!(strategy.take_insurance(&hand, &card(Rank::Ace)));
+    }
+
+    #[test]
+    fn a_whole_shoe_counts_to_the_systems_known_end() {
+        for (system, start) in [(CountSystem::HiLo, 0), (CountSystem::Red7, -12)] {
+            let mut app = counting_app(442);
+            app.count_system = system;
+            app.rebuild_strategy();
+            assert_eq!(app.strategy.running_count(), Some(start));
+            while !app.needs_reshuffle() {
+                app.play_game();
+            }
+            let mut rest = Hand::new();
+            while !app.deck.cards.is_empty() {
+                app.deal_to(&mut rest, false);
+            }
+            assert_eq!(app.strategy.running_count(), Some(system.full_shoe_running_count(6 * 52)), "{}", system.label());
+            assert_eq!(app.strategy.running_count(), Some(0));
+            app.check_count_drift();
+        }
+    }
+
+    #[test]
+    fn three_spots_settle_left_to_right_at_their_own_bets() {
+        let mut app = counting_app(444);
+        app.record_games = true;
+        app.spot_thresholds = vec![-100.0, -100.0];
+        // Twenty, eighteen and seventeen against the dealer's eighteen
+        stack(&mut app, &[card(Rank::Ten), card(Rank::Ten), card(Rank::Ten), card(Rank::Ten),
+            card(Rank::Ten), card(Rank::Eight), card(Rank::Seven), card(Rank::Eight)]);
+        app.play_game();
+        let rows: Vec<(usize, u8, GameResult, f64)> = app.game_rows.iter()
+            .map(|row| (row.spot, row.player_total, row.result.clone(), row.wagered))
+            .collect();
+        assert_eq!(rows, [
+            (0, 20, GameResult::PlayerWin, 10.0),
+            (1, 18, GameResult::Push, 10.0),
+            (2, 17, GameResult::DealerWin, 10.0),
+        ]);
+        assert_eq!(app.bankroll, app.starting_bankroll);
+        assert_eq!(app.seat_prefix(1), "Spot 2: ");
+        assert!(app.game_rows_csv().contains("\n2,1,18,18,Push,10.00,"));
+    }
+
+    #[test]
+    fn fifteen_against_nine_surrenders_only_from_its_index() {
+        let rules = RulesConfig::default();
+        let mut strategy = CountingStrategy::new(BasicStrategy::new(), CountSystem::HiLo, 3.0);
+        strategy.surrender_indices = vec![SurrenderIndex { total: 15, upcard: 9, true_count: 2.0 }];
+        strategy.observe_shuffle(6 * 52);
+        let (hand, nine) = (hand_of(&[10, 5]), card(Rank::Nine));
+        assert_eq!(applied_first_action(&strategy, &hand, &nine, &rules), Action::Hit);
+        strategy.running_count = 6;
+        assert_eq!(applied_first_action(&strategy, &hand, &nine, &rules), Action::Hit);
+        strategy.running_count = 12;
+        assert_eq!(applied_first_action(&strategy, &hand, &nine, &rules), Action::Surrender);
+
+        // Below its index the chart's 15 against a ten is refused too
+        strategy.surrender_indices = FAB_FOUR_SURRENDERS.to_vec();
+        strategy.running_count = -6;
+        assert_eq!(applied_first_action(&strategy, &hand, &card(Rank::Ten), &rules), Action::Hit);
+    }
+
+    // Stands on everything, noting how many cards it had seen when it first had to decide
+    struct CardWatcher {
+        seen: usize,
+        seen_at_decision: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl PlayStrategy for CardWatcher {
+        fn name(&self) -> &str {
+            "Card watcher"
+        }
+
+        fn determine_action(&self, _player_hand: &Hand, _dealer_upcard: &Card) -> Action {
+            Action::Stand
+        }
+
+        fn determine_first_action(&self, _player_hand: &Hand, _dealer_upcard: &Card) -> Action {
+            self.seen_at_decision.store(self.seen, std::sync::atomic::Ordering::Relaxed);
+            Action::Stand
+        }
+
+        fn observe_card(&mut self, _card: &Card) {
+            self.seen += 1;
+        }
+    }
+
+    // Always asks for the same play, one for the first decision and one for every later
+    // decision, however illegal, so validate_action's corrections can be checked
+    struct Scripted {
+        first: Action,
+        then: Action,
+    }
+
+    impl PlayStrategy for Scripted {
+        fn name(&self) -> &str {
+            "Scripted"
+        }
+
+        fn determine_action(&self, _player_hand: &Hand, _dealer_upcard: &Card) -> Action {
+            self.then.clone()
+        }
+
+        fn determine_first_action(&self, _player_hand: &Hand, _dealer_upcard: &Card) -> Action {
+            self.first.clone()
+        }
+
+        fn rescue_double(&self, player_hand: &Hand, _dealer_upcard: &Card) -> bool {
+            player_hand.is_busted()
+        }
+    }
+
+    #[test]
+    fn a_double_after_a_hit_is_played_as_a_hit() {
+        let mut app = quiet_app(360);
+        app.set_strategy(Box::new(Scripted { first: Action::Hit, then: Action::DoubleDown }));
+        // Every double after the first decision is refused: 5, 9 and 19 are hit to 21,
+        // where the refused double stands
+        let deal = [Rank::Two, Rank::Ten, Rank::Three, Rank::Seven, Rank::Four, Rank::Ten, Rank::Two];
+        stack(&mut app, &deal.map(card));
+        let bankroll = app.bankroll;
+        app.play_game();
+        assert_eq!(app.last_game_result, Some(GameResult::PlayerWin));
+        assert_eq!(app.bankroll, bankroll + app.bet_amount);
+        assert!(app.events.contains(&GameEvent::IllegalAction { attempted: Action::DoubleDown, applied: Action::Hit }));
+        assert!(app.events.contains(&GameEvent::IllegalAction { attempted: Action::DoubleDown, applied: Action::Stand }));
+        assert_eq!(app.illegal_actions, 4);
+    }
+
+    #[test]
+    fn a_busted_double_can_be_rescued_for_the_configured_loss() {
+        // 14 against a 10 doubles into a ten
+        let deal = [Rank::Ten, Rank::Ten, Rank::Four, Rank::Seven, Rank::Ten].map(card);
+        for (rescue, result, bets) in [(false, GameResult::DoubledLose, -2.0), (true, GameResult::Rescued, -1.5)] {
+            let mut app = quiet_app(404);
+            app.rules.double_rescue = rescue;
+            app.rules.double_rescue_loss = 1.5;
+            app.set_strategy(Box::new(Scripted { first: Action::DoubleDown, then: Action::Stand }));
+            stack(&mut app, &deal);
+            app.play_game();
+            assert_eq!(app.last_game_result, Some(result), "rescue {}", rescue);
+            assert_eq!(app.bankroll, app.starting_bankroll + bets * app.bet_amount, "rescue {}", rescue);
+        }
+    }
+
+    #[test]
+    fn double_exposure_ties_go_to_the_dealer_but_a_blackjack_still_wins() {
+        let mut app = quiet_app(441);
+        app.rules = RulePreset::DoubleExposure.rules();
+        app.set_strategy(Box::new(Scripted { first: Action::Stand, then: Action::Stand }));
+        stack(&mut app, &[Rank::Ten, Rank::Ten, Rank::Queen, Rank::Jack].map(card));
+        app.play_game();
+        assert_eq!(app.last_game_result, Some(GameResult::DealerWin));
+        assert_eq!(app.bankroll, app.starting_bankroll - app.bet_amount);
+
+        // The other seat stands on 17, so the dealer's 11 draws to 21 against the blackjack
+        let mut app = quiet_app(441);
+        app.rules = RulePreset::DoubleExposure.rules();
+        app.set_seat_count(1);
+        app.seat_position = SeatPosition::FirstBase;
+        let shoe = app.deck.cards.len();
+        stack(&mut app, &[Rank::Ace, Rank::Ten, Rank::Seven, Rank::King, Rank::Seven, Rank::Four, Rank::Ten].map(card));
+        app.play_game();
+        assert_eq!(app.deck.cards.len(), shoe, "the dealer drew to 21");
+        assert_eq!(app.last_game_result, Some(GameResult::PlayerBlackjack));
+        assert_eq!(app.bankroll, app.starting_bankroll + app.bet_amount);
+    }
+
+    #[test]
+    fn a_hit_on_21_stands_instead() {
+        let mut app = quiet_app(362);
+        app.set_strategy(Box::new(Scripted { first: Action::Hit, then: Action::Hit }));
+        // 15 hits a six to 21 and leaves the ten that would bust it on the shoe
+        stack(&mut app, &[Rank::Ten, Rank::Ten, Rank::Five, Rank::Seven, Rank::Six, Rank::Ten].map(card));
+        app.play_game();
+        assert_eq!(app.last_game_result, Some(GameResult::PlayerWin));
+        assert!(app.events.contains(&GameEvent::IllegalAction { attempted: Action::Hit, applied: Action::Stand }));
+        assert_eq!(app.deck.cards.last(), Some(&card(Rank::Ten)));
+    }
+
+    #[test]
+    fn an_always_hit_strategy_stops_at_the_card_cap() {
+        let mut app = quiet_app(374);
+        app.set_evaluator(Box::new(NeverBusts));
+        app.set_strategy(Box::new(Scripted { first: Action::Hit, then: Action::Hit }));
+        // Two twos against a 17 hit nine more twos, then stand on eleven cards with the ace unseen
+        let mut deal = vec![card(Rank::Two), card(Rank::Ten), card(Rank::Two), card(Rank::Seven)];
+        deal.extend([card(Rank::Two); MAX_HAND_CARDS - 2]);
+        deal.push(card(Rank::Ace));
+        stack(&mut app, &deal);
+        app.play_game();
+        assert_eq!(app.last_game_result, Some(GameResult::PlayerWin));
+        assert_eq!(app.deck.cards.last(), Some(&card(Rank::Ace)));
+    }
+
+    #[test]
+    fn the_deal_order_decides_which_cards_reach_the_player() {
+        // Alternating gives the player both tens against A-9; player first gives them A-T
+        for (order, result) in [(DealOrder::Alternating, GameResult::Push), (DealOrder::PlayerFirst, GameResult::PlayerBlackjack)] {
+            let mut app = quiet_app(433);
+            app.rules.deal_order = order;
+            let deal = [Rank::Ten, Rank::Ace, Rank::Ten, Rank::Nine].map(card);
+            stack(&mut app, &deal);
+            app.play_game();
+            assert_eq!(app.last_game_result, Some(result), "{:?}", order);
+            assert_eq!(app.deck.dealt[..4], deal, "{:?}", order);
+        }
+    }
+
+    #[test]
+    fn one_hand_emits_its_events_in_order() {
+        let mut app = quiet_app(403);
+        app.drain_events();
+        // 19 stands and the dealer turns the ten under the 7 for 17
+        stack(&mut app, &[Rank::Ten, Rank::Seven, Rank::Nine, Rank::Ten].map(card));
+        app.play_game();
+        assert_eq!(app.drain_events(), [
+            GameEvent::HandStarted { game: 1 },
+            GameEvent::CardDealt { card: card(Rank::Ten), to_dealer: false },
+            GameEvent::CardDealt { card: card(Rank::Seven), to_dealer: true },
+            GameEvent::CardDealt { card: card(Rank::Nine), to_dealer: false },
+            GameEvent::HoleCardDealt,
+            GameEvent::HoleCardRevealed { card: card(Rank::Ten) },
+            GameEvent::HandResolved { result: GameResult::PlayerWin, bankroll: app.starting_bankroll + app.bet_amount },
+        ]);
+        assert!(app.drain_events().is_empty());
+    }
+
+    #[test]
+    fn dealer_finals_land_in_their_bust_and_made_buckets() {
+        let mut app = quiet_app(448);
+        // 18 stands on the 6, whose 12 draws a ten to 22; then 19 loses to a dealt 20
+        stack(&mut app, &[Rank::Ten, Rank::Six, Rank::Eight, Rank::Six, Rank::Ten].map(card));
+        app.play_game();
+        stack(&mut app, &[Rank::Ten, Rank::Ten, Rank::Nine, Rank::Queen].map(card));
+        app.play_game();
+        let outcomes = &app.dealer_outcomes;
+        assert_eq!(outcomes.busts.get(&22), Some(&1));
+        assert_eq!(outcomes.made.get(&20), Some(&1));
+        assert_eq!((outcomes.total(), outcomes.blackjacks), (2, 0));
+        assert_eq!(outcomes.distribution(), "BJ 0.0%, 20 50.0%, Bust 50.0%, 22 50.0%");
+    }
+
+    #[test]
+    fn a_win_streak_milestone_is_raised_once_per_run() {
+        let mut app = quiet_app(466);
+        app.streak_milestone = 3;
+        let (win, loss) = ([Rank::Ten, Rank::Seven, Rank::Ten, Rank::Ten], [Rank::Ten, Rank::Ten, Rank::Seven, Rank::Ten]);
+        let milestones = |app: &mut BlackjackApp, hands: &[[Rank; 4]]| {
+            for hand in hands {
+                stack(app, &hand.map(card));
+                app.play_game();
+            }
+            app.drain_events().into_iter().filter(|event| matches!(event, GameEvent::Milestone { .. })).collect::<Vec<_>>()
+        };
+        assert!(milestones(&mut app, &[win, win]).is_empty());
+        assert_eq!(milestones(&mut app, &[win, win, win]), [GameEvent::Milestone { description: "3 wins in a row!".to_string() }]);
+        // A loss ends the run, and the next one to reach three raises it again
+        assert_eq!(milestones(&mut app, &[loss, win, win, win, win]).len(), 1);
+    }
+
+    #[test]
+    fn a_dealer_22_pushes_a_20_only_under_push_on_22() {
+        // 20 stands on the 6, whose 12 draws a ten to 22
+        for (push_on_22, result, bets) in [(true, GameResult::Push, 0.0), (false, GameResult::PlayerWin, 1.0)] {
+            let mut app = quiet_app(372);
+            app.rules.dealer_push_on_22 = push_on_22;
+            stack(&mut app, &[Rank::Ten, Rank::Six, Rank::Queen, Rank::Six, Rank::Ten].map(card));
+            app.play_game();
+            assert_eq!(app.last_game_result, Some(result), "push on 22 {}", push_on_22);
+            assert_eq!(app.bankroll, app.starting_bankroll + bets * app.bet_amount);
+        }
+    }
+
+    #[test]
+    fn the_dealer_rule_sets_where_the_dealer_stops_drawing() {
+        let s17 = DealerRule { stand_total: 17, hit_soft: false };
+        let h17 = DealerRule { stand_total: 17, hit_soft: true };
+        let s18 = DealerRule { stand_total: 18, hit_soft: false };
+        // A 19 against the dealer's soft and then hard 17; drawing the two makes it a push
+        let (soft, hard) = ([Rank::Ten, Rank::Ace, Rank::Nine, Rank::Six, Rank::Two], [Rank::Ten, Rank::Ten, Rank::Nine, Rank::Seven, Rank::Two]);
+        for (dealer, deal, result) in [
+            (s17, soft, GameResult::PlayerWin),
+            (h17, soft, GameResult::Push),
+            (s18, soft, GameResult::Push),
+            (s17, hard, GameResult::PlayerWin),
+            (h17, hard, GameResult::PlayerWin),
+            (s18, hard, GameResult::Push),
+        ] {
+            let mut app = quiet_app(377);
+            app.rules.dealer = dealer;
+            stack(&mut app, &deal.map(card));
+            app.play_game();
+            assert_eq!(app.last_game_result, Some(result), "{} against {:?}", dealer.describe(), deal);
+        }
+    }
+
+    #[test]
+    fn the_discard_pile_holds_exactly_the_cards_dealt_this_shoe() {
+        let mut app = quiet_app(378);
+        let before = app.deck.cards.clone();
+        app.play_game();
+        let dealt = before.len() - app.deck.cards.len();
+        assert!(dealt >= 4);
+        assert_eq!(app.deck.dealt.len(), dealt);
+        assert!(app.deck.dealt.iter().eq(before.iter().rev().take(dealt)));
+        app.reshuffle();
+        assert!(app.deck.dealt.is_empty());
+    }
+
+    #[test]
+    fn the_theoretical_loss_is_the_edge_on_the_amount_wagered() {
+        let mut app = quiet_app(379);
+        for _ in 0..300 {
+            app.play_game();
+        }
+        let edge = app.rules.approximate_house_edge().unwrap();
+        let summary = app.build_summary();
+        let theoretical = summary.theoretical_loss.unwrap();
+        assert_eq!(theoretical, app.total_wagered * edge);
+        let line = format!("Theoretical Loss: ${:.2} (actual ${:.2}, delta ${:.2})", theoretical, -app.main_net(), -app.main_net() - theoretical);
+        assert!(summary.to_string().contains(&line), "{}", summary);
+
+        // Without a known edge for the rules there's nothing to compare against
+        app.rules.dealer_wins_ties = true;
+        assert_eq!(app.build_summary().theoretical_loss, None);
+    }
+
+    #[test]
+    fn a_doubled_win_lands_in_the_plus_two_bucket() {
+        let mut app = quiet_app(390);
+        // 11 against a 6 doubles into 20, and the dealer's 16 busts
+        stack(&mut app, &[Rank::Five, Rank::Six, Rank::Six, Rank::Ten, Rank::Nine, Rank::Ten].map(card));
+        app.play_game();
+        assert_eq!(app.last_game_result, Some(GameResult::DoubledWin));
+        assert_eq!(app.outcome_histogram.describe(), "+2: 1");
+        assert!(app.outcome_histogram.to_csv("").ends_with("net_bets,hands\n2.00,1\n"));
+    }
+
+    #[test]
+    fn the_dealer_only_draws_after_a_lone_bust_when_playing_out() {
+        // 12 against a 2 hits a ten and busts; the dealer's 12 would draw the five to 17
+        for (plays_out, top) in [(false, Rank::Five), (true, Rank::Ace)] {
+            let mut app = quiet_app(391);
+            app.rules.dealer_plays_out = plays_out;
+            stack(&mut app, &[Rank::Ten, Rank::Two, Rank::Two, Rank::Ten, Rank::Ten, Rank::Five, Rank::Ace].map(card));
+            app.play_game();
+            assert_eq!(app.last_game_result, Some(GameResult::DealerWin));
+            assert_eq!(app.deck.cards.last(), Some(&card(top)), "plays out {}", plays_out);
+        }
+    }
+
+    #[test]
+    fn multi_card_soft_17s_hit_under_h17_and_stand_under_s17() {
+        let s17 = DealerRule { stand_total: 17, hit_soft: false };
+        let h17 = DealerRule { stand_total: 17, hit_soft: true };
+        for cards in [[11, 2, 4], [11, 3, 3]] {
+            let hand = hand_of(&cards);
+            assert!(hand.is_soft() && hand.total() == 17, "{:?}", cards);
+            assert!(h17.should_hit(&hand), "{:?}", cards);
+            assert!(!s17.should_hit(&hand), "{:?}", cards);
+        }
+        // The dealer's A-2 draws a four to soft 17 against a 19, and under H17 a two to tie it
+        for (dealer, result) in [(s17, GameResult::PlayerWin), (h17, GameResult::Push)] {
+            let mut app = quiet_app(393);
+            app.rules.dealer = dealer;
+            stack(&mut app, &[Rank::Ten, Rank::Ace, Rank::Nine, Rank::Two, Rank::Four, Rank::Two].map(card));
+            app.play_game();
+            assert_eq!(app.last_game_result, Some(result), "{}", dealer.describe());
+        }
+    }
+
+    #[test]
+    fn a_stand_on_15_strategy_stands_on_15_and_hits_14() {
+        let strategy = ThresholdStrategy::new(15);
+        let ten = card(Rank::Ten);
+        for (cards, action) in [(&[10, 5][..], Action::Stand), (&[10, 4], Action::Hit), (&[8, 8], Action::Stand), (&[5, 6], Action::Hit), (&[2, 3, 10], Action::Stand)] {
+            assert_eq!(strategy.determine_first_action(&hand_of(cards), &ten), action, "{:?}", cards);
+            assert_eq!(strategy.determine_action(&hand_of(cards), &ten), action, "{:?}", cards);
+        }
+        // Playing it, 14 takes a card and 15 keeps it
+        let mut app = quiet_app(402);
+        app.strategy_kind = StrategyKind::StandOn;
+        app.strategy_stand_total = 15;
+        app.rebuild_strategy();
+        stack(&mut app, &[Rank::Ten, Rank::Ten, Rank::Four, Rank::Seven, Rank::Ace, Rank::Five].map(card));
+        app.play_game();
+        assert_eq!(app.last_game_result, Some(GameResult::DealerWin));
+        assert_eq!(app.deck.cards.last(), Some(&card(Rank::Five)));
+        assert_eq!((app.action_counts.hits, app.action_counts.doubles, app.action_counts.splits), (1, 0, 0));
+    }
+
+    #[test]
+    fn a_bottom_dealer_can_force_a_dealer_blackjack() {
+        let mut app = quiet_app(406);
+        app.deck.deal_position = DealPosition::Bottom;
+        // Dealt from the bottom up: the dealer gets A-K against the player's 19
+        let deal = [Rank::Ten, Rank::Ace, Rank::Nine, Rank::King].map(card);
+        app.deck.cards.splice(0..0, deal);
+        let top = *app.deck.cards.last().unwrap();
+        app.play_game();
+        assert_eq!(app.last_game_result, Some(GameResult::DealerWin));
+        assert_eq!(app.bankroll, app.starting_bankroll - app.bet_amount);
+        assert_eq!(app.dealer_outcomes.blackjacks, 1);
+        assert_eq!(app.deck.dealt, deal);
+        assert_eq!(app.deck.cards.last(), Some(&top));
+    }
+
+    #[test]
+    fn each_split_hand_gets_a_first_decision_of_its_own() {
+        let mut played = hand_of(&[8, 3]);
+        played.split = true;
+        played.doubled = true;
+        played.first_action = false;
+        played.live = false;
+        played.reset_flags();
+        assert!(played.first_action && played.live && !played.doubled && !played.stood);
+        assert!(played.split && played.cards.len() == 2);
+
+        // 8s split against a 6 make 11 and 10, and both are doubled
+        let mut app = quiet_app(407);
+        let deal = [Rank::Eight, Rank::Six, Rank::Eight, Rank::Ten, Rank::Three, Rank::Nine, Rank::Two, Rank::Ten, Rank::Ten];
+        stack(&mut app, &deal.map(card));
+        app.play_game();
+        assert_eq!(app.action_counts.doubles, 2);
+        assert_eq!(app.bankroll, app.starting_bankroll + 4.0 * app.bet_amount);
+    }
+
+    #[test]
+    fn a_soft_18_stand_variant_stands_where_basic_strategy_hits() {
+        let (soft_18, nine) = (hand_of(&[11, 7]), card(Rank::Nine));
+        assert_eq!(BasicStrategy::new().determine_action(&soft_18, &nine), Action::Hit);
+        assert_eq!(BasicStrategy::with_soft_stand(18).determine_action(&soft_18, &nine), Action::Stand);
+
+        // At the table the variant keeps A-7 against the 9 and leaves the three on the shoe
+        for (soft_stand_total, top) in [(None, Rank::Ten), (Some(18), Rank::Three)] {
+            let mut app = quiet_app(408);
+            app.soft_stand_total = soft_stand_total;
+            app.rebuild_strategy();
+            stack(&mut app, &[Rank::Ace, Rank::Nine, Rank::Seven, Rank::Nine, Rank::Three, Rank::Ten].map(card));
+            app.play_game();
+            assert_eq!(app.deck.cards.last(), Some(&card(top)), "{:?}", soft_stand_total);
+        }
+    }
+
+    // Stands straight away, noting the dealer's second card if it was shown one
+    struct HoleWatcher(std::sync::Arc<std::sync::Mutex<Option<Card>>>);
+
+    impl PlayStrategy for HoleWatcher {
+        fn name(&self) -> &str {
+            "Hole watcher"
+        }
+
+        fn determine_action(&self, _player_hand: &Hand, _dealer_upcard: &Card) -> Action {
+            Action::Stand
+        }
+
+        fn determine_first_action(&self, _player_hand: &Hand, _dealer_upcard: &Card) -> Action {
+            Action::Stand
+        }
+
+        fn determine_first_action_exposed(&self, _player_hand: &Hand, dealer_hand: &Hand) -> Action {
+            *self.0.lock().unwrap() = dealer_hand.cards.get(1).copied();
+            Action::Stand
+        }
+    }
+
+    #[test]
+    fn an_exposed_dealer_hand_reaches_the_strategy() {
+        for (exposed, seen) in [(false, None), (true, Some(card(Rank::Four)))] {
+            let mut app = quiet_app(417);
+            app.rules.dealer_exposes_both = exposed;
+            let hole = std::sync::Arc::new(std::sync::Mutex::new(None));
+            app.set_strategy(Box::new(HoleWatcher(hole.clone())));
+            stack(&mut app, &[Rank::Ten, Rank::Nine, Rank::Eight, Rank::Four, Rank::Ten].map(card));
+            app.play_game();
+            assert_eq!(*hole.lock().unwrap(), seen, "exposed {}", exposed);
+        }
+    }
+
+    #[test]
+    fn a_broken_strategy_runs_up_the_illegal_action_count() {
+        let mut app = quiet_app(432);
+        for _ in 0..200 {
+            app.play_game();
+        }
+        assert_eq!(app.illegal_actions, 0, "basic strategy only asks for legal plays");
+
+        // Splitting every hand is only legal on a pair
+        app.set_strategy(Box::new(Scripted { first: Action::Split, then: Action::Stand }));
+        stack(&mut app, &[Rank::Ten, Rank::Seven, Rank::Six, Rank::Ten].map(card));
+        app.play_game();
+        assert_eq!(app.illegal_actions, 1);
+        assert!(app.events.contains(&GameEvent::IllegalAction { attempted: Action::Split, applied: Action::Stand }));
+        for _ in 0..50 {
+            app.play_game();
+        }
+        assert!(app.illegal_actions > 30, "{}", app.illegal_actions);
+    }
+
+    #[test]
+    fn a_perfect_pair_pays_25_to_1_beside_the_main_bet() {
+        let hearts = |rank| Card { rank, suit: Suit::Hearts };
+        // The player's 20 beats the dealer's 17 every time; only the pair differs
+        for (second, paid) in [(card(Rank::Ten), 25.0), (hearts(Rank::Ten), 6.0), (card(Rank::King), -1.0)] {
+            let mut app = quiet_app(438);
+            app.side_bets[0].1 = true;
+            app.side_bet_amount = 5.0;
+            assert_eq!(app.side_bets[0].0.name(), "Perfect Pairs");
+            stack(&mut app, &[card(Rank::Ten), card(Rank::Seven), second, card(Rank::Queen)]);
+            app.play_game();
+            assert_eq!(app.last_game_result, Some(GameResult::PlayerWin));
+            assert_eq!(app.side_bet_net, paid * app.side_bet_amount, "{:?}", second);
+            assert_eq!(app.net_won, app.bet_amount);
+            assert_eq!(app.bankroll, app.starting_bankroll + app.bet_amount + paid * app.side_bet_amount);
+        }
+    }
+
+    #[test]
+    fn twenty_one_plus_three_pays_flushes_and_straights_only() {
+        let dealt = |cards: [(Rank, Suit); 3]| {
+            let [first, second, upcard] = cards.map(|(rank, suit)| Card { rank, suit });
+            let (mut player, mut dealer) = (Hand::new(), Hand::new());
+            player.add_card(first);
+            player.add_card(second);
+            dealer.add_card(upcard);
+            (player, dealer)
+        };
+        let paytable = TwentyOnePlusThree::default();
+        let shoe = Deck::new(1);
+        let (s, h, d) = (Suit::Spades, Suit::Hearts, Suit::Diamonds);
+        for (cards, paid) in [
+            ([(Rank::Two, s), (Rank::Nine, s), (Rank::King, s)], 5.0),
+            ([(Rank::Five, h), (Rank::Six, s), (Rank::Seven, d)], 10.0),
+            ([(Rank::Queen, h), (Rank::Ace, s), (Rank::King, d)], 10.0),
+            ([(Rank::Eight, h), (Rank::Eight, s), (Rank::Eight, d)], 30.0),
+            ([(Rank::Two, s), (Rank::Nine, h), (Rank::King, d)], -1.0),
+            ([(Rank::Queen, h), (Rank::King, s), (Rank::Two, d)], -1.0),
+        ] {
+            let (player, dealer) = dealt(cards);
+            assert_eq!(paytable.resolve(&player, &dealer, &shoe), paid, "{:?}", cards);
+        }
+        // The paytable is the table's to set
+        let generous = TwentyOnePlusThree { flush: 7.0, ..TwentyOnePlusThree::default() };
+        let (player, dealer) = dealt([(Rank::Two, s), (Rank::Nine, s), (Rank::King, s)]);
+        assert_eq!(generous.resolve(&player, &dealer, &shoe), 7.0);
+    }
+
+    #[test]
+    fn an_error_free_wrapper_plays_exactly_as_its_base_strategy() {
+        let base = BasicStrategy::new();
+        let wrapped = ErrorProneStrategy::new(Box::new(BasicStrategy::new()), 0.0, DoubleRule::AnyTwo, 7);
+        let mut hands: Vec<_> = chart_rows().into_iter().map(|(_, hand)| hand).collect();
+        hands.extend((2..=11).map(|value| hand_of(&[value, value])));
+        for upcard in 2..=11 {
+            let upcard = hand_of(&[upcard]).cards[0];
+            for hand in &hands {
+                assert_eq!(wrapped.determine_first_action(hand, &upcard), base.determine_first_action(hand, &upcard));
+                assert_eq!(wrapped.determine_action(hand, &upcard), base.determine_action(hand, &upcard));
+            }
+        }
+    }
+
+    #[test]
+    fn results_in_big_bets_divide_the_net_by_the_average_and_largest_bet() {
+        let mut histogram = OutcomeHistogram::default();
+        for net_bets in [1.0, -1.0, 1.0, 1.0] {
+            histogram.record(net_bets);
+        }
+        // Mean 0.5 and variance 1 bet per hand
+        let stats = BigBetStats::new(50.0, 10.0, 40.0, &histogram).unwrap();
+        assert_eq!(stats.net_average_bets, 5.0);
+        assert_eq!(stats.net_big_bets, 1.25);
+        assert_eq!(stats.sd_per_100, 10.0);
+        assert_eq!(stats.desirability_index, 500.0);
+        assert_eq!(stats.score(), 250_000.0);
+        assert_eq!(stats.n0, Some(4.0));
+    }
+
+    #[test]
+    fn the_basic_hint_for_16_against_10_ignores_the_active_strategy() {
+        let mut app = quiet_app(1);
+        let (hard_16, ten) = (hand_of(&[10, 6]), hand_of(&[10]));
+        for (kind, hi_lo) in [(StrategyKind::StandOn, false), (StrategyKind::DealerMimic, false), (StrategyKind::Basic, true)] {
+            app.strategy_kind = kind;
+            app.hi_lo = hi_lo;
+            app.rebuild_strategy();
+            app.rules.surrender = SurrenderRule::Late;
+            assert_eq!(app.basic_hint(&hard_16, &ten, true), Action::Surrender, "{:?}", kind);
+            app.rules.surrender = SurrenderRule::None;
+            assert_eq!(app.basic_hint(&hard_16, &ten, true), Action::Hit, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn the_shoe_gauge_fills_from_the_first_card_to_the_cut_card() {
+        let mut app = quiet_app(1);
+        app.rules.decks = 6;
+        app.rules.penetration = Penetration::Fraction(0.75);
+        app.reshuffle();
+        // 312 cards with 78 behind the cut card leaves 234 to play
+        let mut dealt = 0;
+        for (depth, fill) in [(0, 0.0), (117, 0.5), (234, 1.0), (260, 1.0)] {
+            while dealt < depth {
+                app.deck.deal_card().unwrap();
+                dealt += 1;
+            }
+            assert_eq!(app.shoe_progress(), fill, "{} cards dealt", depth);
+        }
+    }
+
+    #[test]
+    fn the_generated_table_agrees_with_the_chart_on_sampled_cells() {
+        let app = quiet_app(1);
+        let mut rules = app.rules.clone();
+        rules.surrender = SurrenderRule::Late;
+        let cells = generate_strategy_table(&rules, app.evaluator());
+        for (row, first_decision, upcard, expected) in [
+            ("Hard 8", true, 6, Action::Hit),
+            ("Hard 11", true, 6, Action::DoubleDown),
+            ("Hard 12", true, 2, Action::Hit),
+            ("Hard 13", true, 4, Action::Stand),
+            ("Hard 16", true, 10, Action::Surrender),
+            ("Hard 16", false, 10, Action::Hit),
+            ("Hard 17", true, 7, Action::Stand),
+            ("Soft 17", true, 5, Action::DoubleDown),
+            ("Soft 18", false, 9, Action::Hit),
+            ("Soft 19", false, 11, Action::Stand),
+        ] {
+            let cell = cells.iter()
+                .find(|cell| cell.row == row && cell.first_decision == first_decision && cell.upcard == upcard)
+                .unwrap();
+            assert_eq!((&cell.best, &cell.chart), (&expected, &expected), "{} vs {}", row, upcard);
+        }
+    }
+
+    #[test]
+    fn soft_19_and_soft_20_stand_against_a_ten_and_an_ace() {
+        let strategy = BasicStrategy::new();
+        for hand in [hand_of(&[11, 8]), hand_of(&[11, 9])] {
+            for upcard in [10, 11] {
+                let upcard = hand_of(&[upcard]).cards[0];
+                assert_eq!(strategy.determine_first_action(&hand, &upcard), Action::Stand);
+                assert_eq!(strategy.determine_action(&hand, &upcard), Action::Stand);
+            }
+        }
+    }
+
+    #[test]
+    fn the_exported_basic_chart_covers_every_hand_and_upcard() {
+        let mut rules = quiet_app(1).rules.clone();
+        rules.surrender = SurrenderRule::Late;
+        let csv = strategy_chart_csv(&BasicStrategy::new(), &rules);
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().starts_with("# "));
+        assert_eq!(lines.next(), Some("hand,2,3,4,5,6,7,8,9,10,A"));
+        let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+        // Hard 5 to 20, soft 13 to 20 and ten pairs
+        assert_eq!(rows.len(), 16 + 8 + 10);
+        assert!(rows.iter().all(|row| row.len() == 11));
+        let cell = |hand: &str, upcard: usize| rows.iter().find(|row| row[0] == hand).unwrap()[upcard - 1];
+        assert_eq!(cell("Hard 11", 6), "D");
+        assert_eq!(cell("Hard 16", 10), "R");
+        assert_eq!(cell("Hard 12", 2), "H");
+        assert_eq!(cell("Soft 18", 9), "H");
+        assert_eq!(cell("Pair 8", 7), "P");
+        assert_eq!(cell("Pair T", 6), "S");
+    }
+
+    #[test]
+    fn a_split_ace_drawing_a_ten_is_paid_even_money() {
+        let mut app = quiet_app(387);
+        // Aces split against a 9; the first draws a ten to 21, the second a six to push
+        // the dealer's 17
+        stack(&mut app, &[Rank::Ace, Rank::Nine, Rank::Ace, Rank::Eight, Rank::Ten, Rank::Six].map(card));
+        app.play_game();
+        assert_eq!(app.games_played, 2);
+        assert_eq!((app.wins, app.pushes), (1, 1));
+        assert_eq!(app.net_won, app.bet_amount);
+    }
+
+    #[test]
+    fn third_base_sees_more_cards_before_deciding() {
+        let (ten, eight, seven, two) = (card(Rank::Ten), card(Rank::Eight), card(Rank::Seven), card(Rank::Two));
+        // The player stands on 18 while both other seats hit 12 against the 7 and bust
+        let mut seen = Vec::new();
+        for (position, shoe) in [
+            (SeatPosition::FirstBase, [ten, ten, ten, seven, eight, two, two, ten, ten, ten]),
+            (SeatPosition::ThirdBase, [ten, ten, ten, seven, two, two, eight, ten, ten, ten]),
+        ] {
+            let mut app = quiet_app(457);
+            app.set_seat_count(2);
+            app.seat_position = position;
+            let seen_at_decision = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+            app.set_strategy(Box::new(CardWatcher { seen: 0, seen_at_decision: seen_at_decision.clone() }));
+            stack(&mut app, &shoe);
+            app.play_game();
+            assert_eq!(app.last_game_result, Some(GameResult::PlayerWin), "{}", position.label());
+            seen.push(seen_at_decision.load(std::sync::atomic::Ordering::Relaxed));
+        }
+        assert_eq!(seen, [7, 9]);
+    }
+
+    #[test]
+    fn a_lagging_count_reflects_the_shoe_some_cards_ago() {
+        let mut lagging = CountingStrategy::new(BasicStrategy::new(), CountSystem::HiLo, 3.0);
+        lagging.count_lag = 3;
+        lagging.observe_shuffle(6 * 52);
+        let mut prompt = CountingStrategy::new(BasicStrategy::new(), CountSystem::HiLo, 3.0);
+        prompt.observe_shuffle(6 * 52);
+        let cards = [Rank::Five, Rank::Six, Rank::King, Rank::Two, Rank::Three, Rank::Four, Rank::Ace];
+        for (dealt, rank) in cards.iter().enumerate() {
+            lagging.observe_card(&card(*rank));
+            if dealt >= 3 {
+                prompt.observe_card(&card(cards[dealt - 3]));
+            }
+            assert_eq!(lagging.running_count, prompt.running_count);
+            assert_eq!(lagging.true_count(), prompt.true_count());
+        }
+        assert_eq!(lagging.running_count, 2);
+        lagging.observe_shuffle(6 * 52);
+        lagging.observe_card(&card(Rank::Two));
+        assert_eq!(lagging.running_count, 0);
+
+        let mut app = counting_app(471);
+        app.count_lag = 3;
+        app.rebuild_strategy();
+        stack(&mut app, &[card(Rank::Five), card(Rank::Six), card(Rank::Two), card(Rank::Ten), card(Rank::Ace)]);
+        app.play_game();
+        let dealt = app.deck.dealt.len();
+        let counted: i32 = app.deck.dealt[..dealt - 3].iter().map(|card| CountSystem::HiLo.tag(card)).sum();
+        assert_eq!(app.strategy.running_count(), Some(counted));
+    }
+
+    #[test]
+    fn a_bigger_bankroll_widens_the_recommended_spread() {
+        let spread = |bankroll: f64| {
+            let ramp = recommended_ramp(0.05, bankroll, EDGE_PER_TRUE_COUNT, 0.005, 10.0, 5.0);
+            assert_eq!(ramp.len(), MAX_RAMP_TRUE_COUNT as usize);
+            assert!(ramp.windows(2).all(|steps| steps[0].bet <= steps[1].bet), "{:?}", ramp);
+            assert_eq!(ramp[0].bet, 10.0);
+            ramp.last().unwrap().bet / ramp[0].bet
+        };
+        let small = spread(10_000.0);
+        let large = spread(40_000.0);
+        assert!(large > small, "spread {} then {}", small, large);
+
+        let mut app = counting_app(472);
+        app.bankroll = 40_000.0;
+        app.bet_ramp = app.recommend_ramp(0.05);
+        app.bet_sizing = BetSizing::Ramp;
+        assert_eq!(app.stake(), app.table_min);
+        for _ in 0..60 {
+            app.strategy.observe_card(&card(Rank::Five));
+        }
+        assert_eq!(app.stake(), app.bet_ramp.last().unwrap().bet);
+    }
+
+    #[test]
+    fn a_dealer_mimic_never_doubles_or_surrenders() {
+        let play = |kind| {
+            let mut app = quiet_app(368);
+            app.rules.surrender = SurrenderRule::Late;
+            app.strategy_kind = kind;
+            app.rebuild_strategy();
+            for _ in 0..2000 {
+                app.play_game();
+            }
+            app.action_counts
+        };
+        let mimic = play(StrategyKind::DealerMimic);
+        assert_eq!((mimic.doubles, mimic.surrenders), (0, 0));
+        assert!(mimic.hits > 0 && mimic.stands > 0);
+        assert!(mimic.distribution().contains("Double 0 (0.0%)"), "{}", mimic.distribution());
+        // Basic strategy at the same table does both
+        let basic = play(StrategyKind::Basic);
+        assert!(basic.doubles > 0 && basic.surrenders > 0);
+    }
+
+    #[test]
+    fn stats_add_up_after_a_partial_chunk() {
+        let mut app = quiet_app(371);
+        app.pending_games = 1_000_000;
+        app.start_batch();
+        app.run_pending_games();
+        let played = 1_000_000 - app.pending_games;
+        assert!(played > 0 && app.pending_games > 0, "played {}", played);
+        // Split hands are games of their own, so there are at least as many as rounds dealt
+        assert!(app.games_played >= played);
+        assert_eq!(app.wins + app.losses + app.pushes, app.games_played);
+        assert!((app.net_result() - app.net_won).abs() < 1e-6);
+        assert_eq!(app.bankroll_history.last(1), [[app.games_played as f64, app.bankroll]]);
+        let summary = app.build_summary();
+        assert_eq!((summary.hands, summary.wins), (app.games_played, app.wins));
+    }
+
+    #[test]
+    fn rule_comparison_sessions_play_the_selected_strategy() {
+        let mut app = quiet_app(456);
+        app.strategy_kind = StrategyKind::StandOn;
+        app.strategy_stand_total = 12;
+        app.rebuild_strategy();
+        let mut no_peek = app.rules.clone();
+        no_peek.peek_on_ace = false;
+        let session = app.comparison_session(&no_peek, 1, &[]);
+        assert_eq!(session.strategy.name(), app.strategy.name());
+        assert_ne!(session.strategy.name(), BasicStrategy::new().name());
+        let twelve = hand_of(&[10, 2]);
+        assert_eq!(session.strategy.determine_action(&twelve, &card(Rank::Ten)), Action::Stand);
+
+        app.strategy_kind = StrategyKind::Basic;
+        app.hi_lo = true;
+        app.count_system = CountSystem::Red7;
+        app.rebuild_strategy();
+        let session = app.comparison_session(&no_peek, 2, &[]);
+        assert_eq!(session.strategy.name(), app.strategy.name());
+        assert!(session.strategy.running_count().is_some());
+    }
+
+    #[test]
+    fn a_fresh_red_7_shoe_reads_a_true_count_of_zero() {
+        let mut app = counting_app(385);
+        app.count_system = CountSystem::Red7;
+        app.rebuild_strategy();
+        assert_eq!(app.strategy.running_count(), Some(-12));
+        assert_eq!(app.strategy.true_count(), Some(0.0));
+        let sixteen = hand_of(&[10, 6]);
+        assert_eq!(app.strategy.determine_action(&sixteen, &card(Rank::Ten)), Action::Stand);
+
+        // Two decks in, with the running count gained at the average rate
+        let mut counter = CountingStrategy::new(BasicStrategy::new(), CountSystem::Red7, 3.0);
+        counter.observe_shuffle(6 * 52);
+        counter.cards_remaining = 4 * 52;
+        counter.running_count = -8;
+        assert_eq!(counter.current_true_count(), 0.0);
+    }
+
+    #[test]
+    fn half_deck_estimation_rounds_the_true_count_divisor() {
+        let cards_remaining = 91;
+        assert_eq!(DeckEstimation::Exact.estimate(cards_remaining), 1.75);
+        assert_eq!(DeckEstimation::HalfDeck.estimate(cards_remaining), 2.0);
+        assert_eq!(DeckEstimation::WholeDeck.estimate(cards_remaining), 2.0);
+        assert_eq!(DeckEstimation::HalfDeck.estimate(70), 1.5);
+
+        let mut counter = CountingStrategy::new(BasicStrategy::new(), CountSystem::HiLo, 3.0);
+        counter.cards_remaining = cards_remaining;
+        counter.running_count = 7;
+        assert_eq!(counter.current_true_count(), 4.0);
+        counter.deck_estimation = DeckEstimation::HalfDeck;
+        assert_eq!(counter.current_true_count(), 3.5);
+    }
+
+    #[test]
+    fn a_reshuffle_can_start_the_progression_over() {
+        let losing = [card(Rank::Ten), card(Rank::Ten), card(Rank::Seven), card(Rank::Eight)];
+        for reset in [false, true] {
+            let mut app = quiet_app(411);
+            app.bet_amount = 20.0;
+            app.bet_sizing = BetSizing::Martingale;
+            app.reset_progression_on_shuffle = reset;
+            stack(&mut app, &losing);
+            app.play_game();
+            assert_eq!(app.progression_bet, Some(40.0));
+            app.reshuffle();
+            assert_eq!(app.progression_bet, if reset { None } else { Some(40.0) });
+            assert_eq!(app.stake(), if reset { 20.0 } else { 40.0 });
+        }
+    }
+
+    #[test]
+    fn count_observers_keep_their_own_counts_of_the_same_cards() {
+        let mut app = quiet_app(398);
+        app.add_count_observer(CountSystem::HiLo);
+        app.add_count_observer(CountSystem::KO);
+        assert_eq!(app.count_observers[1].running_count, -20);
+        let sevens = [Card { rank: Rank::Seven, suit: Suit::Clubs }, Card { rank: Rank::Seven, suit: Suit::Hearts }];
+        for seen in [card(Rank::Two), card(Rank::Five), card(Rank::King), sevens[0], card(Rank::Ace), sevens[1], card(Rank::Six)] {
+            app.observe_card(&seen);
+        }
+        assert_eq!(app.count_observers[0].running_count, 1);
+        assert_eq!(app.count_observers[1].running_count, -20 + 3);
+        // The player's own strategy doesn't count
+        assert_eq!(app.strategy.running_count(), None);
+
+        app.reshuffle();
+        assert_eq!(app.count_observers[0].running_count, 0);
+        assert_eq!(app.count_observers[1].running_count, -20);
+        assert_eq!(app.count_observers[1].true_count(), 0.0);
+    }
+
+    #[test]
+    fn csv_amounts_use_two_decimals_and_a_dot() {
+        assert_eq!(csv_amount(12.5), "12.50");
+        assert_eq!(csv_amount(-0.001), "0.00");
+        let mut app = quiet_app(397);
+        app.record_games = true;
+        app.bet_amount = 12.5;
+        stack(&mut app, &[card(Rank::Ten), card(Rank::Ten), card(Rank::Ten), card(Rank::Seven)]);
+        app.play_game();
+        let row = app.game_rows_csv().lines().nth(2).unwrap().to_string();
+        assert!(row.ends_with(",12.50,1012.50"), "{}", row);
+        assert!(!row.contains("12,50"));
+    }
+
+    #[test]
+    fn what_if_tables_on_the_same_shoes_chart_the_same_curve_for_the_same_strategy() {
+        let mut app = quiet_app(359);
+        app.bet_amount = 20.0;
+        for _ in 0..30 {
+            app.play_game();
+        }
+        app.add_what_if(StrategyKind::Basic);
+        app.add_what_if(StrategyKind::NeverBust);
+        app.add_what_if(StrategyKind::Basic);
+        let shoes = app.completed_shoes;
+        for _ in 0..400 {
+            app.play_game();
+        }
+        assert!(app.completed_shoes >= shoes + 3);
+        let curve = |table: &BlackjackApp| table.bankroll_history.last(400);
+        assert_eq!(curve(&app.what_ifs[0]), curve(&app));
+        assert_eq!(curve(&app.what_ifs[2]), curve(&app));
+        assert_eq!(app.what_ifs[0].bankroll, app.bankroll);
+        assert_ne!(curve(&app.what_ifs[1]), curve(&app));
+        app.reshuffle();
+        assert!(app.what_ifs.iter().all(|what_if| what_if.deck.cards == app.deck.cards));
+        assert_eq!(app.what_ifs[1].strategy.name(), simple_strategy(StrategyKind::NeverBust, 17, &app.rules).name());
+    }
+
+    #[test]
+    fn a_million_exact_cent_settlements_leave_no_drift() {
+        let mut app = quiet_app(449);
+        app.exact_cents = true;
+        app.rules.blackjack_payout = BlackjackPayout::SixToFive;
+        app.round_bet = 0.3;
+        let results = [
+            (GameResult::PlayerBlackjack, 36),
+            (GameResult::DealerWin, -30),
+            (GameResult::PlayerWin, 30),
+            (GameResult::Surrender, -15),
+            (GameResult::DoubledWin, 60),
+            (GameResult::DoubledLose, -60),
+            (GameResult::Push, 0),
+        ];
+        let mut settled_cents: i64 = 0;
+        for hand in 0..1_000_000 {
+            let (result, cents) = &results[hand % results.len()];
+            app.games_played += 1;
+            app.pay_bet(result);
+            settled_cents += cents;
+            if hand % 1000 == 0 {
+                app.drain_events();
+            }
+        }
+        assert_eq!(app.bankroll, from_cents(to_cents(app.starting_bankroll) + settled_cents));
+        assert_eq!(app.net_won, from_cents(settled_cents));
+    }
+
+    #[test]
+    fn a_rotated_log_rolls_to_the_first_free_numbered_file() {
+        let dir = std::env::temp_dir().join(format!("countertoolbox-rotation-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = |name: &str| dir.join(name).to_str().unwrap().to_string();
+        let log = file("blackjack_log.txt");
+        std::fs::write(&log, "hand\n").unwrap();
+        std::fs::write(file("blackjack_log.1.txt"), "kept\n").unwrap();
+
+        assert!(!LogRotation::Hands(3).rotate(2, &log));
+        assert!(LogRotation::Hands(3).rotate(3, &log));
+        assert!(!std::path::Path::new(&log).exists());
+        assert_eq!(std::fs::read_to_string(file("blackjack_log.2.txt")).unwrap(), "hand\n");
+        assert_eq!(std::fs::read_to_string(file("blackjack_log.1.txt")).unwrap(), "kept\n");
+
+        std::fs::write(&log, vec![b'x'; 1000]).unwrap();
+        assert!(!LogRotation::Kilobytes(1).rotate(0, &log));
+        std::fs::write(&log, vec![b'x'; 1024]).unwrap();
+        assert!(LogRotation::Kilobytes(1).rotate(0, &log));
+        assert!(std::path::Path::new(&file("blackjack_log.3.txt")).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn two_sessions_merged_into_a_stats_file_add_up() {
+        let dir = std::env::temp_dir().join(format!("countertoolbox-stats-{}", std::process::id()));
+        let path = dir.join("basic_strategy.json");
+        let session = |seed, games| {
+            let mut app = quiet_app(seed);
+            for _ in 0..games {
+                app.play_game();
+            }
+            app
+        };
+        let (first, mut second) = (session(460, 50), session(461, 80));
+        let fingerprint = first.rules.fingerprint();
+        merge_strategy_stats(&path, "other rules", &first.session_stats()).unwrap();
+        merge_strategy_stats(&path, &fingerprint, &first.session_stats()).unwrap();
+        let lifetime = merge_strategy_stats(&path, &fingerprint, &second.session_stats()).unwrap();
+        let (a, b) = (first.session_stats(), second.session_stats());
+        assert_eq!(lifetime.hands, a.hands + b.hands);
+        assert_eq!((lifetime.wins, lifetime.losses, lifetime.pushes), (a.wins + b.wins, a.losses + b.losses, a.pushes + b.pushes));
+        assert_eq!(lifetime.total_wagered, a.total_wagered + b.total_wagered);
+        assert_eq!(lifetime.net_won, a.net_won + b.net_won);
+
+        // Saving again only adds the hands played since the last save
+        for _ in 0..10 {
+            second.play_game();
+        }
+        let lifetime = merge_strategy_stats(&path, &fingerprint, &second.session_stats().since(&b)).unwrap();
+        assert_eq!(lifetime.hands, a.hands + second.session_stats().hands);
+        let stored = load_strategy_stats(&path).unwrap();
+        assert_eq!(stored[&fingerprint], lifetime);
+        assert_eq!(stored["other rules"], a);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unit_mode_shows_the_bankroll_in_table_minimums() {
+        let mut app = quiet_app(357);
+        app.table_min = 10.0;
+        app.bankroll = 1000.0;
+        assert_eq!(app.format_amount(app.bankroll), "$1000.00");
+        app.show_units = true;
+        assert_eq!(app.format_amount(app.bankroll), "100.00 units");
+        assert_eq!(app.format_amount(-25.0), "-2.50 units");
+    }
+
+    #[test]
+    fn auto_play_spaces_hands_by_the_delay() {
+        let start = std::time::Instant::now();
+        let delay = std::time::Duration::from_millis(500);
+        let at = |ms| start + std::time::Duration::from_millis(ms);
+        let mut autoplay = AutoPlay::start(2, delay, start);
+        // The first hand is dealt straight away
+        assert!(autoplay.is_due(start));
+        autoplay.mark_played(at(10));
+        assert_eq!(autoplay.remaining, 1);
+        assert!(!autoplay.is_due(at(300)));
+        assert_eq!(autoplay.wait(at(300)), std::time::Duration::from_millis(210));
+        assert!(autoplay.is_due(at(510)));
+        // Once the last hand is dealt nothing is due and there is nothing to wait for
+        autoplay.mark_played(at(700));
+        assert_eq!(autoplay.remaining, 0);
+        assert!(!autoplay.is_due(at(5000)));
+        assert_eq!(autoplay.wait(at(5000)), std::time::Duration::ZERO);
+    }
+
+    #[test]
+    fn hourly_loss_is_edge_times_average_bet_times_hands_per_hour() {
+        // Half a percent on a $25 average bet at 80 hands an hour is $10 an hour
+        assert!((expected_hourly_loss(0.005, 25.0, 80) - 10.0).abs() < 1e-9);
+        assert!((expected_hourly_loss(-0.01, 50.0, 100) + 50.0).abs() < 1e-9);
+
+        let mut app = quiet_app(388);
+        app.hands_per_hour = 60;
+        app.session_hours = 4.0;
+        for _ in 0..200 {
+            app.play_game();
+        }
+        let summary = app.build_summary();
+        let hourly = app.house_edge() * (app.total_wagered / app.games_played as f64) * 60.0;
+        assert!((summary.hourly_loss - hourly).abs() < 1e-9);
+        let line = format!("${:.2} per 4-hour session at 60 hands/hour", summary.hourly_loss * 4.0);
+        assert!(summary.to_string().contains(&line), "{}", summary);
+    }
+
+    #[test]
+    fn a_known_rule_set_gives_its_fingerprint() {
+        let rules = RulesConfig {
+            decks: 6,
+            dealer: DealerRule { stand_total: 17, hit_soft: true },
+            surrender: SurrenderRule::Late,
+            penetration: Penetration::Fraction(0.75),
+            ..RulesConfig::default()
+        };
+        assert_eq!(rules.fingerprint(), "6D H17 DA2 LS BJ3:2 75%pen");
+
+        // Each key rule shows up in its own token, so changing one changes the fingerprint
+        let variants = [
+            (RulesConfig { decks: 2, ..rules.clone() }, "2D"),
+            (RulesConfig { dealer: DealerRule { stand_total: 17, hit_soft: false }, ..rules.clone() }, "S17"),
+            (RulesConfig { double_rule: DoubleRule::TenToEleven, ..rules.clone() }, "D10"),
+            (RulesConfig { surrender: SurrenderRule::None, ..rules.clone() }, "NS"),
+            (RulesConfig { blackjack_payout: BlackjackPayout::SixToFive, ..rules.clone() }, "BJ6:5"),
+            (RulesConfig { double_after_split: false, ..rules.clone() }, "NDAS"),
+            (RulesConfig { peek_on_ace: false, peek_on_ten: false, ..rules.clone() }, "ENHC"),
+            (RulesConfig { penetration: Penetration::Fraction(0.5), ..rules.clone() }, "50%pen"),
+        ];
+        for (variant, token) in variants {
+            let fingerprint = variant.fingerprint();
+            assert!(fingerprint.split(' ').any(|part| part == token), "{} in {}", token, fingerprint);
+            assert_ne!(fingerprint, rules.fingerprint());
+        }
+    }
+
+    #[test]
+    fn each_preset_sets_its_documented_rules() {
+        let defaults = RulesConfig::default();
+        let strip = RulePreset::VegasStrip.rules();
+        assert_eq!(strip.fingerprint(), defaults.fingerprint());
+        assert_eq!(RulePreset::AtlanticCity.rules().fingerprint(), defaults.fingerprint());
+
+        let downtown = RulePreset::Downtown.rules();
+        assert_eq!(downtown.dealer, DealerRule { stand_total: 17, hit_soft: true });
+
+        let european = RulePreset::European.rules();
+        assert!(!european.peek_on_ace && !european.peek_on_ten, "European is no hole card");
+        assert!(!european.dealer.hit_soft);
+        assert_eq!(european.double_rule, DoubleRule::NineToEleven);
+        assert!(!european.double_rule.allows(&hand_of(&[6, 2])));
+        assert!([[5, 4], [6, 4], [5, 6]].iter().all(|cards| european.double_rule.allows(&hand_of(cards))));
+        assert!(european.fingerprint().contains("D9") && european.fingerprint().contains("ENHC"));
+
+        let double_exposure = RulePreset::DoubleExposure.rules();
+        assert!(double_exposure.dealer_exposes_both && double_exposure.dealer_wins_ties);
+        assert_eq!(double_exposure.blackjack_payout, BlackjackPayout::EvenMoney);
+    }
+
+    #[test]
+    fn computed_bets_round_down_to_the_bet_increment() {
+        let mut app = quiet_app(4);
+        app.table_min = 5.0;
+        app.bet_increment = 5.0;
+        app.bankroll = 237.0;
+        app.bet_sizing = BetSizing::FixedFraction(0.1);
+        assert_eq!(app.stake(), 20.0);
+        app.bet_increment = 1.0;
+        assert_eq!(app.stake(), 23.0);
+    }
+
+    #[test]
+    fn count_calibration_divides_by_the_decks_remaining() {
+        let mut app = quiet_app(5);
+        app.deck = Deck::new(2);
+        stack(&mut app, &[card(Rank::Five), card(Rank::Two), card(Rank::King), card(Rank::Six), card(Rank::Three)]);
+        let steps = app.run_count_calibration();
+        assert_eq!(steps.len(), 109);
+        assert_eq!(steps[4].card, card(Rank::Three));
+        assert_eq!(steps[4].running_count, 3);
+        assert_eq!(steps[4].cards_remaining, 104);
+        assert!((steps[4].true_count - 3.0 / 2.0).abs() < 1e-9);
+        // Two decks are balanced, so the whole shoe counts back to the five stacked cards
+        assert_eq!(steps[108].running_count, 3);
+    }
+
+    // Asserts the chart's play for each upcard 2 through ace, given as action codes
+    fn assert_chart_row(strategy: &dyn PlayStrategy, rules: &RulesConfig, cards: &[u8], expected: &str) {
+        let hand = hand_of(cards);
+        let played: String = (2..=11)
+            .map(|upcard| applied_first_action(strategy, &hand, &hand_of(&[upcard]).cards[0], rules).code())
+            .collect();
+        assert_eq!(played, expected, "{:?} against 2 through A", cards);
+    }
+
+    #[test]
+    fn basic_strategy_splits_pairs_by_the_chart() {
+        let rules = RulesConfig::default();
+        let strategy = BasicStrategy::new();
+        for (cards, expected) in [
+            ([2, 2], "PPPPPPHHHH"),
+            ([3, 3], "PPPPPPHHHH"),
+            ([4, 4], "HHHPPHHHHH"),
+            ([5, 5], "DDDDDDDDHH"),
+            ([6, 6], "HPPPPHHHHH"),
+            ([7, 7], "PPPPPPHHHH"),
+            ([8, 8], "PPPPPPPPPP"),
+            ([9, 9], "PPPPPSPPSS"),
+            ([10, 10], "SSSSSSSSSS"),
+            ([11, 11], "PPPPPPPPPP"),
+        ] {
+            assert_chart_row(&strategy, &rules, &cards, expected);
+        }
+    }
+
+    #[test]
+    fn basic_strategy_soft_doubles_are_gated_by_the_upcard() {
+        let rules = RulesConfig::default();
+        let strategy = BasicStrategy::new();
+        for (cards, expected) in [
+            ([11, 2], "HHHDDHHHHH"),
+            ([11, 3], "HHHDDHHHHH"),
+            ([11, 4], "HHDDDHHHHH"),
+            ([11, 5], "HHDDDHHHHH"),
+            ([11, 6], "HDDDDHHHHH"),
+        ] {
+            assert_chart_row(&strategy, &rules, &cards, expected);
+        }
+    }
+
+    #[test]
+    fn a_refused_soft_double_falls_back_to_the_charted_hit_or_stand() {
+        // Soft 18 against a 3 stands, and soft 17 against a 4 hits
+        for (second, upcard, applied) in [(Rank::Seven, Rank::Three, Action::Stand), (Rank::Six, Rank::Four, Action::Hit)] {
+            let mut app = quiet_app(421);
+            app.rules.double_rule = DoubleRule::NineToEleven;
+            stack(&mut app, &[Rank::Ace, upcard, second, Rank::Ten].map(card));
+            app.play_game();
+            let refused = GameEvent::IllegalAction { attempted: Action::DoubleDown, applied };
+            assert!(app.events.contains(&refused), "{:?}", refused);
+        }
+    }
+
+    fn counting_app(seed: u64) -> BlackjackApp {
+        let mut app = quiet_app(seed);
+        app.hi_lo = true;
+        app.rebuild_strategy();
+        app
+    }
+
+    #[test]
+    fn hole_card_is_counted_only_when_revealed() {
+        let mut app = counting_app(7);
+        let count = app.strategy.running_count();
+        let mut dealer_hand = Hand::new();
+        stack(&mut app, &[card(Rank::King)]);
+        app.deal_hole_card(&mut dealer_hand);
+        assert_eq!(app.strategy.running_count(), count);
+        app.reveal_hole_card();
+        assert_eq!(app.strategy.running_count(), count.map(|count| count - 1));
+    }
+
+    #[test]
+    fn dealer_draws_reach_the_count_before_the_next_bet() {
+        let mut app = counting_app(430);
+        app.rules.decks = 1;
+        app.reshuffle();
+        app.rebuild_strategy();
+        app.bet_sizing = BetSizing::Ramp;
+        app.bet_ramp = vec![RampStep { true_count: 0, bet: 10.0 }, RampStep { true_count: 3, bet: 50.0 }];
+        assert_eq!(app.stake(), 10.0);
+        // 20 stands against a 6; the dealer's 12 draws three small cards to 19
+        let deal = [Rank::Ten, Rank::Six, Rank::Queen, Rank::Six, Rank::Two, Rank::Two, Rank::Three];
+        stack(&mut app, &deal.map(card));
+        app.play_game();
+        assert_eq!(app.last_game_result, Some(GameResult::PlayerWin));
+        // The first four cards cancel out; the dealer's draws are all that's left
+        assert_eq!(app.strategy.running_count(), Some(3));
+        assert!(app.strategy.true_count().unwrap() >= 3.0);
+        assert_eq!(app.stake(), 50.0);
+    }
+
+    #[test]
+    fn hole_card_stays_out_of_the_next_shoe_count() {
+        let mut app = counting_app(8);
+        app.deck.cards = vec![card(Rank::King)];
+        let mut dealer_hand = Hand::new();
+        app.deal_hole_card(&mut dealer_hand);
+        // The shoe runs dry mid-round
+        app.draw_card();
+        assert_eq!(app.strategy.running_count(), Some(0));
+        app.reveal_hole_card();
+        assert_eq!(app.strategy.running_count(), Some(0));
+    }
+
+    #[test]
+    fn monte_carlo_records_every_session_from_the_starting_bankroll() {
+        let mut app = quiet_app(9);
+        let result = app.run_monte_carlo(100, 20);
+        assert_eq!(result.final_bankrolls.len(), 100);
+
+        app.starting_bankroll = 5000.0;
+        let result = app.run_monte_carlo(10, 0);
+        assert!(result.final_bankrolls.iter().all(|&bankroll| bankroll == 5000.0));
+        assert_eq!(result.probability_ahead(), 0.0);
+    }
+
+    #[test]
+    fn monte_carlo_results_do_not_depend_on_the_thread_count() {
+        let run = |threads: usize| {
+            let mut app = quiet_app(453);
+            app.cash_out_target = Some(1100.0);
+            app.monte_carlo_threads = threads;
+            app.run_monte_carlo(23, 50)
+        };
+        let single = run(1);
+        let threaded = run(4);
+        assert_eq!(single.final_bankrolls, threaded.final_bankrolls);
+        assert_eq!(single.goals_reached, threaded.goals_reached);
+        assert_ne!(single.final_bankrolls.first(), single.final_bankrolls.last());
+    }
+
+    #[test]
+    fn six_to_five_loses_more_than_three_to_two_on_the_same_shoes() {
+        let mut app = quiet_app(21);
+        app.starting_bankroll = 100_000.0;
+        let six_to_five = RulesConfig { blackjack_payout: BlackjackPayout::SixToFive, ..app.rules.clone() };
+        app.comparison_rules = vec![app.rules.clone(), six_to_five];
+        app.comparison_hands = 2000;
+        let rows = app.run_rule_comparison();
+        assert_eq!(rows[0].hands, rows[1].hands);
+        assert!(rows[1].net_won < rows[0].net_won);
+        assert!(rows[1].house_edge() > rows[0].house_edge());
+    }
+
+    #[test]
+    fn rule_comparison_stops_at_the_loss_limit() {
+        let mut app = quiet_app(22);
+        app.bet_amount = 100.0;
+        app.loss_limit = Some(900.0);
+        app.comparison_rules = vec![app.rules.clone()];
+        app.comparison_hands = 1000;
+        let rows = app.run_rule_comparison();
+        assert!(rows[0].hands < 1000);
+        assert!(rows[0].net_won <= -100.0);
+    }
+
+    #[test]
+    fn a_hidden_blackjack_takes_doubled_and_split_stakes_unless_original_bets_only() {
+        // 11 doubles against a ten to 20; 8-8 splits and both hands make 18
+        let doubled = [Rank::Six, Rank::Ten, Rank::Five, Rank::Ace, Rank::Nine];
+        let split = [Rank::Eight, Rank::King, Rank::Eight, Rank::Ace, Rank::Ten, Rank::Ten];
+        for (deal, hands) in [(&doubled[..], 1), (&split[..], 2)] {
+            for (original_bets_only, lost_bets) in [(false, 2.0), (true, 1.0)] {
+                let mut app = quiet_app(370);
+                app.rules.peek_on_ace = false;
+                app.rules.peek_on_ten = false;
+                app.rules.original_bets_only = original_bets_only;
+                stack(&mut app, &deal.iter().map(|rank| card(*rank)).collect::<Vec<_>>());
+                let bankroll = app.bankroll;
+                app.play_game();
+                assert_eq!(app.games_played, hands);
+                assert_eq!(app.bankroll, bankroll - lost_bets * app.bet_amount, "{:?}, OBO {}", deal, original_bets_only);
+            }
+        }
+    }
+
+    #[test]
+    fn a_ten_up_with_no_ten_peek_plays_on_over_a_hidden_ace() {
+        // 12 against a ten hits to 17 before the dealer's blackjack is turned over
+        let deal = [Rank::Ten, Rank::Ten, Rank::Two, Rank::Ace, Rank::Five].map(card);
+        for (peek_on_ten, cards_dealt) in [(false, 5), (true, 4)] {
+            let mut app = quiet_app(454);
+            app.rules.peek_on_ace = true;
+            app.rules.peek_on_ten = peek_on_ten;
+            stack(&mut app, &deal);
+            let dealt = app.deck.dealt.len();
+            let bankroll = app.bankroll;
+            app.play_game();
+            assert_eq!(app.deck.dealt.len() - dealt, cards_dealt, "peek on ten {}", peek_on_ten);
+            assert_eq!(app.last_game_result, Some(GameResult::DealerWin));
+            assert_eq!(app.bankroll, bankroll - app.bet_amount);
+            assert_eq!(app.action_counts.total(), if peek_on_ten { 0 } else { 2 });
+        }
+    }
+
+    #[test]
+    fn original_bets_only_refunds_every_split_hand_against_a_hidden_blackjack() {
+        let mut app = quiet_app(6);
+        app.rules.peek_on_ace = false;
+        app.rules.peek_on_ten = false;
+        app.rules.original_bets_only = true;
+        // 8-8 splits against a ten; the first hand stands on 18, the second hits 14 and busts
+        let deal = [Rank::Eight, Rank::Ten, Rank::Eight, Rank::Ace, Rank::Ten, Rank::Six, Rank::King];
+        stack(&mut app, &deal.map(card));
+        let bankroll = app.bankroll;
+        app.play_game();
+        assert_eq!(app.games_played, 2);
+        assert_eq!(app.bankroll, bankroll - app.bet_amount);
+
+        app.rules.original_bets_only = false;
+        stack(&mut app, &deal.map(card));
+        let bankroll = app.bankroll;
+        app.play_game();
+        assert_eq!(app.bankroll, bankroll - 2.0 * app.bet_amount);
+    }
+}
@@ -1,496 +1,227 @@
+use countertoolbox::*;
 use eframe::egui;
-use rand::{Rng};
-use serde::de;
-//use serde::de;
-use std::fs::OpenOptions;
-use std::io::Write;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum Suit {
-    Hearts,
-    Diamonds,
-    Clubs,
-    Spades,
-}
+// The egui front end over the engine in lib.rs
+struct SimulatorApp(BlackjackApp);
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-struct Card {
-    rank: u8,
-    suit: Suit,
-}
-impl Card {
-    fn value(&self) -> u8 {
-        match self.rank {
-            1 => 11, // Ace
-            11 | 12 | 13 => 10, // Face cards
-            _ => self.rank,
-        }
-    }
-
-    fn name(&self) -> String {
-        let rank_str = match self.rank {
-            1 => "A".to_string(),
-            11 => "J".to_string(),
-            12 => "Q".to_string(),
-            13 => "K".to_string(),
-            _ => self.rank.to_string(),
-        };
-        let suit_str = match self.suit {
-            Suit::Hearts => "♥",
-            Suit::Diamonds => "♦",
-            Suit::Clubs => "♣",
-            Suit::Spades => "♠",
-        };
-        format!("{}{}", rank_str, suit_str)
+impl Default for SimulatorApp {
+    fn default() -> Self {
+        SimulatorApp(BlackjackApp::restore_session())
     }
 }
 
-#[derive(Debug, Clone)]
-struct Hand {
-    cards: Vec<Card>,
-    doubled: bool,
-    split: bool,
-    first_action: bool,
-    live: bool,
-    stood: bool,
-}
-impl Hand {
-    fn new() -> Self {
-        Self { cards: Vec::new(), doubled: false, split: false, first_action: true, live: true, stood: false }        
-    }
-
-    fn add_card(&mut self, card: Card) {
-        self.cards.push(card);
-    }
-
-    fn total(&self) -> u8 {
-        let mut total = 0;
-        let mut aces = 0;
-        for card in &self.cards {
-            total += card.value();
-            if card.rank == 1 {
-                aces += 1;
-            }
-        }
-        while total > 21 && aces > 0 {
-            total -= 10;
-            aces -= 1;
+// Checkbox plus amount for an optional limit; returns whether either was changed
+fn optional_amount(ui: &mut egui::Ui, label: &str, value: &mut Option<f64>, default: f64) -> bool {
+    let mut enabled = value.is_some();
+    let mut changed = ui.checkbox(&mut enabled, label).changed();
+    match (enabled, value.as_mut()) {
+        (true, Some(amount)) => {
+            changed |= ui.add(egui::DragValue::new(amount).range(0.0..=1_000_000.0).speed(10.0).prefix("$")).changed();
         }
-        total
+        (true, None) => *value = Some(default),
+        (false, _) => *value = None,
     }
-
-    fn is_blackjack(&self) -> bool {
-        self.cards.len() == 2 && self.total() == 21
-    }
-
-    fn is_busted(&self) -> bool {
-        self.total() > 21
-    }
-
-    fn is_soft(&self) -> bool {
-        let mut total = 0;
-        let mut aces = 0;
-        for card in &self.cards {
-            total += card.value();
-            if card.rank == 1 {
-                aces += 1;
-            }
-        }
-        aces > 0 && total <= 21
-    }
-
-    fn display(&self) -> String {
-        self.cards.iter()
-            .map(|c| c.name())
-            .collect::<Vec<_>>()
-            .join(", ")
-    }
-
+    changed
 }
 
-struct Deck {
-    cards: Vec<Card>,
+fn stat_row(ui: &mut egui::Ui, name: &str, value: String) {
+    ui.label(name);
+    ui.label(value);
+    ui.end_row();
 }
 
-impl Deck {
-    fn new(count: u8) -> Deck {
-        let mut cards = Vec::new();
-        for _ in 0..count {    
-            for &suit in &[Suit::Hearts, Suit::Diamonds, Suit::Clubs, Suit::Spades] {
-                for rank in 1..=13 {
-                    cards.push(Card { rank, suit });
-                }
-            }
+impl eframe::App for SimulatorApp {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if let Err(err) = self.0.save_session() {
+            eprintln!("{}", err);
         }
-
-        Deck { cards }
     }
 
-    fn shuffle(&mut self) {
-        let mut rng = rand::rng();
-        for i in (1..self.cards.len()).rev() {
-            let j = rng.random_range(0..=i);
-            self.cards.swap(i, j);
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let app = &mut self.0;
+        if app.pending_games > 0 || app.pending_shoes > 0 {
+            app.run_pending_games();
+            ctx.request_repaint();
         }
-    }
-
-    fn deal_card(&mut self) -> Option<Card> {
-        self.cards.pop()
-    }
-}
-
-#[derive(Debug, Clone, PartialEq)]
-enum GameResult {
-    PlayerWin,
-    DealerWin,
-    Push,
-    PlayerBlackjack,
-    Surrender,
-    DoubledWin,
-    DoubledLose,
-}
-
-struct BlackjackApp {
-    last_game_result: Option<GameResult>,
-    games_played: u32,
-    wins: u32,
-    losses: u32,
-    pushes: u32,
-    deck: Deck,
-    bankroll: f64,
-    bet_amount: f64,
-    strategy: Box<dyn PlayStrategy>,
-}
-
-impl Default for BlackjackApp {
-    fn default() -> Self {
-        let mut new_deck = Deck::new(6);
-        new_deck.shuffle();
-        Self {
-            last_game_result: None,            
-            games_played: 0,
-            wins: 0,
-            losses: 0,
-            pushes: 0,
-            deck: new_deck,
-            bankroll: 1000.0,
-            bet_amount: 10.0,
-            strategy: Box::new(BasicStrategy{}),
+        if let Some(wait) = app.run_autoplay() {
+            ctx.request_repaint_after(wait);
         }
-    }
-}
-
-#[derive(Debug, Clone, PartialEq)]
-enum Action {
-    Hit,
-    Stand,
-    DoubleDown,
-    Split,
-    Surrender,
-}
-
-trait PlayStrategy {
-    fn determine_action(&self, player_hand: &Hand, dealer_upcard: &Card) -> Action;
-    fn determine_first_action(&self, player_hand: &Hand, dealer_upcard: &Card) -> Action;
-
-}
-
-
-struct BasicStrategy;
+        if app.pending_games == 0 && app.pending_shoes == 0 && app.autoplay.is_none() && let Some(start) = app.batch_start.take() {
+            app.batch_banner = Some((start.describe(app), std::time::Instant::now()));
+        }
+        let events = app.drain_events();
+        if let Some(GameEvent::Milestone { description }) = events.iter().rev().find(|event| matches!(event, GameEvent::Milestone { .. })) {
+            app.milestone_banner = Some((description.clone(), std::time::Instant::now()));
+        }
+        app.event_log.extend(events.iter().map(|event| event.to_string()));
+        let overflow = app.event_log.len().saturating_sub(EVENT_LOG_LINES);
+        app.event_log.drain(..overflow);
 
-impl PlayStrategy for BasicStrategy {
-    fn determine_first_action(&self, player_hand: &Hand, dealer_upcard: &Card) -> Action {
-        let player_total = player_hand.total();
-        let dealer_value = dealer_upcard.value();
-        if player_hand.is_soft() {
-            if player_hand.cards[0].rank == 1 && player_hand.cards[1].rank == 1 {
-                return Action::Split;
-            }
-            if player_total == 19 && dealer_value == 6 {
-                return Action::DoubleDown;
-            }
-            if player_total == 18 && dealer_value <= 6 {
-                return Action::DoubleDown;
-            }
-            if player_total == 17 && (3..=6).contains(&dealer_value) {
-                return Action::DoubleDown;
-            }
-            if player_total == 16 || player_total == 15 && (4..=6).contains(&dealer_value) {
-                return Action::DoubleDown;
+        // Sitting out short of the bet only lasts until the bet or the policy changes
+        if app.stop_reason == Some(StopReason::ShortOfBet) && !app.short_of_bet() {
+            app.stop_reason = None;
+        }
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let can_play = app.bankroll >= app.table_min && app.stop_reason.is_none();
+            let previous_decks = app.rules.decks;
+            ui.heading("Blackjack Simulator");
+            if let Some((banner, shown_at)) = &app.batch_banner {
+                match BANNER_DURATION.checked_sub(shown_at.elapsed()) {
+                    Some(remaining) => {
+                        ui.colored_label(egui::Color32::LIGHT_GREEN, banner);
+                        ctx.request_repaint_after(remaining);
+                    }
+                    None => app.batch_banner = None,
+                }
             }
-            if player_total == 14 || player_total == 13 && (5..=6).contains(&dealer_value) {
-                return Action::DoubleDown;
-            }    
-        }        
-        if player_hand.cards[0].rank == player_hand.cards[1].rank {
-            if player_total == 18 {
-                if dealer_value < 7 || dealer_value >= 10 {
-                    return Action::Split;
+            if let Some((banner, shown_at)) = &app.milestone_banner {
+                match BANNER_DURATION.checked_sub(shown_at.elapsed()) {
+                    Some(remaining) => {
+                        ui.colored_label(egui::Color32::GOLD, banner);
+                        ctx.request_repaint_after(remaining);
+                    }
+                    None => app.milestone_banner = None,
                 }
             }
-            if player_total == 16 {
-                    return Action::Split;
+            if ui.add_enabled(can_play, egui::Button::new("Play Game")).clicked() {
+                app.play_game();
+                if !app.check_bankroll() {
+                    app.show_summary = true;
                 }
-            if player_total == 14 && dealer_value <= 7 {
-                    return Action::Split;
+            }
+            if ui.add_enabled(can_play, egui::Button::new("Play 1000 Games")).clicked() {
+                #[cfg(feature = "profiling")]
+                if app.pending_games == 0 {
+                    app.clear_profile();
                 }
-            if player_total == 12 && (3..= 7).contains(&dealer_value) {
-                    return Action::Split;
+                app.pending_games += 1000;
+                app.start_batch();
+            }
+            ui.horizontal(|ui| {
+                if ui.add_enabled(can_play && app.pending_shoes == 0, egui::Button::new("Play Shoes")).clicked() {
+                    app.start_shoe_batch(app.shoes_to_play);
                 }
-            if player_total == 6 || player_total == 4 && (4..= 7).contains(&dealer_value) {
-                    return Action::Split;
+                ui.add(egui::DragValue::new(&mut app.shoes_to_play).range(1..=1000).suffix(" shoes"));
+            });
+            ui.horizontal(|ui| {
+                if ui.add_enabled(can_play, egui::Button::new("Auto-play")).clicked() {
+                    let delay = std::time::Duration::from_millis(app.autoplay_delay_ms);
+                    app.autoplay = Some(AutoPlay::start(app.autoplay_hands, delay, std::time::Instant::now()));
+                    app.start_batch();
                 }
+                ui.add(egui::DragValue::new(&mut app.autoplay_hands).range(1..=1000).suffix(" hands"));
+                ui.add(egui::DragValue::new(&mut app.autoplay_delay_ms).range(0..=5000).suffix(" ms"));
+            });
+            if let Some(autoplay) = &app.autoplay {
+                let remaining = autoplay.remaining;
+                ui.horizontal(|ui| {
+                    ui.label(format!("Auto-playing, {} hands left", remaining));
+                    if ui.button("Stop").clicked() {
+                        app.autoplay = None;
+                    }
+                });
             }
-
-        if player_total == 16 && (9..= 11).contains(&dealer_value) {
-            return Action::Surrender;
-        }
-        if player_total == 15 && dealer_value == 10 {
-            return Action::Surrender;
-        }
-        if player_total == 11 {
-            return Action::DoubleDown;
-        }
-        if player_total == 10 && dealer_value < 10 {
-            return Action::DoubleDown;
-        }
-        if player_total == 9 && (3..= 6).contains(&dealer_value) {
-            return Action::DoubleDown;
-        }
-
-        return Action::Stand
-    }
-        
-    fn determine_action(&self, player_hand: &Hand, dealer_upcard: &Card) -> Action {
-        let player_total = player_hand.total();
-        let dealer_value = dealer_upcard.value();
-       
-        if player_hand.is_soft() {
-            if player_total <= 17 {
-                return Action::Hit;
-            }
-            if player_total == 18 && dealer_value >= 9 {
-                return Action::Hit;
+            if app.pending_games > 0 {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} games remaining", app.pending_games));
+                    if ui.button("Stop").clicked() {
+                        app.pending_games = 0;
+                    }
+                });
             }
-        }
-
-        if player_total <= 11 {
-            return Action::Hit;
-        }
-        if player_total == 12 && (dealer_value < 4 || dealer_value > 6) {
-            return Action::Hit;
-        }
-        if (13..= 16).contains(&player_total) && dealer_value >= 7 {
-            return Action::Hit;
-        }
-
-        return Action::Stand;
-    }
-}
-
-impl BlackjackApp {
-    fn play_game(&mut self) {
-        // Placeholder cut off of 15 cards to reshuffle
-        if self.deck.cards.len() < 15 {
-            self.deck = Deck::new(6);
-            self.deck.shuffle();
-        }
-        self.bet_amount = 10.0;
-
-        let mut player_hand = Hand::new();
-        let mut dealer_hand = Hand::new();
-
-        player_hand.add_card(self.deck.deal_card().unwrap());
-        dealer_hand.add_card(self.deck.deal_card().unwrap());
-        player_hand.add_card(self.deck.deal_card().unwrap());
-        dealer_hand.add_card(self.deck.deal_card().unwrap());
-
-
-
-        
-        if player_hand.is_blackjack() && dealer_hand.is_blackjack() {
-            self.last_game_result = Some(GameResult::Push);
-            self.pushes += 1;
-            self.games_played += 1;
-            let log = format!("*** Game {} ***\nPlayer's hand: {} (Total: {})\nDealer's hand: {} (Total: {})\nBoth have Blackjack! Push!\n", 
-                self.games_played, player_hand.display(), player_hand.total(), dealer_hand.display(), dealer_hand.total());
-            self.append_log(&log);
-            return;
-        } else if dealer_hand.is_blackjack() {
-            self.last_game_result = Some(GameResult::DealerWin);
-            self.losses += 1;
-            self.games_played += 1;
-            let log = format!("*** Game {} ***\nPlayer's hand: {} (Total: {})\nDealer's hand: {} (Total: {})\nBlackjack! Dealer wins!\n", 
-                self.games_played, player_hand.display(), player_hand.total(), dealer_hand.display(), dealer_hand.total());
-            self.append_log(&log);
-            self.pay_bet(&GameResult::DealerWin);
-            return;
-        } else if player_hand.is_blackjack() {
-            self.last_game_result = Some(GameResult::PlayerBlackjack);
-            self.wins += 1;
-            self.games_played += 1;
-            let log = format!("*** Game {} ***\nPlayer's hand: {} (Total: {})\nDealer shows: {}\nBlackjack! Player wins!\n", 
-                self.games_played, player_hand.display(), player_hand.total(), dealer_hand.cards[0].name());
-            self.append_log(&log);
-            self.pay_bet(&GameResult::PlayerBlackjack);
-            return;
-        }        
-
-        let mut log = String::new();
-        log.push_str(&format!("*** Game {} ***\n", self.games_played + 1));
-        log.push_str(&format!("Player's hand: {} (Total: {})\n", player_hand.display(), player_hand.total()));
-        log.push_str(&format!("Dealer shows: {}\n", dealer_hand.cards[0].name()));
-
-        while player_hand.first_action {
-            let action = self.strategy.determine_first_action(&player_hand, &dealer_hand.cards[0]);
-            match action {
-                Action::DoubleDown => {
-                    player_hand.add_card(self.deck.deal_card().unwrap());
-                    log.push_str(&format!("Player doubles down: {} (Total: {})\n", player_hand.cards.last().unwrap().name(), player_hand.total()));
-                    player_hand.doubled = true;
-                    player_hand.live = false;
-                    player_hand.stood = true;
-                    if player_hand.is_busted() {
-                        log.push_str("Player busts!\n");
-                        self.last_game_result = Some(GameResult::DoubledLose);
-                        self.losses += 1;
-                    }  
-                }
-                Action::Surrender => {
-                    log.push_str("Player surrenders.\n");
-                    self.last_game_result = Some(GameResult::Surrender);
-                    self.losses += 1;
-                    player_hand.live = false;
-                }
-                Action::Split => {
-                    // For simplicity, we won't implement splitting in this version
-                    log.push_str("Player chooses to split, but splitting is not implemented. Player stands.\n");
-                    player_hand.live = false;
-                }
-                _ => {
-                    log.push_str("Player chooses to hit or stand.\n");
-                }
-            }
-            player_hand.first_action = false;
-            
-        }
-
-        while player_hand.live {
-            let action = self.strategy.determine_action(&player_hand, &dealer_hand.cards[0]);
-            match action {
-                Action::Hit => {
-                    player_hand.add_card(self.deck.deal_card().unwrap());
-                    log.push_str(&format!("Player hits: {} (Total: {})\n", player_hand.cards.last().unwrap().name(), player_hand.total()));
-                    if player_hand.is_busted() {
-                        log.push_str("Player busts!\n");
-                        self.last_game_result = Some(GameResult::DealerWin);
-                        self.losses += 1;
-                        player_hand.live = false;                                         
-                        }
-                }
-                Action::Stand => {
-                    log.push_str("Player stands.\n");
-                    player_hand.live = false;
-                    player_hand.stood = true;
-                }
-                _ => {
-                    log.push_str("Invalid action during main turn. Player stands.\n");
-                    player_hand.live = false;}
+            if app.pending_shoes > 0 {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} shoes remaining", app.pending_shoes));
+                    if ui.button("Stop").clicked() {
+                        app.pending_shoes = 0;
+                    }
+                });
             }
-        }
-        
-        while dealer_hand.total() < 17 && player_hand.stood {
-            dealer_hand.add_card(self.deck.deal_card().unwrap());
-            log.push_str(&format!("Dealer hits: {} (Total: {})\n", dealer_hand.cards.last().unwrap().name(), dealer_hand.total()));
-            if dealer_hand.is_busted() {
-                log.push_str("Dealer busts!\n");
-                self.last_game_result = Some(GameResult::PlayerWin);
-                self.wins += 1;
-                self.games_played += 1;
-                self.append_log(&log);
-                self.pay_bet(&GameResult::PlayerWin);
-                return;
+            if let Some(reason) = app.stop_reason {
+                ui.label(format!("{}.", reason.label()));
+            } else if !can_play {
+                ui.label(format!("{}.", StopReason::Bankrupt.label()));
             }
-        }
-        log.push_str("Dealer stands.\n");
-        log.push_str(&format!("Dealer's hand: {} (Total: {})\n", dealer_hand.display(), dealer_hand.total()));
-        if player_hand.stood {
-            if player_hand.total() > dealer_hand.total() {
-                log.push_str("Player wins!\n");
-                if player_hand.doubled {
-                    self.last_game_result = Some(GameResult::DoubledWin);
-                } else {
-                    self.last_game_result = Some(GameResult::PlayerWin);
-                }
-                self.wins += 1;
-            } else if player_hand.total() < dealer_hand.total() {
-                log.push_str("Dealer wins!\n");
-                if player_hand.doubled {
-                    self.last_game_result = Some(GameResult::DoubledLose);
-                } else {
-                    self.last_game_result = Some(GameResult::DealerWin);
-                }
-                self.losses += 1;
-            } else {
-                log.push_str("Push!\n");
-                self.last_game_result = Some(GameResult::Push);
-                self.pushes += 1;
+            if ui.button("Cards Seen...").clicked() {
+                app.show_cards_seen = true;
             }
-        }
-        self.games_played += 1;
-        self.pay_bet(&self.last_game_result.clone().unwrap());
-        self.append_log(&log);
-
-    }
-
-    fn append_log(&self, log: &str) {
-        let mut file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open("blackjack_log.txt")
-            .unwrap();
-        writeln!(file, "{}", log).unwrap();
-    }
-
-    fn pay_bet(&mut self, result: &GameResult) {
-        match result {
-            GameResult::PlayerWin => self.bankroll += self.bet_amount,
-            GameResult::DealerWin => self.bankroll -= self.bet_amount,
-            GameResult::Push => {},
-            GameResult::PlayerBlackjack => self.bankroll += self.bet_amount * 1.5,
-            GameResult::Surrender => self.bankroll -= self.bet_amount / 2.0,
-            GameResult::DoubledWin => self.bankroll += self.bet_amount * 2.0,
-            GameResult::DoubledLose => self.bankroll -= self.bet_amount * 2.0,
-        }
-    }
-}
-
-impl eframe::App for BlackjackApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            let can_play = self.bankroll >= self.bet_amount;
-            ui.heading("Blackjack Simulator");
-            if ui.add_enabled(can_play, egui::Button::new("Play Game")).clicked() {
-                self.play_game();
+            if ui.button("Count Calibration...").clicked() {
+                app.show_count_calibration = true;
             }
-            if ui.add_enabled(can_play, egui::Button::new("Play 1000 Games")).clicked() {
-                for _ in 0..1000 {
-                    if self.bankroll < self.bet_amount {
-                    ui.label("Insufficient bankroll to continue playing.");
-                    return;
-                }
-                    self.play_game();
-                }
+            if ui.button("Events...").clicked() {
+                app.show_events = true;
+            }
+            if ui.button("Monte Carlo...").clicked() {
+                app.show_monte_carlo = true;
+            }
+            if ui.button("Compare Rules...").clicked() {
+                app.show_comparison = true;
+            }
+            if ui.button("Check Basic Strategy...").clicked() {
+                app.show_strategy_check = true;
+            }
+            if ui.button("End Session").clicked() {
+                app.pending_games = 0;
+                app.pending_shoes = 0;
+                app.autoplay = None;
+                app.show_summary = true;
             }
             if ui.button("Reset Bankroll").clicked() {
-                self.bankroll = 1000.0;
-                self.games_played = 0;
-                self.wins = 0;
-                self.losses = 0;
-                self.pushes = 0;
-                self.last_game_result = None;
-            }
-            if let Some(result) = &self.last_game_result {
+                app.bankroll = 1000.0;
+                app.games_played = 0;
+                app.wins = 0;
+                app.losses = 0;
+                app.pushes = 0;
+                app.last_game_result = None;
+                app.starting_bankroll = app.bankroll;
+                app.total_wagered = 0.0;
+                app.largest_bet = 0.0;
+                app.progression_bet = None;
+                app.net_won = 0.0;
+                app.batch_start = None;
+                app.peak_bankroll = app.bankroll;
+                app.max_drawdown = 0.0;
+                app.ruin_count = 0;
+                app.total_topped_up = 0.0;
+                app.stop_reason = None;
+                app.action_counts = ActionCounts::default();
+                app.dealer_outcomes = DealerOutcomes::default();
+                app.streaks = StreakTracker::default();
+                app.illegal_actions = 0;
+                app.side_bet_wagered = 0.0;
+                app.side_bet_net = 0.0;
+                app.insurance_taken = 0;
+                app.insurance_wagered = 0.0;
+                app.insurance_net = 0.0;
+                app.outcome_histogram = OutcomeHistogram::default();
+                app.results_by_count = TrueCountResults::default();
+                app.bankroll_history = BankrollHistory::default();
+                app.what_ifs.clear();
+                app.game_rows.clear();
+                for seat in &mut app.other_seats {
+                    seat.reset(1000.0);
+                }
+                app.stats_saved = LifetimeStats::default();
+                app.pending_games = 0;
+                app.pending_shoes = 0;
+                app.completed_shoes = 0;
+                app.completed_shoe_hands = 0;
+                app.autoplay = None;
+                app.show_summary = false;
+                app.session_status = app.save_session().err();
+            }
+            if ui.button("Save").clicked() {
+                app.session_status = Some(match app.save_session() {
+                    Ok(()) => format!("Saved to {}", SESSION_PATH),
+                    Err(err) => format!("Save failed: {}", err),
+                });
+            }
+            ui.checkbox(&mut app.save_shoe, "Keep the shoe")
+                .on_hover_text("Save the shoe's remaining cards too, so the restored session plays on from the same card");
+            if let Some(status) = &app.session_status {
+                ui.label(status);
+            }
+            if let Some(result) = &app.last_game_result {
                 let result_str = match result {
                     GameResult::PlayerWin => "Player Wins!",
                     GameResult::DealerWin => "Dealer Wins!",
@@ -499,22 +230,865 @@ impl eframe::App for BlackjackApp {
                     GameResult::Surrender => "Player Surrendered",
                     GameResult::DoubledWin => "Player Wins with Double Down!",
                     GameResult::DoubledLose => "Player Loses with Double Down!",
+                    GameResult::Rescued => "Player Rescued the Double Down",
+                    GameResult::EvenMoney => "Player Took Even Money",
                 };
                 ui.label(format!("Last Game Result: {}", result_str));
             } else {
                 ui.label("No games played yet.");
             }
             ui.separator();
-            ui.label(format!("Bankroll: ${:.2}", self.bankroll));
-            ui.label(format!("Games Played: {}", self.games_played));
-            ui.label(format!("Wins: {}", self.wins));
-            ui.label(format!("Losses: {}", self.losses));
-            ui.label(format!("Pushes: {}", self.pushes));
+            let previous_preset = app.shoe_preset;
+            egui::ComboBox::from_label("Shoe")
+                .selected_text(app.shoe_preset.label())
+                .show_ui(ui, |ui| {
+                    for preset in ShoePreset::ALL {
+                        ui.selectable_value(&mut app.shoe_preset, preset, preset.label());
+                    }
+                });
+            let previous_shuffle = app.deck.shuffle_method;
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut app.deck.shuffle_method, ShuffleMethod::FisherYates, "Fisher-Yates");
+                let passes = match app.deck.shuffle_method {
+                    ShuffleMethod::Riffle(passes) => passes,
+                    ShuffleMethod::FisherYates => 7,
+                };
+                ui.radio_value(&mut app.deck.shuffle_method, ShuffleMethod::Riffle(passes), "Riffle");
+                if let ShuffleMethod::Riffle(passes) = &mut app.deck.shuffle_method {
+                    ui.add(egui::DragValue::new(passes).range(1..=20).suffix(" passes"));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Deal from:");
+                ui.radio_value(&mut app.deck.deal_position, DealPosition::Top, "Top");
+                ui.radio_value(&mut app.deck.deal_position, DealPosition::Bottom, "Bottom");
+                let depth = match app.deck.deal_position {
+                    DealPosition::Fixed(depth) => depth,
+                    _ => 1,
+                };
+                ui.radio_value(&mut app.deck.deal_position, DealPosition::Fixed(depth), "Fixed depth");
+                if let DealPosition::Fixed(depth) = &mut app.deck.deal_position {
+                    ui.add(egui::DragValue::new(depth).range(0..=311));
+                }
+            });
+            let mut cut_percent = app.cut_fraction * 100.0;
+            let cut_changed = ui.add(egui::DragValue::new(&mut cut_percent).range(0.0..=100.0).suffix("%").prefix("Cut at ")).changed();
+            app.cut_fraction = cut_percent / 100.0;
+            if app.shoe_preset != previous_preset || app.deck.shuffle_method != previous_shuffle || cut_changed {
+                app.reshuffle();
+            }
+            let previous_strategy = (app.strategy_kind, app.strategy_stand_total, app.soft_stand_total, app.hi_lo, app.count_system,
+                app.insurance_true_count, app.play_error_rate, app.rules.dealer_wins_ties, app.rules.double_rule, app.rules.dealer,
+                app.rules.double_after_split, (app.insure_by_tens, app.surrender_indices.clone(), app.count_lag, app.deck_estimation));
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_label("Strategy")
+                    .selected_text(app.strategy_kind.label())
+                    .show_ui(ui, |ui| {
+                        for kind in StrategyKind::ALL {
+                            ui.selectable_value(&mut app.strategy_kind, kind, kind.label());
+                        }
+                    });
+                if app.strategy_kind == StrategyKind::StandOn {
+                    ui.add(egui::DragValue::new(&mut app.strategy_stand_total).range(12..=21));
+                }
+            });
+            ui.horizontal(|ui| {
+                let basic = app.strategy_kind == StrategyKind::Basic;
+                let mut soft_stand = app.soft_stand_total.is_some();
+                ui.add_enabled(basic, egui::Checkbox::new(&mut soft_stand, "Stand on soft"));
+                match (soft_stand, &mut app.soft_stand_total) {
+                    (true, Some(soft_stand_total)) => {
+                        ui.add(egui::DragValue::new(soft_stand_total).range(13..=21));
+                    }
+                    (true, None) => app.soft_stand_total = Some(18),
+                    (false, _) => app.soft_stand_total = None,
+                }
+                ui.add_enabled(basic, egui::Checkbox::new(&mut app.hi_lo, "Count deviations"));
+                ui.add_enabled_ui(basic && app.hi_lo, |ui| {
+                    egui::ComboBox::from_id_salt("count_system")
+                        .selected_text(app.count_system.label())
+                        .show_ui(ui, |ui| {
+                            for system in CountSystem::ALL {
+                                ui.selectable_value(&mut app.count_system, system, system.label());
+                            }
+                        });
+                });
+                ui.add_enabled_ui(basic && app.hi_lo, |ui| {
+                    egui::ComboBox::from_id_salt("deck_estimation")
+                        .selected_text(app.deck_estimation.label())
+                        .show_ui(ui, |ui| {
+                            for estimation in DeckEstimation::ALL {
+                                ui.selectable_value(&mut app.deck_estimation, estimation, estimation.label());
+                            }
+                        })
+                        .response
+                        .on_hover_text("How the decks left are judged for the true count");
+                });
+                ui.add_enabled(basic && app.hi_lo, egui::DragValue::new(&mut app.count_lag).range(0..=10).prefix("Count lags ").suffix(" cards"))
+                    .on_hover_text("Each card only reaches the count this many cards after it is dealt");
+                ui.add_enabled(basic && app.hi_lo, egui::Checkbox::new(&mut app.insure_by_tens, "Insure by tens"))
+                    .on_hover_text("Insure whenever more than a third of the unseen cards are tens, by a side count");
+                ui.add_enabled(basic && app.hi_lo && !app.insure_by_tens, egui::DragValue::new(&mut app.insurance_true_count)
+                    .range(-10.0..=10.0).speed(0.1).prefix("Insure at TC "));
+                let mut insurance_percent = app.insurance_fraction * 100.0;
+                if ui.add_enabled(basic && app.hi_lo, egui::DragValue::new(&mut insurance_percent)
+                    .range(0.0..=MAX_INSURANCE_FRACTION * 100.0).speed(1.0).prefix("for ").suffix("% of the bet")).changed() {
+                    app.insurance_fraction = insurance_percent / 100.0;
+                }
+            });
+            ui.add_enabled_ui(app.strategy_kind == StrategyKind::Basic && app.hi_lo, |ui| {
+                ui.horizontal(|ui| {
+                    let mut fab_four = !app.surrender_indices.is_empty();
+                    if ui.checkbox(&mut fab_four, "Surrender indices").changed() {
+                        app.surrender_indices = if fab_four { FAB_FOUR_SURRENDERS.to_vec() } else { Vec::new() };
+                    }
+                    for index in &mut app.surrender_indices {
+                        let upcard = if index.upcard == 11 { "A".to_string() } else { index.upcard.to_string() };
+                        ui.add(egui::DragValue::new(&mut index.true_count).range(-10.0..=10.0).speed(0.1)
+                            .prefix(format!("{} v {} at TC ", index.total, upcard)));
+                    }
+                });
+            });
+            ui.checkbox(&mut app.show_basic_hint, "Log basic strategy hints where the strategy differs");
+            ui.horizontal(|ui| {
+                let mut play_errors = app.play_error_rate.is_some();
+                ui.checkbox(&mut play_errors, "Play errors");
+                match (play_errors, &mut app.play_error_rate) {
+                    (true, Some(error_rate)) => {
+                        let mut error_percent = *error_rate * 100.0;
+                        ui.add(egui::DragValue::new(&mut error_percent).range(0.0..=100.0).speed(0.1).suffix("% of decisions"));
+                        *error_rate = error_percent / 100.0;
+                    }
+                    (true, None) => app.play_error_rate = Some(0.02),
+                    (false, _) => app.play_error_rate = None,
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Other seats");
+                let mut seat_count = app.other_seats.len();
+                if ui.add(egui::DragValue::new(&mut seat_count).range(0..=6)).changed() {
+                    app.set_seat_count(seat_count);
+                }
+                for position in SeatPosition::ALL {
+                    if ui.add_enabled(seat_count > 0, egui::RadioButton::new(app.seat_position == position, position.label())).clicked() {
+                        app.seat_position = position;
+                    }
+                }
+            });
+            let table_min = app.table_min;
+            let seat_numbers: Vec<usize> = (0..app.other_seats.len()).map(|other| app.other_seat_number(other)).collect();
+            let mut seats_changed = false;
+            for (index, seat) in app.other_seats.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    let previous = (seat.strategy_kind, seat.stand_total);
+                    ui.label(format!("Seat {}", seat_numbers[index]));
+                    egui::ComboBox::from_id_salt(("seat_strategy", index))
+                        .selected_text(seat.strategy_kind.label())
+                        .show_ui(ui, |ui| {
+                            for kind in StrategyKind::ALL {
+                                ui.selectable_value(&mut seat.strategy_kind, kind, kind.label());
+                            }
+                        });
+                    if seat.strategy_kind == StrategyKind::StandOn {
+                        ui.add(egui::DragValue::new(&mut seat.stand_total).range(12..=21));
+                    }
+                    ui.add(egui::DragValue::new(&mut seat.bet).range(table_min..=100_000.0).speed(1.0).prefix("$"));
+                    ui.label(format!("${:.2}, {} / {} / {}, edge {:.2}%",
+                        seat.bankroll, seat.wins, seat.losses, seat.pushes, seat.house_edge() * 100.0));
+                    seats_changed |= (seat.strategy_kind, seat.stand_total) != previous;
+                });
+            }
+            if seats_changed {
+                app.rebuild_seat_strategies();
+            }
+            ui.checkbox(&mut app.show_units, "Show amounts in units");
+            ui.checkbox(&mut app.log_shoe_order, "Log full shoe after each shuffle");
+            ui.horizontal(|ui| {
+                ui.label("Seed");
+                ui.add(egui::DragValue::new(&mut app.seed_input));
+                if ui.button("Reseed").clicked() {
+                    app.reseed(Some(app.seed_input));
+                }
+                if ui.add_enabled(app.seed.is_some(), egui::Button::new("Unseeded")).clicked() {
+                    app.reseed(None);
+                }
+                match app.seed {
+                    Some(seed) => ui.label(format!("Playing seed {}", seed)),
+                    None => ui.label("Playing unseeded"),
+                };
+            });
+            ui.horizontal(|ui| {
+                ui.label("Log format:");
+                for format in LogFormat::ALL {
+                    ui.radio_value(&mut app.log_format, format, format.label());
+                }
+                ui.label(format!("Writing {}", app.log_format.path()));
+            });
+            ui.checkbox(&mut app.record_batch_games, format!("Keep batch games for the CSV (last {})", GAME_ROWS_KEPT));
+            ui.horizontal(|ui| {
+                ui.label("Rotate log:");
+                ui.radio_value(&mut app.log_rotation, None, "Never");
+                let hands = match app.log_rotation {
+                    Some(LogRotation::Hands(hands)) => hands,
+                    _ => 10_000,
+                };
+                ui.radio_value(&mut app.log_rotation, Some(LogRotation::Hands(hands)), "Every");
+                if let Some(LogRotation::Hands(hands)) = &mut app.log_rotation {
+                    ui.add(egui::DragValue::new(hands).range(100..=10_000_000).speed(100.0).suffix(" hands"));
+                }
+                let kilobytes = match app.log_rotation {
+                    Some(LogRotation::Kilobytes(kilobytes)) => kilobytes,
+                    _ => 10_240,
+                };
+                ui.radio_value(&mut app.log_rotation, Some(LogRotation::Kilobytes(kilobytes)), "At");
+                if let Some(LogRotation::Kilobytes(kilobytes)) = &mut app.log_rotation {
+                    ui.add(egui::DragValue::new(kilobytes).range(64..=10_485_760).speed(64.0).suffix(" KB"));
+                }
+            });
+            ui.checkbox(&mut app.exact_cents, "Exact cent accounting");
+            ui.horizontal(|ui| {
+                ui.label("Card names:");
+                ui.radio_value(&mut app.card_style, CardStyle::Unicode, "Unicode");
+                ui.radio_value(&mut app.card_style, CardStyle::Ascii, "ASCII");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Bet");
+                ui.add(egui::DragValue::new(&mut app.bet_amount).range(app.table_min..=100_000.0).speed(1.0).prefix("$"));
+                ui.label("Table minimum");
+                ui.add(egui::DragValue::new(&mut app.table_min).range(1.0..=10_000.0).speed(1.0).prefix("$"));
+                ui.label("Chip");
+                ui.add(egui::DragValue::new(&mut app.bet_increment).range(0.01..=10_000.0).speed(1.0).prefix("$"))
+                    .on_hover_text("Bankroll share, Kelly and constant risk bets are rounded down to a multiple of this");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Bet sizing:");
+                ui.radio_value(&mut app.bet_sizing, BetSizing::Flat, "Flat");
+                let fraction = match app.bet_sizing {
+                    BetSizing::FixedFraction(fraction) => fraction,
+                    _ => 0.02,
+                };
+                ui.radio_value(&mut app.bet_sizing, BetSizing::FixedFraction(fraction), "Bankroll share");
+                if let BetSizing::FixedFraction(fraction) = &mut app.bet_sizing {
+                    let mut percent = *fraction * 100.0;
+                    ui.add(egui::DragValue::new(&mut percent).range(0.1..=100.0).speed(0.1).suffix("%"));
+                    *fraction = percent / 100.0;
+                }
+                let kelly = match app.bet_sizing {
+                    BetSizing::Kelly(kelly) => kelly,
+                    _ => 0.5,
+                };
+                let counting = app.strategy.true_count().is_some();
+                if ui.add_enabled(counting, egui::RadioButton::new(app.bet_sizing == BetSizing::Kelly(kelly), "Kelly"))
+                    .on_disabled_hover_text("Needs a counting strategy")
+                    .clicked() {
+                    app.bet_sizing = BetSizing::Kelly(kelly);
+                }
+                if let BetSizing::Kelly(kelly) = &mut app.bet_sizing {
+                    let mut percent = *kelly * 100.0;
+                    ui.add(egui::DragValue::new(&mut percent).range(1.0..=100.0).suffix("% Kelly"));
+                    *kelly = percent / 100.0;
+                }
+                ui.radio_value(&mut app.bet_sizing, BetSizing::Martingale, "Martingale");
+                if app.bet_sizing == BetSizing::Martingale {
+                    egui::ComboBox::from_id_salt("progression_shortfall")
+                        .selected_text(app.progression_shortfall.label())
+                        .show_ui(ui, |ui| {
+                            for shortfall in ProgressionShortfall::ALL {
+                                ui.selectable_value(&mut app.progression_shortfall, shortfall, shortfall.label());
+                            }
+                        })
+                        .response
+                        .on_hover_text("What the progression does after a bet cut short by the cap or the bankroll");
+                    ui.checkbox(&mut app.reset_progression_on_shuffle, "Reset each shoe");
+                }
+                let risk = match app.bet_sizing {
+                    BetSizing::ConstantRisk(risk) => risk,
+                    _ => 0.1,
+                };
+                if ui.add_enabled(counting, egui::RadioButton::new(app.bet_sizing == BetSizing::ConstantRisk(risk), "Constant risk"))
+                    .on_disabled_hover_text("Needs a counting strategy")
+                    .on_hover_text("Kelly bets sized to keep the chance of ever losing half the bankroll fixed")
+                    .clicked() {
+                    app.bet_sizing = BetSizing::ConstantRisk(risk);
+                }
+                if let BetSizing::ConstantRisk(risk) = &mut app.bet_sizing {
+                    let mut percent = *risk * 100.0;
+                    ui.add(egui::DragValue::new(&mut percent).range(1.0..=50.0).suffix("% risk of halving"));
+                    *risk = percent / 100.0;
+                }
+                if ui.add_enabled(counting, egui::RadioButton::new(app.bet_sizing == BetSizing::Ramp, "Ramp"))
+                    .on_disabled_hover_text("Needs a counting strategy")
+                    .on_hover_text("A fixed bet for each true count, from the bet ramp below")
+                    .clicked() {
+                    app.bet_sizing = BetSizing::Ramp;
+                }
+            });
+            ui.add_enabled_ui(app.strategy.true_count().is_some(), |ui| {
+                ui.horizontal(|ui| {
+                    let mut percent = app.ramp_risk_of_ruin * 100.0;
+                    ui.add(egui::DragValue::new(&mut percent).range(0.1..=50.0).speed(0.1).suffix("% risk of ruin"));
+                    app.ramp_risk_of_ruin = percent / 100.0;
+                    if ui.button("Recommend ramp")
+                        .on_hover_text("Bets per true count for the current bankroll, held to this chance of going broke")
+                        .clicked() {
+                        app.bet_ramp = app.recommend_ramp(app.ramp_risk_of_ruin);
+                        app.bet_sizing = BetSizing::Ramp;
+                    }
+                    if let (Some(first), Some(last)) = (app.bet_ramp.first(), app.bet_ramp.last()) {
+                        ui.label(format!("{:.1}x spread", last.bet / first.bet));
+                    }
+                });
+                if !app.bet_ramp.is_empty() {
+                    ui.horizontal_wrapped(|ui| {
+                        for step in &mut app.bet_ramp {
+                            ui.add(egui::DragValue::new(&mut step.bet).range(app.table_min..=100_000.0).prefix(format!("TC {}: $", step.true_count)));
+                        }
+                    });
+                }
+            });
+            ui.horizontal(|ui| {
+                let mut capped = app.max_bet_fraction.is_some();
+                ui.checkbox(&mut capped, "Cap each bet at");
+                let mut percent = app.max_bet_fraction.unwrap_or(0.25) * 100.0;
+                ui.add_enabled(capped, egui::DragValue::new(&mut percent).range(1.0..=100.0).suffix("% of bankroll"));
+                app.max_bet_fraction = capped.then_some(percent / 100.0);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Short of the bet:");
+                for policy in BetShortfallPolicy::ALL {
+                    ui.radio_value(&mut app.bet_shortfall, policy, policy.label());
+                }
+            });
+            ui.add_enabled_ui(app.strategy.true_count().is_some(), |ui| {
+                ui.horizontal(|ui| {
+                    let mut extra_spots = app.spot_thresholds.len();
+                    ui.add(egui::DragValue::new(&mut extra_spots).range(0..=MAX_EXTRA_SPOTS).suffix(" extra spots"))
+                        .on_disabled_hover_text("Needs a counting strategy");
+                    while app.spot_thresholds.len() < extra_spots {
+                        app.spot_thresholds.push(app.spot_thresholds.last().map_or(2.0, |threshold| threshold + 2.0));
+                    }
+                    app.spot_thresholds.truncate(extra_spots);
+                    for threshold in &mut app.spot_thresholds {
+                        ui.add(egui::DragValue::new(threshold).range(-10.0..=10.0).speed(0.1).prefix("from TC "));
+                    }
+                });
+            });
+            ui.checkbox(&mut app.auto_reset_on_ruin, "Auto-reset bankroll on bankruptcy");
+            ui.horizontal(|ui| {
+                optional_amount(ui, "Top up on bankruptcy", &mut app.top_up_on_ruin, app.starting_bankroll / 2.0);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Milestone at");
+                ui.add(egui::DragValue::new(&mut app.streak_milestone).range(0..=50).suffix(" wins in a row"));
+            });
+            let limits_changed = ui.horizontal(|ui| {
+                optional_amount(ui, "Cash out at", &mut app.cash_out_target, app.starting_bankroll * 2.0)
+                    | optional_amount(ui, "Stop at loss limit", &mut app.loss_limit, app.starting_bankroll / 2.0)
+            }).inner | ui.horizontal(|ui| {
+                optional_amount(ui, "Trailing stop", &mut app.trailing_stop, app.starting_bankroll / 10.0)
+            }).inner;
+            if limits_changed && app.stop_reason != Some(StopReason::Bankrupt) {
+                app.stop_reason = None;
+            }
+            egui::ComboBox::from_label("Rule preset")
+                .selected_text("Apply...")
+                .show_ui(ui, |ui| {
+                    for preset in RulePreset::ALL {
+                        if ui.selectable_label(false, preset.label()).clicked() {
+                            app.rules = preset.rules();
+                        }
+                    }
+                });
+            ui.horizontal(|ui| {
+                ui.label("Dealer stands on:");
+                ui.add(egui::DragValue::new(&mut app.rules.dealer.stand_total).range(12..=21));
+                let hit_soft_label = format!("Hit soft {}", app.rules.dealer.stand_total);
+                ui.checkbox(&mut app.rules.dealer.hit_soft, hit_soft_label);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Decks:");
+                ui.add(egui::DragValue::new(&mut app.rules.decks).range(1..=8));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Penetration:");
+                let fraction = match app.rules.penetration {
+                    Penetration::Fraction(fraction) => fraction,
+                    Penetration::CardsCutOff(_) => 0.75,
+                };
+                ui.radio_value(&mut app.rules.penetration, Penetration::Fraction(fraction), "Dealt");
+                if let Penetration::Fraction(fraction) = &mut app.rules.penetration {
+                    let mut percent = *fraction * 100.0;
+                    ui.add(egui::DragValue::new(&mut percent).range(50.0..=100.0).suffix("%"));
+                    *fraction = percent / 100.0;
+                }
+                let cards = match app.rules.penetration {
+                    Penetration::CardsCutOff(cards) => cards,
+                    Penetration::Fraction(_) => 78,
+                };
+                ui.radio_value(&mut app.rules.penetration, Penetration::CardsCutOff(cards), "Cut off");
+                if let Penetration::CardsCutOff(cards) = &mut app.rules.penetration {
+                    let mut decks = *cards as f64 / 52.0;
+                    ui.add(egui::DragValue::new(&mut decks).range(0.0..=3.0).speed(0.05).max_decimals(2).suffix(" decks"));
+                    *cards = (decks * 52.0).round() as usize;
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Deal order:");
+                ui.radio_value(&mut app.rules.deal_order, DealOrder::Alternating, "P-D-P-D");
+                ui.radio_value(&mut app.rules.deal_order, DealOrder::PlayerFirst, "P-P-D-D");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Split hands:");
+                ui.radio_value(&mut app.rules.split_play_order, SplitPlayOrder::HandByHand, "Play each in turn");
+                ui.radio_value(&mut app.rules.split_play_order, SplitPlayOrder::DealAcross, "Deal across first");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Split up to:");
+                ui.add(egui::DragValue::new(&mut app.rules.max_pair_splits).range(0..=7).suffix(" times"));
+                ui.add(egui::DragValue::new(&mut app.rules.max_ace_splits).range(0..=3).prefix("aces "));
+                ui.checkbox(&mut app.rules.double_after_split, "Double after split");
+            });
+            if app.rules.decks != previous_decks {
+                app.reshuffle();
+            }
+            egui::ComboBox::from_label("Doubling")
+                .selected_text(app.rules.double_rule.label())
+                .show_ui(ui, |ui| {
+                    for rule in DoubleRule::ALL {
+                        ui.selectable_value(&mut app.rules.double_rule, rule, rule.label());
+                    }
+                });
+            egui::ComboBox::from_label("Surrender")
+                .selected_text(app.rules.surrender.label())
+                .show_ui(ui, |ui| {
+                    for rule in SurrenderRule::ALL {
+                        ui.selectable_value(&mut app.rules.surrender, rule, rule.label());
+                    }
+                });
+            ui.horizontal(|ui| {
+                ui.label("Dealer peeks under:");
+                ui.checkbox(&mut app.rules.peek_on_ace, "Ace");
+                ui.checkbox(&mut app.rules.peek_on_ten, "Ten");
+            });
+            ui.add_enabled(!(app.rules.peek_on_ace && app.rules.peek_on_ten),
+                egui::Checkbox::new(&mut app.rules.original_bets_only, "Original bets only"));
+            ui.checkbox(&mut app.rules.dealer_push_on_22, "Dealer 22 pushes");
+            ui.checkbox(&mut app.rules.dealer_plays_out, "Dealer plays out after player bust");
+            ui.checkbox(&mut app.rules.dealer_exposes_both, "Dealer exposes both cards");
+            ui.checkbox(&mut app.rules.dealer_wins_ties, "Dealer wins ties");
+            egui::ComboBox::from_label("Blackjack pays")
+                .selected_text(app.rules.blackjack_payout.label())
+                .show_ui(ui, |ui| {
+                    for payout in BlackjackPayout::ALL {
+                        ui.selectable_value(&mut app.rules.blackjack_payout, payout, payout.label());
+                    }
+                });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut app.rules.double_rescue, "Double down rescue");
+                ui.add_enabled(app.rules.double_rescue,
+                    egui::DragValue::new(&mut app.rules.double_rescue_loss).range(0.0..=2.0).speed(0.1).suffix(" bets lost"));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Side bets:");
+                for (side_bet, enabled) in &mut app.side_bets {
+                    ui.checkbox(enabled, side_bet.name());
+                }
+                ui.add(egui::DragValue::new(&mut app.side_bet_amount).range(1.0..=100.0).prefix("$").suffix(" each"));
+            });
+            if (app.strategy_kind, app.strategy_stand_total, app.soft_stand_total, app.hi_lo, app.count_system,
+                app.insurance_true_count, app.play_error_rate, app.rules.dealer_wins_ties, app.rules.double_rule, app.rules.dealer,
+                app.rules.double_after_split, (app.insure_by_tens, app.surrender_indices.clone(), app.count_lag, app.deck_estimation)) != previous_strategy {
+                app.rebuild_strategy();
+                app.reference_strategy.ties_lose = app.rules.dealer_wins_ties;
+                app.reference_strategy.double_after_split = app.rules.double_after_split;
+            }
+            ui.label(format!("Rules: {}", app.rules.fingerprint()));
+            ui.label(format!("Bankroll: {}", app.format_amount(app.bankroll)));
+            if app.total_wagered > 0.0 {
+                ui.label(format!("House edge: {:.2}% of {} wagered, EV {} per 100 hands",
+                    app.house_edge() * 100.0, app.format_amount(app.total_wagered), app.format_amount(app.ev_per_100())));
+            }
+            ui.horizontal(|ui| {
+                let mut windowed = app.chart_window.is_some();
+                ui.checkbox(&mut windowed, "Chart last");
+                let mut hands = app.chart_window.unwrap_or(500);
+                ui.add_enabled(windowed, egui::Slider::new(&mut hands, 10..=MAX_CHART_WINDOW).logarithmic(true).suffix(" hands"));
+                app.chart_window = windowed.then_some(hands);
+            });
+            ui.horizontal(|ui| {
+                ui.label("What if:");
+                for kind in StrategyKind::ALL {
+                    if ui.add_enabled(app.what_ifs.len() < MAX_WHAT_IFS, egui::Button::new(kind.label()))
+                        .on_hover_text("Chart this strategy playing the same shoes from here on")
+                        .clicked() {
+                        app.add_what_if(kind);
+                    }
+                }
+                if ui.add_enabled(!app.what_ifs.is_empty(), egui::Button::new("Clear")).clicked() {
+                    app.what_ifs.clear();
+                }
+            });
+            if app.bankroll_history.points().len() > 1 {
+                let curve = |history: &BankrollHistory| match app.chart_window {
+                    Some(hands) => history.last(hands),
+                    None => history.points().to_vec(),
+                };
+                egui_plot::Plot::new("bankroll_history")
+                    .height(120.0)
+                    .show(ui, |plot_ui| {
+                        plot_ui.line(egui_plot::Line::new("Bankroll", curve(&app.bankroll_history)));
+                        for (number, what_if) in app.what_ifs.iter().enumerate() {
+                            let name = format!("What if {}: {}", number + 1, what_if.strategy.name());
+                            plot_ui.line(egui_plot::Line::new(name, curve(&what_if.bankroll_history)));
+                        }
+                    });
+            }
+            if let (Some(running), Some(true_count)) = (app.strategy.running_count(), app.strategy.true_count()) {
+                ui.label(format!("{} count: running {:+}, true {:+.1}", app.count_system.label(), running, true_count));
+            }
+            ui.horizontal(|ui| {
+                ui.label("Compare counts:");
+                for system in CountSystem::ALL {
+                    let mut observed = app.count_observers.iter().any(|observer| observer.system == system);
+                    if ui.checkbox(&mut observed, system.label()).changed() {
+                        if observed {
+                            app.add_count_observer(system);
+                        } else {
+                            app.count_observers.retain(|observer| observer.system != system);
+                        }
+                    }
+                }
+            });
+            for observer in &app.count_observers {
+                ui.label(format!("{} count: running {:+}, true {:+.1}", observer.system.label(), observer.running_count, observer.true_count()));
+            }
+            let shoe_cards = app.deck.cards.len() + app.deck.dealt.len();
+            ui.add(egui::ProgressBar::new(app.shoe_progress()).text(format!("{} cards left, reshuffle below {}",
+                app.deck.cards.len(), app.reshuffle_below(shoe_cards))));
+            let illegal_rate = app.illegal_actions as f64 / app.action_counts.total().max(1) as f64;
+            if illegal_rate > ILLEGAL_ACTION_WARNING {
+                ui.colored_label(egui::Color32::YELLOW, format!("Warning: {} used {} illegal actions ({:.1}% of decisions)",
+                    app.strategy.name(), app.illegal_actions, illegal_rate * 100.0));
+            }
+            ui.checkbox(&mut app.show_detailed_stats, "Detailed statistics");
+            if app.show_detailed_stats {
+                egui::CollapsingHeader::new("Statistics").default_open(true).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut app.hands_per_hour).range(1..=500).suffix(" hands/hour"));
+                        ui.add(egui::DragValue::new(&mut app.session_hours).range(0.5..=24.0).speed(0.5).suffix(" hour session"));
+                    });
+                    egui::Grid::new("detailed_stats").striped(true).show(ui, |ui| {
+                        let summary = app.build_summary();
+                        stat_row(ui, "Games Played", summary.hands.to_string());
+                        stat_row(ui, "Wins", summary.wins.to_string());
+                        stat_row(ui, "Losses", summary.losses.to_string());
+                        stat_row(ui, "Pushes", summary.pushes.to_string());
+                        stat_row(ui, "Net Result", app.format_amount(summary.net_result));
+                        stat_row(ui, "Main Game Net", app.format_amount(summary.main_net));
+                        stat_row(ui, "House Edge", format!("{:.2}%", summary.house_edge * 100.0));
+                        stat_row(ui, "EV per 100 Hands", app.format_amount(summary.ev_per_100));
+                        stat_row(ui, "Peak Bankroll", app.format_amount(summary.peak_bankroll));
+                        stat_row(ui, "Max Drawdown", app.format_amount(summary.max_drawdown));
+                        stat_row(ui, "Bankruptcies", summary.ruin_count.to_string());
+                        if summary.total_topped_up > 0.0 {
+                            stat_row(ui, "Total Topped Up", app.format_amount(summary.total_topped_up));
+                        }
+                        stat_row(ui, "Longest Win Streak", summary.longest_win_streak.to_string());
+                        stat_row(ui, "Longest Loss Streak", summary.longest_loss_streak.to_string());
+                        if let Some(per_shoe) = summary.hands_per_shoe {
+                            stat_row(ui, "Shoes Completed", summary.shoes_completed.to_string());
+                            stat_row(ui, "Hands per Shoe", format!("{:.1}", per_shoe));
+                        }
+                        stat_row(ui, "Illegal Actions", summary.illegal_actions.to_string());
+                        stat_row(ui, "Side Bets Wagered", app.format_amount(summary.side_bet_wagered));
+                        stat_row(ui, "Side Bet Net", app.format_amount(summary.side_bet_net));
+                        if summary.insurance_taken > 0 {
+                            stat_row(ui, "Insurance (taken / net)", format!("{} / {}", summary.insurance_taken, app.format_amount(summary.insurance_net)));
+                        }
+                        stat_row(ui, "Actions", summary.action_counts.distribution());
+                        stat_row(ui, "Dealer Outcomes", summary.dealer_outcomes.distribution());
+                        stat_row(ui, "Outcomes (bets)", app.outcome_histogram.describe());
+                        if let Some(theoretical) = summary.theoretical_loss {
+                            stat_row(ui, "Theoretical Loss", app.format_amount(theoretical));
+                            stat_row(ui, "Actual Loss", app.format_amount(-summary.main_net));
+                            stat_row(ui, "Delta", app.format_amount(-summary.net_result - theoretical));
+                        }
+                        stat_row(ui, "Expected Loss / Hour", app.format_amount(summary.hourly_loss));
+                        stat_row(ui, "Expected Loss / Session", app.format_amount(summary.hourly_loss * summary.session_hours));
+                        if let Some(big_bets) = &summary.big_bets {
+                            stat_row(ui, "Net (average / big bets)", format!("{:+.2} / {:+.2}", big_bets.net_average_bets, big_bets.net_big_bets));
+                            stat_row(ui, "SD / 100 Hands", format!("{:.2} bets", big_bets.sd_per_100));
+                            stat_row(ui, "DI / SCORE", format!("{:.2} / {:.2}", big_bets.desirability_index, big_bets.score()));
+                            if let Some(n0) = big_bets.n0 {
+                                stat_row(ui, "N0", format!("{:.0} hands", n0));
+                            }
+                        }
+                    });
+                    if ui.button("Export Outcome Histogram").clicked() {
+                        app.export_status = Some(match std::fs::write(OUTCOME_HISTOGRAM_PATH, app.outcome_histogram.to_csv(&app.rules.fingerprint())) {
+                            Ok(()) => format!("Exported to {}", OUTCOME_HISTOGRAM_PATH),
+                            Err(err) => format!("Export failed: {}", err),
+                        });
+                    }
+                    if !app.results_by_count.is_empty() && ui.button("Export Results by True Count").clicked() {
+                        app.export_status = Some(match std::fs::write(RESULTS_BY_COUNT_PATH, app.results_by_count.to_csv(&app.rules.fingerprint(), |true_count| app.theoretical_edge(true_count))) {
+                            Ok(()) => format!("Exported to {}", RESULTS_BY_COUNT_PATH),
+                            Err(err) => format!("Export failed: {}", err),
+                        });
+                    }
+                    if ui.button("Export CSV").clicked() {
+                        app.export_status = Some(match std::fs::write(GAMES_CSV_PATH, app.game_rows_csv()) {
+                            Ok(()) => format!("Exported {} games to {}", app.game_rows.len(), GAMES_CSV_PATH),
+                            Err(err) => format!("Export failed: {}", err),
+                        });
+                    }
+                    if ui.button("Save to Strategy Stats").clicked() {
+                        app.export_status = Some(match app.save_strategy_stats() {
+                            Ok(lifetime) => {
+                                app.lifetime_stats = Some(lifetime);
+                                format!("Saved to {}", strategy_stats_path(app.strategy.name()).display())
+                            }
+                            Err(err) => format!("Save failed: {}", err),
+                        });
+                    }
+                    if let Some(status) = &app.export_status {
+                        ui.label(status);
+                    }
+                    if let Some(lifetime) = &app.lifetime_stats {
+                        ui.label(format!("Lifetime at these rules: {} hands, net {}, house edge {:.2}%",
+                            lifetime.hands, app.format_amount(lifetime.net_won), lifetime.house_edge() * 100.0));
+                    }
+                });
+            }
+            #[cfg(feature = "profiling")]
+            if let Some(profile) = app.last_profile() {
+                ui.label(format!("Last Batch: {}", profile));
+            }
         });
+
+        let summary = app.build_summary();
+        egui::Window::new("Session Summary")
+            .open(&mut app.show_summary)
+            .show(ctx, |ui| {
+                ui.label(summary.to_string());
+            });
+
+        egui::Window::new("Cards Seen This Shoe")
+            .open(&mut app.show_cards_seen)
+            .show(ctx, |ui| {
+                ui.label(format!("{} dealt, {} remaining", app.deck.dealt.len(), app.deck.cards.len()));
+                let depths = app.deck.slug_depths(0..SLUG_SIZE)
+                    .iter()
+                    .map(|depth| depth.to_string())
+                    .collect::<Vec<_>>();
+                ui.label(format!("First {} unshuffled cards landed at depths: {}", SLUG_SIZE, depths.join(", ")));
+                egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    let names = app.deck.dealt.iter().map(|c| c.format(app.card_style)).collect::<Vec<_>>();
+                    ui.label(names.join(" "));
+                });
+            });
+
+        let mut show_count_calibration = app.show_count_calibration;
+        egui::Window::new("Count Calibration")
+            .open(&mut show_count_calibration)
+            .show(ctx, |ui| {
+                if ui.button("Count Through This Shoe").clicked() {
+                    app.count_calibration = app.run_count_calibration();
+                }
+                ui.label(format!("{} true count = running count / (cards remaining / 52), never dividing by less than half a deck",
+                    app.count_system.label()));
+                let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                egui::ScrollArea::vertical().max_height(300.0).show_rows(ui, row_height, app.count_calibration.len(), |ui, rows| {
+                    for index in rows {
+                        let step = &app.count_calibration[index];
+                        ui.label(format!("{:>4}. {:<4} running {:+}, {} left, true {:+.2}",
+                            index + 1, step.card.format(app.card_style), step.running_count, step.cards_remaining, step.true_count));
+                    }
+                });
+            });
+        app.show_count_calibration = show_count_calibration;
+
+        egui::Window::new("Events")
+            .open(&mut app.show_events)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().max_height(200.0).stick_to_bottom(true).show(ui, |ui| {
+                    ui.label(app.event_log.join("\n"));
+                });
+            });
+
+        let mut show_monte_carlo = app.show_monte_carlo;
+        egui::Window::new("Monte Carlo")
+            .open(&mut show_monte_carlo)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Sessions:");
+                    ui.add(egui::DragValue::new(&mut app.monte_carlo_sessions).range(1..=10_000));
+                    ui.label("Hands each:");
+                    ui.add(egui::DragValue::new(&mut app.monte_carlo_hands).range(1..=100_000));
+                    ui.label("Threads:");
+                    ui.add(egui::DragValue::new(&mut app.monte_carlo_threads).range(1..=256));
+                });
+                if ui.button("Run").clicked() {
+                    app.monte_carlo = Some(app.run_monte_carlo(app.monte_carlo_sessions, app.monte_carlo_hands));
+                }
+                if let Some(result) = &app.monte_carlo {
+                    ui.label(format!("Mean: ${:.2}  Median: ${:.2}", result.mean(), result.median()));
+                    ui.label(format!("5th / 95th percentile: ${:.2} / ${:.2}", result.percentile(5.0), result.percentile(95.0)));
+                    ui.label(format!("Probability ahead: {:.1}%", result.probability_ahead() * 100.0));
+                    if result.goals_reached > 0 {
+                        ui.label(format!("Reached cash-out target: {:.1}%", result.probability_goal_reached() * 100.0));
+                    }
+                    if result.loss_limits_hit > 0 {
+                        ui.label(format!("Hit loss limit: {:.1}%", result.probability_loss_limit() * 100.0));
+                    }
+                    if result.trailing_stops_hit > 0 {
+                        ui.label(format!("Hit trailing stop: {:.1}%", result.probability_trailing_stop() * 100.0));
+                    }
+                    let bars = result.histogram(20)
+                        .into_iter()
+                        .map(|(centre, width, count)| egui_plot::Bar::new(centre, count as f64).width(width))
+                        .collect();
+                    egui_plot::Plot::new("monte_carlo_histogram")
+                        .height(150.0)
+                        .show(ui, |plot_ui| plot_ui.bar_chart(egui_plot::BarChart::new("Final bankroll", bars)));
+                }
+            });
+        app.show_monte_carlo = show_monte_carlo;
+
+        let mut show_comparison = app.show_comparison;
+        egui::Window::new("Compare Rules")
+            .open(&mut show_comparison)
+            .show(ctx, |ui| {
+                let mut removed = None;
+                for (index, rules) in app.comparison_rules.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(rules.fingerprint());
+                        if ui.small_button("Remove").clicked() {
+                            removed = Some(index);
+                        }
+                    });
+                }
+                if let Some(index) = removed {
+                    app.comparison_rules.remove(index);
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Add Current Rules").clicked() {
+                        app.comparison_rules.push(app.rules.clone());
+                    }
+                    ui.label("Hands each:");
+                    ui.add(egui::DragValue::new(&mut app.comparison_hands).range(100..=1_000_000).speed(100.0));
+                });
+                if ui.add_enabled(!app.comparison_rules.is_empty(), egui::Button::new("Run")).clicked() {
+                    app.comparison = app.run_rule_comparison();
+                }
+                if !app.comparison.is_empty() {
+                    egui::Grid::new("rule_comparison").striped(true).show(ui, |ui| {
+                        ui.label("Rules");
+                        ui.label("Hands");
+                        ui.label("Net");
+                        ui.label("House Edge");
+                        ui.label("Expected");
+                        ui.end_row();
+                        for row in &app.comparison {
+                            ui.label(&row.fingerprint);
+                            ui.label(row.hands.to_string());
+                            ui.label(app.format_amount(row.net_won));
+                            ui.label(format!("{:.2}%", row.house_edge() * 100.0));
+                            ui.label(row.approximate_edge.map_or("unknown".to_string(), |edge| format!("{:.2}%", edge * 100.0)));
+                            ui.end_row();
+                        }
+                    });
+                }
+            });
+        app.show_comparison = show_comparison;
+
+        let mut show_strategy_check = app.show_strategy_check;
+        egui::Window::new("Basic Strategy Check")
+            .open(&mut show_strategy_check)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Generate for Current Rules").clicked() {
+                        app.strategy_check = generate_strategy_table(&app.rules, app.evaluator());
+                    }
+                    if ui.button("Export Active Strategy Chart").clicked() {
+                        let csv = strategy_chart_csv(app.strategy.as_ref(), &app.rules);
+                        app.chart_export_status = Some(match std::fs::write(STRATEGY_CHART_PATH, csv) {
+                            Ok(()) => format!("Exported to {}", STRATEGY_CHART_PATH),
+                            Err(err) => format!("Export failed: {}", err),
+                        });
+                    }
+                });
+                if let Some(status) = &app.chart_export_status {
+                    ui.label(status);
+                }
+                if app.strategy_check.is_empty() {
+                    return;
+                }
+                ui.label("Best first decision by EV (infinite deck, no splits); red cells differ from the chart");
+                egui::Grid::new("strategy_check").striped(true).show(ui, |ui| {
+                    ui.label("");
+                    for upcard in 2..=11 {
+                        ui.label(if upcard == 11 { "A".to_string() } else { upcard.to_string() });
+                    }
+                    ui.end_row();
+                    let first_decisions: Vec<_> = app.strategy_check.iter().filter(|cell| cell.first_decision).collect();
+                    let rows = first_decisions.len() / 10;
+                    for row in 0..rows {
+                        ui.label(&first_decisions[row].row);
+                        for upcard in 0..10 {
+                            let cell = first_decisions[upcard * rows + row];
+                            if cell.disagrees() {
+                                ui.colored_label(egui::Color32::RED, cell.best.code());
+                            } else {
+                                ui.label(cell.best.code());
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+                let disagreements: Vec<_> = app.strategy_check.iter().filter(|cell| cell.disagrees()).collect();
+                ui.label(format!("{} cells where the chart gives up EV", disagreements.len()));
+                egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    for cell in disagreements {
+                        ui.label(format!("{} vs {}{}: chart {} ({:+.3}), best {} ({:+.3})",
+                            cell.row, if cell.upcard == 11 { "A".to_string() } else { cell.upcard.to_string() },
+                            if cell.first_decision { "" } else { " after a hit" },
+                            cell.chart.code(), cell.chart_ev, cell.best.code(), cell.best_ev));
+                    }
+                });
+            });
+        app.show_strategy_check = show_strategy_check;
+    }
+}
+
+// Plays a command-line batch and prints the results instead of opening the window
+fn simulate(args: &SimulationArgs) {
+    let mut app = BlackjackApp::default();
+    app.run_headless(args);
+    println!("{}", app.build_summary());
+    println!("Final Bankroll: ${:.2}", app.bankroll);
+    println!("Win Rate: {:.2}%", app.win_rate() * 100.0);
+    println!("Total Wagered: ${:.2}", app.total_wagered);
+    #[cfg(feature = "profiling")]
+    if let Some(profile) = app.last_profile() {
+        println!("Throughput: {}", profile);
     }
 }
 
 fn main() -> Result<(), eframe::Error> {
+    match SimulationArgs::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => {
+            simulate(&args);
+            return Ok(());
+        }
+        Ok(None) => {}
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("Usage: countertoolbox [--simulate HANDS [--decks DECKS] [--bet AMOUNT] [--seed SEED]]");
+            std::process::exit(2);
+        }
+    }
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([400.0, 300.0]),
         ..Default::default()
@@ -523,6 +1097,6 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "Blackjack Simulator",
         options,
-        Box::new(|_cc| Ok(Box::<BlackjackApp>::default())),
+        Box::new(|_cc| Ok(Box::<SimulatorApp>::default())),
     )
 }
\ No newline at end of file